@@ -0,0 +1,55 @@
+//! Measures the compilation of a restricted DD on a fixed set of generated instances,
+//! which is where the states and the paths of the nodes are allocated in arenas. The
+//! instances are generated and the DDs compiled in the process of the benchmark, so that
+//! only the compilation is timed. The same benchmark can be run on two commits to
+//! compare them:
+//!
+//!     cargo bench --bench restricted_dd
+
+use std::{hint::black_box, time::{Duration, Instant}};
+
+use ddo::NoCutoff;
+use psp::generate::PspGenerator;
+use psp::resolution::{restricted_dd, Psp, PspRanking};
+
+/// The seeds of the generated instances
+const SEEDS: [u32; 5] = [1, 2, 3, 4, 5];
+/// The number of items and of periods of the generated instances
+const SIZE: (usize, usize) = (20, 500);
+/// The widths of the restricted DDs
+const WIDTHS: [usize; 2] = [100, 1000];
+/// The number of runs averaged for each instance and width
+const RUNS: u32 = 3;
+
+/// Generates the instance of the given seed
+fn instance(seed: u32) -> Psp {
+    let (seed, n, p) = (seed.to_string(), SIZE.0.to_string(), SIZE.1.to_string());
+    let args = ["generate", "-s", seed.as_str(), "-n", n.as_str(), "-p", p.as_str()].map(String::from);
+    let mut generator = PspGenerator::from_args(args).expect("the options of the generator are parsed");
+    let instance = generator.instance().expect("the options of the generator are valid");
+    Psp::try_from(instance).expect("the generated instances are feasible")
+}
+
+/// Returns the time taken to compile the restricted DD of the given width
+fn compile(problem: &Psp, width: usize) -> Duration {
+    let start = Instant::now();
+    let restriction = restricted_dd(problem, &PspRanking, width, &NoCutoff);
+    let elapsed = start.elapsed();
+    black_box(restriction.best);
+    elapsed
+}
+
+fn main() {
+    let problems = SEEDS.map(instance);
+
+    println!("seed,width,mean_time");
+    for width in WIDTHS {
+        let mut total = Duration::ZERO;
+        for (seed, problem) in SEEDS.iter().zip(problems.iter()) {
+            let elapsed = (0..RUNS).map(|_| compile(problem, width)).sum::<Duration>() / RUNS;
+            println!("{seed},{width},{:.3}", elapsed.as_secs_f64());
+            total += elapsed;
+        }
+        println!("total,{width},{:.3}", total.as_secs_f64());
+    }
+}
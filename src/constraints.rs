@@ -0,0 +1,32 @@
+//! This module defines the side constraints of a PSP instance: the ad-hoc restrictions of
+//! a plant, such as maintenance periods or cleaning schedules, which are given in a file
+//! of their own rather than in the instance.
+
+use serde::{Serialize, Deserialize};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SideConstraints {
+    /// The items that may not be produced at some periods
+    #[serde(default)]
+    pub productions: Vec<BannedProduction>,
+    /// The items that may not directly follow some items produced at some periods
+    #[serde(default)]
+    pub transitions: Vec<BannedTransition>,
+}
+
+/// The item may not be produced at the period
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct BannedProduction {
+    pub item: usize,
+    pub period: usize,
+}
+
+/// The production of an item at the period may not be directly followed by the
+/// production of the other item, even through idle periods when the setup is kept
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct BannedTransition {
+    pub from: usize,
+    pub to: usize,
+    /// The period at which the first item is produced
+    pub period: usize,
+}
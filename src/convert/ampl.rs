@@ -0,0 +1,146 @@
+//! This module writes an instance as an AMPL data file, for a model that declares
+//!
+//! ```ampl
+//! set ITEMS;
+//! set PERIODS ordered;
+//! param stocking {ITEMS} >= 0;
+//! param changeover {ITEMS, ITEMS} >= 0;
+//! param demand {ITEMS, PERIODS} integer >= 0;
+//! param max_stock {ITEMS} integer >= 0 default Infinity;
+//! param storage_capacity integer >= 0 default Infinity;
+//! param keep_setup binary default 1;
+//! param overtime_cost >= 0 default Infinity;
+//! param idle_cost >= 0 default 0;
+//! param second_order {ITEMS, ITEMS, ITEMS} >= 0 default 0;
+//! param earliest {i in ITEMS, t in PERIODS} integer >= 0 default t;
+//! set FORBIDDEN within {ITEMS, ITEMS} default {};
+//! set PRECEDENCES within {ITEMS, PERIODS, ITEMS, PERIODS} default {};
+//! set DOWN within PERIODS default {};
+//! ```
+//!
+//! The maximum stocks and the storage capacity are only written for the instances that
+//! bound them, `keep_setup` for those where the setup is lost during idle periods,
+//! `overtime_cost`, the surcharge of a second unit produced at a period, for those that
+//! allow overtime, `idle_cost` for those that penalize the idle periods, the nonzero
+//! `second_order` changeovers (a, b, c), from b to c after a, for those that give them,
+//! and `earliest` for those whose demands have service windows. `FORBIDDEN` holds the pairs
+//! (i, j) such that j may not directly follow i, and `PRECEDENCES` the demands (i, s) that
+//! must be produced before the demands (j, t). `DOWN` holds the periods where the
+//! machine is down and nothing may be produced.
+//! The items and the periods are numbered from 0, as in the solution files, and the costs
+//! are written in the units of the instance.
+
+use std::fmt::Write;
+
+use crate::instance::{IdleSetup, PspInstance};
+
+pub fn to_ampl(instance: &PspInstance) -> String {
+    let (n, h) = (instance.nb_types, instance.nb_periods);
+    let items = (0..n).map(|i| i.to_string()).collect::<Vec<String>>().join(" ");
+    let periods = (0..h).map(|t| t.to_string()).collect::<Vec<String>>().join(" ");
+    let mut out = String::new();
+
+    writeln!(out, "# PSP instance with {n} items and {h} periods").unwrap();
+    writeln!(out, "set ITEMS := {items};").unwrap();
+    writeln!(out, "set PERIODS := {periods};").unwrap();
+    writeln!(out).unwrap();
+
+    writeln!(out, "param stocking :=").unwrap();
+    for (i, cost) in instance.stocking.iter().enumerate() {
+        writeln!(out, "  {i} {cost}").unwrap();
+    }
+    writeln!(out, ";").unwrap();
+    writeln!(out).unwrap();
+
+    writeln!(out, "param changeover: {items} :=").unwrap();
+    for (i, row) in instance.changeover.iter().enumerate() {
+        let costs = row.iter().map(|c| c.to_string()).collect::<Vec<String>>().join(" ");
+        writeln!(out, "  {i} {costs}").unwrap();
+    }
+    writeln!(out, ";").unwrap();
+    writeln!(out).unwrap();
+
+    if let Some(costs) = instance.second_order_changeover.as_ref() {
+        writeln!(out, "param second_order :=").unwrap();
+        for (a, rows) in costs.iter().enumerate() {
+            for (b, row) in rows.iter().enumerate() {
+                for (c, cost) in row.iter().enumerate().filter(|(_, cost)| **cost != 0.0) {
+                    writeln!(out, "  {a} {b} {c} {cost}").unwrap();
+                }
+            }
+        }
+        writeln!(out, ";").unwrap();
+        writeln!(out).unwrap();
+    }
+
+    writeln!(out, "param demand: {periods} :=").unwrap();
+    for (i, row) in instance.demand_table().iter().enumerate() {
+        let demands = row.iter().map(|d| d.to_string()).collect::<Vec<String>>().join(" ");
+        writeln!(out, "  {i} {demands}").unwrap();
+    }
+    writeln!(out, ";").unwrap();
+
+    if let Some(max_stock) = instance.max_stock.as_ref() {
+        writeln!(out).unwrap();
+        writeln!(out, "param max_stock :=").unwrap();
+        for (i, cap) in max_stock.iter().enumerate() {
+            writeln!(out, "  {i} {cap}").unwrap();
+        }
+        writeln!(out, ";").unwrap();
+    }
+
+    if let Some(capacity) = instance.storage_capacity {
+        writeln!(out).unwrap();
+        writeln!(out, "param storage_capacity := {capacity};").unwrap();
+    }
+
+    let lists = instance.demand_lists();
+    if lists.iter().flatten().any(|d| d.earliest.is_some()) {
+        writeln!(out).unwrap();
+        writeln!(out, "param earliest: {periods} :=").unwrap();
+        for (i, demands) in lists.iter().enumerate() {
+            let mut row = (0..h).collect::<Vec<usize>>();
+            demands.iter().for_each(|d| row[d.period] = d.earliest.unwrap_or(d.period));
+            let periods = row.iter().map(|e| e.to_string()).collect::<Vec<String>>().join(" ");
+            writeln!(out, "  {i} {periods}").unwrap();
+        }
+        writeln!(out, ";").unwrap();
+    }
+
+    if let Some(transitions) = instance.forbidden_transitions.as_ref() {
+        let pairs = transitions.iter().map(|(a, b)| format!("({a},{b})")).collect::<Vec<String>>().join(" ");
+        writeln!(out).unwrap();
+        writeln!(out, "set FORBIDDEN := {pairs};").unwrap();
+    }
+
+    if let Some(precedences) = instance.precedences.as_ref() {
+        let tuples = precedences.iter()
+            .map(|p| format!("({},{},{},{})", p.before.item, p.before.period, p.after.item, p.after.period))
+            .collect::<Vec<String>>().join(" ");
+        writeln!(out).unwrap();
+        writeln!(out, "set PRECEDENCES := {tuples};").unwrap();
+    }
+
+    if let Some(availability) = instance.availability.as_ref().filter(|a| a.iter().any(|up| !up)) {
+        let periods = (0..h).filter(|t| !availability[*t]).map(|t| t.to_string()).collect::<Vec<String>>().join(" ");
+        writeln!(out).unwrap();
+        writeln!(out, "set DOWN := {periods};").unwrap();
+    }
+
+    if let Some(overtime) = instance.overtime_cost {
+        writeln!(out).unwrap();
+        writeln!(out, "param overtime_cost := {overtime};").unwrap();
+    }
+
+    if let Some(idle) = instance.idle_cost {
+        writeln!(out).unwrap();
+        writeln!(out, "param idle_cost := {idle};").unwrap();
+    }
+
+    if instance.idle_setup == Some(IdleSetup::Lost) {
+        writeln!(out).unwrap();
+        writeln!(out, "param keep_setup := 0;").unwrap();
+    }
+
+    out
+}
@@ -0,0 +1,215 @@
+//! This module builds instances from demand plans exported as csv files or, when the xlsx
+//! feature is enabled, as Excel workbooks.
+//!
+//! The demand table has a header row with the label of each period, then one row per item
+//! with its name and its demanded quantity at each period (empty cells mean no demand).
+//! The stocking table gives the name and the stocking cost of each item, and the changeover
+//! table is a matrix whose header row and first column give the names of the items. In a
+//! workbook, these tables are the sheets named Demands, Stocking and Changeover. When a 
+//! cost table is missing, the default costs are used.
+
+use std::collections::HashMap;
+
+use clap::Args;
+
+use crate::error::{PspError, read_file, write_file};
+use crate::instance::PspInstance;
+use crate::resolution::model::Psp;
+
+#[derive(Debug, Args)]
+pub struct Import {
+    /// The path to the demand plan: a csv file or an xlsx workbook (- for a csv file on the standard input)
+    #[clap(short, long)]
+    pub demands: String,
+    /// If present, the path to the csv file giving the stocking costs (ignored for a workbook)
+    #[clap(long)]
+    pub stocking: Option<String>,
+    /// If present, the path to the csv file giving the changeover costs (ignored for a workbook)
+    #[clap(long)]
+    pub changeover: Option<String>,
+    /// The stocking cost of the items whose cost is not given
+    #[clap(long, default_value="1")]
+    pub default_stocking: f64,
+    /// The changeover cost between distinct items whose cost is not given
+    #[clap(long, default_value="1")]
+    pub default_changeover: f64,
+    /// Name of the file where to write the instance (it is printed if absent or -)
+    #[clap(short, long)]
+    pub output: Option<String>,
+}
+
+/// The cells of a table, as text
+type Table = Vec<Vec<String>>;
+
+impl Import {
+    pub fn import(&self) -> Result<(), PspError> {
+        let (demands, stocking, changeover) = if self.demands.ends_with(".xlsx") {
+            Self::read_workbook(&self.demands)?
+        } else {
+            let read = |path: &String| read_file(path).map(|text| Self::parse_csv(&text));
+            (read(&self.demands)?, self.stocking.as_ref().map(read).transpose()?, self.changeover.as_ref().map(read).transpose()?)
+        };
+
+        let instance = self.build(&demands, stocking.as_ref(), changeover.as_ref())?;
+        // the instance can be modeled, which also checks that its demands can be met
+        Psp::try_from(instance.clone())?;
+
+        let instance = serde_json::to_string_pretty(&instance).expect("an instance can always be serialized");
+        match self.output.as_ref() {
+            Some(path) => write_file(path, instance),
+            None => {
+                println!("{instance}");
+                Ok(())
+            },
+        }
+    }
+
+    /// Builds the instance from the demand table and the cost tables, if any
+    fn build(&self, demands: &Table, stocking: Option<&Table>, changeover: Option<&Table>) -> Result<PspInstance, PspError> {
+        let invalid = |table: &str, message: String| PspError::Invalid(format!("{table} table: {message}"));
+
+        let header = demands.first().ok_or_else(|| invalid("demand", "it is empty".to_string()))?;
+        let period_labels = header.iter().skip(1).cloned().collect::<Vec<String>>();
+        let rows = demands.iter().skip(1).filter(|row| row.first().map_or(false, |name| !name.is_empty()));
+
+        let mut item_names = vec![];
+        let mut quantities = vec![];
+        for row in rows {
+            let mut demand = vec![0; period_labels.len()];
+            for (t, cell) in row.iter().skip(1).enumerate().filter(|(_, c)| !c.is_empty()) {
+                let quantity = cell.parse::<f64>().ok()
+                    .filter(|q| *q >= 0.0 && q.fract() == 0.0)
+                    .ok_or_else(|| invalid("demand", format!("{cell} is not a quantity")))?;
+                *demand.get_mut(t).ok_or_else(|| invalid("demand", format!("the row of {} is too long", row[0])))? = quantity as usize;
+            }
+            item_names.push(row[0].clone());
+            quantities.push(demand);
+        }
+        let mut index = HashMap::new();
+        for (i, name) in item_names.iter().enumerate() {
+            if index.insert(name.as_str(), i).is_some() {
+                return Err(invalid("demand", format!("item {name} is given twice")));
+            }
+        }
+        let n = item_names.len();
+
+        let mut stocking_costs = vec![self.default_stocking; n];
+        for (k, row) in stocking.into_iter().flatten().enumerate() {
+            let Some(name) = row.first().filter(|name| !name.is_empty()) else {
+                continue; // an empty row
+            };
+            let cost = match row.get(1).and_then(|c| c.parse::<f64>().ok()) {
+                Some(cost) => cost,
+                None if k == 0 => continue, // the header row
+                None => return Err(invalid("stocking", format!("the row of {name} has no cost"))),
+            };
+            let item = *index.get(name.as_str()).ok_or_else(|| invalid("stocking", format!("unknown item {name}")))?;
+            stocking_costs[item] = cost;
+        }
+
+        let mut changeover_costs = (0..n).map(|a| (0..n).map(|b| if a == b { 0.0 } else { self.default_changeover }).collect()).collect::<Vec<Vec<f64>>>();
+        if let Some(table) = changeover {
+            let header = table.first().ok_or_else(|| invalid("changeover", "it is empty".to_string()))?;
+            let columns = header.iter().skip(1)
+                .map(|name| index.get(name.as_str()).copied().ok_or_else(|| invalid("changeover", format!("unknown item {name}"))))
+                .collect::<Result<Vec<usize>, PspError>>()?;
+            for row in table.iter().skip(1).filter(|row| row.first().map_or(false, |name| !name.is_empty())) {
+                let from = *index.get(row[0].as_str()).ok_or_else(|| invalid("changeover", format!("unknown item {}", row[0])))?;
+                for (to, cell) in columns.iter().zip(row.iter().skip(1)).filter(|(_, c)| !c.is_empty()) {
+                    changeover_costs[from][*to] = cell.parse::<f64>().map_err(|_| invalid("changeover", format!("{cell} is not a cost")))?;
+                }
+            }
+        }
+
+        Ok(PspInstance {
+            nb_types: n,
+            nb_periods: period_labels.len(),
+            stocking: stocking_costs,
+            changeover: changeover_costs,
+            second_order_changeover: None,
+            demands: quantities,
+            sparse_demands: None,
+            item_names: Some(item_names),
+            earliest: None,
+            period_labels: Some(period_labels),
+            max_stock: None,
+            storage_capacity: None,
+            idle_setup: None,
+            availability: None,
+            overtime_cost: None,
+            idle_cost: None,
+            forbidden_transitions: None,
+            precedences: None,
+            cost_scale: None,
+        })
+    }
+
+    /// Splits the given csv text in cells (quoted cells are not supported)
+    fn parse_csv(text: &str) -> Table {
+        text.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.split(',').map(|cell| cell.trim().to_string()).collect())
+            .collect()
+    }
+
+    /// Reads the demand table and the cost tables of the given workbook
+    #[cfg(feature = "xlsx")]
+    fn read_workbook(path: &str) -> Result<(Table, Option<Table>, Option<Table>), PspError> {
+        use calamine::{open_workbook_auto, Reader};
+
+        let error = |e: calamine::Error| PspError::Invalid(format!("{path}: {e}"));
+        let mut workbook = open_workbook_auto(path).map_err(error)?;
+        let mut sheet = |name: &str| -> Result<Option<Table>, PspError> {
+            if !workbook.sheet_names().iter().any(|s| s == name) {
+                return Ok(None);
+            }
+            let range = workbook.worksheet_range(name).map_err(error)?;
+            Ok(Some(range.rows().map(|row| row.iter().map(|cell| cell.to_string().trim().to_string()).collect()).collect()))
+        };
+
+        let demands = sheet("Demands")?.ok_or_else(|| PspError::Invalid(format!("{path}: there is no Demands sheet")))?;
+        Ok((demands, sheet("Stocking")?, sheet("Changeover")?))
+    }
+
+    #[cfg(not(feature = "xlsx"))]
+    fn read_workbook(path: &str) -> Result<(Table, Option<Table>, Option<Table>), PspError> {
+        Err(PspError::Invalid(format!("{path}: reading workbooks requires the xlsx feature")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use ddo::TimeBudget;
+
+    use crate::resolution::dp::dynamic_programming;
+    use crate::resolution::exhaustive::exhaustive;
+    use crate::resolution::model::IDLE;
+    use super::*;
+
+    fn import() -> Import {
+        Import { demands: "demands.csv".to_string(), stocking: None, changeover: None, default_stocking: 1.0, default_changeover: 10.0, output: None }
+    }
+
+    #[test]
+    fn orders_of_several_units_are_served() {
+        let demands = Import::parse_csv("item,mon,tue,wed,thu,fri\nblue,,,2,,\nred,,,,,2\n");
+        let stocking = Import::parse_csv("item,cost\nblue,1\nred,2\n");
+        let instance = import().build(&demands, Some(&stocking), None).unwrap();
+        // the instance is written and read back
+        let instance = serde_json::from_str::<PspInstance>(&serde_json::to_string_pretty(&instance).unwrap()).unwrap();
+        assert_eq!(instance.demands, vec![vec![0, 0, 2, 0, 0], vec![0, 0, 0, 0, 2]]);
+
+        let problem = Psp::try_from(instance).unwrap();
+        let budget = TimeBudget::new(Duration::from_secs(60));
+        let (_, Some((value, solution))) = exhaustive(&problem, &budget) else {
+            panic!("the imported instance is feasible");
+        };
+        let schedule = problem.schedule(&solution);
+        assert!((0..problem.n_items).all(|i| problem.is_item_feasible(&schedule, i)));
+        assert_eq!(schedule, vec![IDLE, 0, 0, 1, 1]);
+        assert_eq!(problem.cost(&schedule), Some(13));
+        assert_eq!(dynamic_programming(&problem, &budget).1.map(|(v, _)| v), Some(value));
+    }
+}
@@ -0,0 +1,118 @@
+//! This module implements the conversion of the instances to the input formats of other
+//! solvers and modeling languages, and the import of instances from demand plans.
+
+use clap::{Args, ValueEnum};
+use rand::{Rng, SeedableRng, seq::SliceRandom};
+use rand_chacha::ChaChaRng;
+
+use crate::error::{PspError, read_json, write_file};
+use crate::instance::PspInstance;
+use crate::resolution::model::Psp;
+
+mod xcsp;
+mod ampl;
+mod import;
+
+pub use import::Import;
+
+/// The format to which an instance is converted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// XCSP3 XML model, as read by the solvers of the XCSP competitions
+    Xcsp,
+    /// AMPL data file
+    Ampl,
+    /// The JSON format of the instances, to rescale them
+    Json,
+}
+
+#[derive(Debug, Args)]
+pub struct Convert {
+    /// The path to the instance file (- for the standard input)
+    #[clap(short, long)]
+    pub instance: String,
+    /// The format of the converted instance
+    #[clap(short, long, value_enum)]
+    pub format: Format,
+    /// The path of the converted instance (it is printed if absent or -)
+    #[clap(short, long)]
+    pub output: Option<String>,
+    /// If present, the costs are multiplied by this factor before the conversion, and the
+    /// factor is recorded in the instance
+    #[clap(long)]
+    pub rescale: Option<f64>,
+    /// If present, the costs are rounded to the nearest integers before the conversion
+    #[clap(long)]
+    pub round: bool,
+    /// If present, the items are shuffled, the costs are multiplied by a random factor
+    /// between 0.5 and 2 with two decimals (which preserves the optimal schedules), and the names of the
+    /// items and periods and the cost scale are removed, so that the instance can be shared
+    #[clap(long)]
+    pub anonymize: bool,
+    /// The seed of the anonymization (random if absent)
+    #[clap(long, requires = "anonymize")]
+    pub anonymize_seed: Option<u64>,
+    /// The number of times the horizon is repeated, with its demands
+    #[clap(long, default_value = "1")]
+    pub tile: usize,
+    /// The number of copies of each item, which serve its demands in turn
+    #[clap(long, default_value = "1")]
+    pub copies: usize,
+    /// The relative amplitude of the random variations of the costs of the copies of the items
+    #[clap(long, default_value = "0.1")]
+    pub jitter: f64,
+    /// The seed of the variations of the costs of the copies
+    #[clap(long, default_value = "0")]
+    pub jitter_seed: u64,
+}
+
+impl Convert {
+    pub fn convert(&self) -> Result<(), PspError> {
+        let mut instance: PspInstance = read_json(&self.instance)?;
+        if self.tile == 0 || self.copies == 0 || !(0.0..1.0).contains(&self.jitter) {
+            return Err(PspError::Invalid("the instance must be tiled and copied at least once, with a jitter between 0 and 1".to_string()));
+        }
+        if self.tile > 1 {
+            instance = instance.tiled(self.tile);
+        }
+        if self.copies > 1 {
+            instance = instance.with_copies(self.copies, self.jitter, &mut ChaChaRng::seed_from_u64(self.jitter_seed));
+        }
+        if self.anonymize {
+            let mut rng = match self.anonymize_seed {
+                Some(seed) => ChaChaRng::seed_from_u64(seed),
+                None => ChaChaRng::from_entropy(),
+            };
+            let mut order = (0..instance.nb_types).collect::<Vec<usize>>();
+            order.shuffle(&mut rng);
+            // a factor with two decimals keeps the costs almost as precise as they are
+            let factor = (rng.gen_range(0.5..2.0_f64) * 100.0).round() / 100.0;
+            instance = instance.anonymized(&order, factor);
+        }
+        if self.rescale.is_some() || self.round {
+            let factor = self.rescale.unwrap_or(1.0);
+            if !factor.is_finite() || factor <= 0.0 {
+                return Err(PspError::Invalid(format!("the costs cannot be rescaled by {factor}")));
+            }
+            instance = instance.rescaled(factor, self.round);
+        }
+        let problem = Psp::try_from(instance.clone())?;
+
+        let converted = match self.format {
+            Format::Xcsp if problem.windows || !problem.precedences.is_empty() || problem.overtime.is_some() || problem.idle > 0
+                || !problem.second_order.is_empty() => 
+                return Err(PspError::Invalid("the XCSP model does not support service windows, precedences, overtime, idle costs nor second-order changeovers".to_string())),
+            Format::Xcsp => xcsp::to_xcsp(&problem),
+            Format::Ampl => ampl::to_ampl(&instance),
+            Format::Json => serde_json::to_string_pretty(&instance).expect("an instance can always be serialized"),
+        };
+
+        match self.output.as_ref() {
+            Some(path) => write_file(path, converted),
+            None => {
+                print!("{converted}");
+                Ok(())
+            },
+        }
+    }
+}
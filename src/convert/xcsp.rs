@@ -0,0 +1,153 @@
+//! This module writes an instance as an XCSP3 model. The item produced at each period is
+//! given by `x[t]`, where the value `n` (the number of items) means the machine is idle.
+//! The last item produced up to each period is tracked by `p[t + 1]`, with `p[0] = n` as
+//! nothing has been produced yet, so that the changeover cost `c[t]` of each period is 
+//! given by a table constraint over `p[t]`, `x[t]`, `p[t + 1]` and `c[t]`. When the setup
+//! is lost during idle periods, an idle period resets `p[t + 1]` to `n`. The forbidden
+//! transitions are left out of the table, and the machine is idle while it is down.
+//!
+//! The stocking cost of an item produced at period t is proportional to the number of 
+//! periods left until the horizon, minus the time that remains after its deadline. The 
+//! latter does not depend on the schedule: it is written in a comment and must be 
+//! subtracted from the objective to obtain the cost of the instance. All the costs are
+//! scaled to integers as in the solver.
+
+use std::{collections::BTreeSet, fmt::Write};
+
+use crate::resolution::model::Psp;
+
+pub fn to_xcsp(problem: &Psp) -> String {
+    let (n, h) = (problem.n_items, problem.horizon);
+    let none = n;
+    let mut out = String::new();
+
+    // the stocking cost of each value of x, and the constant part of the stocking costs
+    let stocking = (0..=n).map(|v| if v == none { 0 } else { problem.stocking[v] }).collect::<Vec<usize>>();
+    let offset = (0..n)
+        .map(|i| problem.stocking[i] * problem.demands_of(i).iter().map(|d| d.quantity * (h - d.period)).sum::<usize>())
+        .sum::<usize>();
+
+    let changeovers = (0..n).flat_map(|a| (0..n).map(move |b| (a, b)))
+        .map(|(a, b)| problem.changeover(a, b))
+        .chain(std::iter::once(0))
+        .collect::<BTreeSet<usize>>();
+    let stockings = stocking.iter().copied().collect::<BTreeSet<usize>>();
+
+    writeln!(out, "<!-- PSP instance with {n} items and {h} periods, costs scaled by 10^{} -->", problem.precision).unwrap();
+    writeln!(out, "<!-- the cost of the instance is the objective minus {offset} -->").unwrap();
+    writeln!(out, "<instance format=\"XCSP3\" type=\"COP\">").unwrap();
+    writeln!(out, "  <variables>").unwrap();
+    writeln!(out, "    <array id=\"x\" size=\"[{h}]\"> 0..{n} </array>").unwrap();
+    writeln!(out, "    <array id=\"p\" size=\"[{}]\"> 0..{n} </array>", h + 1).unwrap();
+    writeln!(out, "    <array id=\"c\" size=\"[{h}]\"> {} </array>", join(changeovers.iter())).unwrap();
+    writeln!(out, "    <array id=\"s\" size=\"[{h}]\"> {} </array>", join(stockings.iter())).unwrap();
+    writeln!(out, "  </variables>").unwrap();
+
+    writeln!(out, "  <constraints>").unwrap();
+    writeln!(out, "    <intension> eq(p[0],{none}) </intension>").unwrap();
+
+    // the demands due by each deadline must be produced before it, and all in all
+    // exactly the demanded quantities are produced
+    for i in 0..n {
+        let mut cumulated = 0;
+        for d in problem.demands_of(i).iter() {
+            let t = d.period;
+            cumulated = d.cumulated;
+            writeln!(out, "    <count>").unwrap();
+            writeln!(out, "      <list> x[0..{t}] </list>").unwrap();
+            writeln!(out, "      <values> {i} </values>").unwrap();
+            writeln!(out, "      <condition> (ge,{cumulated}) </condition>").unwrap();
+            writeln!(out, "    </count>").unwrap();
+        }
+
+        // at most max_stock units may have been produced in advance at the end of each period
+        let cap = problem.max_stock[i];
+        if cap != usize::MAX {
+            for t in 0..h {
+                let limit = problem.rem_demand(i, t) as usize + cap;
+                if limit < cumulated {
+                    writeln!(out, "    <count>").unwrap();
+                    writeln!(out, "      <list> x[0..{t}] </list>").unwrap();
+                    writeln!(out, "      <values> {i} </values>").unwrap();
+                    writeln!(out, "      <condition> (le,{limit}) </condition>").unwrap();
+                    writeln!(out, "    </count>").unwrap();
+                }
+            }
+        }
+
+        writeln!(out, "    <count>").unwrap();
+        writeln!(out, "      <list> x[] </list>").unwrap();
+        writeln!(out, "      <values> {i} </values>").unwrap();
+        writeln!(out, "      <condition> (eq,{cumulated}) </condition>").unwrap();
+        writeln!(out, "    </count>").unwrap();
+    }
+
+    // at most capacity units of all items may have been produced in advance at the end of each period
+    if problem.capacity != usize::MAX {
+        let items = (0..n).map(|i| i.to_string()).collect::<Vec<String>>().join(" ");
+        for t in 0..h {
+            let limit = (0..n).map(|i| problem.rem_demand(i, t) as usize).sum::<usize>() + problem.capacity;
+            if limit <= t {
+                writeln!(out, "    <count>").unwrap();
+                writeln!(out, "      <list> x[0..{t}] </list>").unwrap();
+                writeln!(out, "      <values> {items} </values>").unwrap();
+                writeln!(out, "      <condition> (le,{limit}) </condition>").unwrap();
+                writeln!(out, "    </count>").unwrap();
+            }
+        }
+    }
+
+    for t in (0..h).filter(|t| problem.is_down(*t)) {
+        writeln!(out, "    <intension> eq(x[{t}],{none}) </intension>").unwrap();
+    }
+
+    // the last item produced and the changeover cost of each period
+    writeln!(out, "    <group>").unwrap();
+    writeln!(out, "      <extension>").unwrap();
+    writeln!(out, "        <list> %0 %1 %2 %3 </list>").unwrap();
+    write!(out, "        <supports> ").unwrap();
+    for prev in 0..=n {
+        for x in 0..=n {
+            match (prev, x) {
+                (_, x) if x == none && problem.keeps_setup() => write!(out, "({prev},{x},{prev},0)").unwrap(),
+                (_, x) if x == none => write!(out, "({prev},{x},{none},0)").unwrap(),
+                (prev, x) if prev == none => write!(out, "({prev},{x},{x},0)").unwrap(),
+                (prev, x) if !problem.can_follow(prev, x) => {},
+                (prev, x) => write!(out, "({prev},{x},{x},{})", problem.changeover(prev, x)).unwrap(),
+            }
+        }
+    }
+    writeln!(out, " </supports>").unwrap();
+    writeln!(out, "      </extension>").unwrap();
+    for t in 0..h {
+        writeln!(out, "      <args> p[{t}] x[{t}] p[{}] c[{t}] </args>", t + 1).unwrap();
+    }
+    writeln!(out, "    </group>").unwrap();
+
+    // the stocking cost of the item produced at each period
+    writeln!(out, "    <group>").unwrap();
+    writeln!(out, "      <element>").unwrap();
+    writeln!(out, "        <list> {} </list>", join(stocking.iter())).unwrap();
+    writeln!(out, "        <index> %0 </index>").unwrap();
+    writeln!(out, "        <value> %1 </value>").unwrap();
+    writeln!(out, "      </element>").unwrap();
+    for t in 0..h {
+        writeln!(out, "      <args> x[{t}] s[{t}] </args>").unwrap();
+    }
+    writeln!(out, "    </group>").unwrap();
+    writeln!(out, "  </constraints>").unwrap();
+
+    writeln!(out, "  <objectives>").unwrap();
+    writeln!(out, "    <minimize type=\"sum\">").unwrap();
+    writeln!(out, "      <list> c[] s[] </list>").unwrap();
+    writeln!(out, "      <coeffs> {} {} </coeffs>", join(std::iter::repeat(&1).take(h)), join((0..h).map(|t| h - t).collect::<Vec<usize>>().iter())).unwrap();
+    writeln!(out, "    </minimize>").unwrap();
+    writeln!(out, "  </objectives>").unwrap();
+    writeln!(out, "</instance>").unwrap();
+
+    out
+}
+
+fn join<'a>(values: impl Iterator<Item = &'a usize>) -> String {
+    values.map(|v| v.to_string()).collect::<Vec<String>>().join(" ")
+}
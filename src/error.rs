@@ -0,0 +1,80 @@
+//! This module defines the errors reported to the user by the subcommands, and the
+//! helpers that read and write files with these errors.
+
+use std::{fs::File, io::{self, BufReader, Write}};
+
+use serde::{de::DeserializeOwned, Serialize};
+use thiserror::Error;
+
+use crate::solution::SolveStatus;
+
+#[derive(Debug, Error)]
+pub enum PspError {
+    /// A file could not be read or written
+    #[error("{path}: {source}")]
+    Io { path: String, source: io::Error },
+    /// A file does not contain the expected json document
+    #[error("{path}: malformed json: {source}")]
+    Json { path: String, source: serde_json::Error },
+    /// A database could not be read or written
+    #[cfg(feature = "sqlite")]
+    #[error("{path}: {source}")]
+    Database { path: String, source: rusqlite::Error },
+    /// A workbook could not be written
+    #[cfg(feature = "xlsx")]
+    #[error("{path}: {source}")]
+    Xlsx { path: String, source: rust_xlsxwriter::XlsxError },
+    /// The instance cannot be modeled
+    #[error("invalid instance: {0}")]
+    InvalidInstance(String),
+    /// The instance has no feasible schedule
+    #[error("infeasible instance: {0}")]
+    Infeasible(String),
+    /// The options or the input files are inconsistent
+    #[error("{0}")]
+    Invalid(String),
+}
+
+impl PspError {
+    /// Returns the exit code reporting this error
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            PspError::Infeasible(_) => SolveStatus::Infeasible.exit_code(),
+            _ => SolveStatus::ERROR_EXIT_CODE,
+        }
+    }
+}
+
+/// The path that designates the standard input or output
+pub const STDIO: &str = "-";
+
+/// Reads the json document of the given file, or of the standard input
+pub fn read_json<T: DeserializeOwned>(path: &str) -> Result<T, PspError> {
+    if path == STDIO {
+        return serde_json::from_reader(io::stdin().lock()).map_err(|source| PspError::Json { path: "stdin".to_string(), source });
+    }
+    let file = File::open(path).map_err(|source| PspError::Io { path: path.to_string(), source })?;
+    serde_json::from_reader(BufReader::new(file)).map_err(|source| PspError::Json { path: path.to_string(), source })
+}
+
+/// Reads the given text file, or the standard input
+pub fn read_file(path: &str) -> Result<String, PspError> {
+    if path == STDIO {
+        return io::read_to_string(io::stdin().lock()).map_err(|source| PspError::Io { path: "stdin".to_string(), source });
+    }
+    std::fs::read_to_string(path).map_err(|source| PspError::Io { path: path.to_string(), source })
+}
+
+/// Writes the given json document to the given file, or to the standard output
+pub fn write_json<T: Serialize>(path: &str, value: &T) -> Result<(), PspError> {
+    let json = serde_json::to_string(value).map_err(|source| PspError::Json { path: path.to_string(), source })?;
+    write_file(path, json)
+}
+
+/// Writes the given contents to the given file, or to the standard output
+pub fn write_file(path: &str, contents: impl AsRef<[u8]>) -> Result<(), PspError> {
+    if path == STDIO {
+        return io::stdout().lock().write_all(contents.as_ref()).map_err(|source| PspError::Io { path: "stdout".to_string(), source });
+    }
+    std::fs::write(path, contents).map_err(|source| PspError::Io { path: path.to_string(), source })
+}
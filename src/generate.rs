@@ -1,12 +1,41 @@
-use std::{time::{SystemTime, UNIX_EPOCH}, fs::File, io::Write, collections::BTreeSet, ops::Bound::*};
+use std::{time::{SystemTime, UNIX_EPOCH}, collections::BTreeSet, ops::Bound::*};
 
-use clap::Args;
+use clap::{Args, Parser, ValueEnum};
 use rand::{Rng, SeedableRng};
 use rand_chacha::ChaChaRng;
-use rand_distr::{Uniform, Normal, Distribution};
+use rand_distr::{Uniform, Normal, LogNormal, Exp, Distribution};
 
+use crate::error::{PspError, write_file};
 use crate::instance::PspInstance;
 
+/// The distribution of the costs (or positions) of the items of a cluster around its centroid
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CostDistribution {
+    /// Normal distribution with the given std deviation
+    Normal,
+    /// Lognormal distribution with the given std deviation, whose right tail is heavy
+    Lognormal,
+    /// Exponential distribution, whose std deviation is the centroid itself
+    Exponential,
+}
+
+/// A sampler of one of the cost distributions
+enum CostSampler {
+    Normal(Normal<f64>),
+    Lognormal(LogNormal<f64>),
+    Exponential(Exp<f64>),
+}
+
+impl Distribution<f64> for CostSampler {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        match self {
+            CostSampler::Normal(d) => d.sample(rng),
+            CostSampler::Lognormal(d) => d.sample(rng),
+            CostSampler::Exponential(d) => d.sample(rng),
+        }
+    }
+}
+
 #[derive(Debug, Args)]
 pub struct PspGenerator {
     /// An optional seed to kickstart the instance generation
@@ -18,12 +47,26 @@ pub struct PspGenerator {
     /// The number of clusters of similar item types
     #[clap(short='c', long, default_value="3")]
     nb_clusters: usize,
+    /// The Zipf exponent of the cluster sizes: the k-th cluster is 1/k^skew times as large
+    /// as the first one (0 gives clusters as equal as possible)
+    #[clap(long, default_value="0")]
+    cluster_size_skew: f64,
     /// The number of time periods
     #[clap(short='p', long, default_value="50")]
     nb_periods: usize,
     /// The number of demands normalized by the number of periods
     #[clap(short='d', long, default_value="0.95")]
     density: f64,
+    /// The maximum number of periods by which a demand can be served late: each demand
+    /// is given a service window of random length up to that bound, ending at its period
+    #[clap(long, default_value="0")]
+    max_lateness: usize,
+    /// The number of shutdown windows, during which the machine is down
+    #[clap(long, default_value="0")]
+    shutdowns: usize,
+    /// The maximum number of periods of a shutdown window, whose length is drawn uniformly
+    #[clap(long, default_value="1")]
+    max_shutdown_length: usize,
     /// The minimum stocking cost
     #[clap(long, default_value="100")]
     min_stocking: usize,
@@ -33,6 +76,9 @@ pub struct PspGenerator {
     /// The std deviation of the stocking cost among a cluster
     #[clap(long, default_value="100")]
     stocking_std_dev: usize,
+    /// The distribution of the stocking costs among a cluster
+    #[clap(long, value_enum, default_value_t=CostDistribution::Normal)]
+    stocking_distribution: CostDistribution,
     /// The minimum changeover position used to generate the pairwise costs
     #[clap(long, default_value="100")]
     min_changeover_position: isize,
@@ -42,14 +88,65 @@ pub struct PspGenerator {
     /// The std deviation of the changeover positions among a cluster
     #[clap(long, default_value="100")]
     changeover_position_std_dev: isize,
+    /// The distribution of the changeover positions among a cluster
+    #[clap(long, value_enum, default_value_t=CostDistribution::Normal)]
+    changeover_distribution: CostDistribution,
+    /// The cost of each period where the machine is available but idle, if idling is
+    /// penalized
+    #[clap(long)]
+    idle_cost: Option<f64>,
+    /// If present, the demands are written as lists of demanded periods instead of a
+    /// table, which is much smaller for long horizons with few demands
+    #[clap(long)]
+    sparse: bool,
     /// Name of the file where to generate the psp instance
     #[clap(short, long)]
     output: Option<String>,
 }
 
+/// Parses the options of the generator from a list of arguments
+#[derive(Debug, Parser)]
+struct GeneratorParser {
+    #[clap(flatten)]
+    generator: PspGenerator,
+}
+
 impl PspGenerator {
 
-    pub fn generate(&mut self) {
+    pub fn generate(&mut self) -> Result<(), PspError> {
+        let instance = self.instance()?;
+        let instance = serde_json::to_string_pretty(&instance).expect("an instance can always be serialized");
+
+        if let Some(output) = self.output.as_ref() {
+            write_file(output, instance)?;
+        } else {
+            println!("{instance}");
+        }
+        Ok(())
+    }
+
+    /// Parses the options from the given arguments, the first of which is the program name
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Self, clap::Error> {
+        GeneratorParser::try_parse_from(args).map(|p| p.generator)
+    }
+
+    /// Generates an instance with the options of the generator
+    pub fn instance(&mut self) -> Result<PspInstance, PspError> {
+        if self.nb_types == 0 || self.nb_periods == 0 || self.nb_clusters == 0 || self.nb_clusters > self.nb_types {
+            return Err(PspError::Invalid("there must be at least one item, one period, and between one cluster and one cluster per item".to_string()));
+        }
+        if !self.cluster_size_skew.is_finite() || self.cluster_size_skew < 0.0 {
+            return Err(PspError::Invalid("the cluster size skew must be a non-negative number".to_string()));
+        }
+        if !(0.0..=1.0).contains(&self.density) {
+            return Err(PspError::Invalid("the density must be between 0 and 1".to_string()));
+        }
+        if self.shutdowns > 0 && self.max_shutdown_length == 0 {
+            return Err(PspError::Invalid("the shutdown windows must have at least one period".to_string()));
+        }
+        if self.idle_cost.map_or(false, |c| !c.is_finite() || c < 0.0) {
+            return Err(PspError::Invalid("the idle cost must be a non-negative number".to_string()));
+        }
         if self.min_stocking < self.stocking_std_dev {
             self.max_stocking += self.stocking_std_dev - self.min_stocking;
             self.min_stocking = self.stocking_std_dev;
@@ -57,49 +154,80 @@ impl PspGenerator {
 
         let mut rng = self.rng();
 
-        let mut nb_types_per_cluster = vec![self.nb_types / self.nb_clusters; self.nb_clusters];
-        for i in 0..(self.nb_types % self.nb_clusters) {
-            nb_types_per_cluster[i] += 1;
-        }
-        
-        let stocking = self.generate_stocking_costs(&mut rng, &nb_types_per_cluster);
-        let changeover = self.generate_changeover_costs(&mut rng, &nb_types_per_cluster);
-        let demands = self.generate_demands(&mut rng);
+        let nb_types_per_cluster = self.cluster_sizes();
+        let stocking = self.generate_stocking_costs(&mut rng, &nb_types_per_cluster)?;
+        let changeover = self.generate_changeover_costs(&mut rng, &nb_types_per_cluster)?;
+        let availability = (self.shutdowns > 0).then(|| self.generate_shutdowns(&mut rng));
+        let demands = self.generate_demands(&mut rng, availability.as_deref());
+        let earliest = (self.max_lateness > 0).then(|| self.generate_windows(&mut rng, &demands));
 
-        let instance = PspInstance {
+        let mut instance = PspInstance {
             nb_types: self.nb_types,
             nb_periods: self.nb_periods,
-            stocking,
-            changeover,
-            demands
+            stocking: stocking.into_iter().map(|c| c as f64).collect(),
+            changeover: changeover.into_iter().map(|row| row.into_iter().map(|c| c as f64).collect()).collect(),
+            second_order_changeover: None,
+            demands,
+            sparse_demands: None,
+            item_names: None,
+            earliest,
+            period_labels: None,
+            max_stock: None,
+            storage_capacity: None,
+            idle_setup: None,
+            availability,
+            overtime_cost: None,
+            idle_cost: self.idle_cost,
+            forbidden_transitions: None,
+            precedences: None,
+            cost_scale: None,
         };
+        if self.sparse {
+            instance.sparse_demands = Some(instance.demand_lists());
+            instance.demands.clear();
+            instance.earliest = None;
+        }
+        Ok(instance)
+    }
 
-        let instance = serde_json::to_string_pretty(&instance).unwrap();
+    /// Returns the number of items of each cluster. Each cluster has at least one item, and
+    /// the other items are shared in proportion to the Zipf weights of the clusters, the
+    /// largest remainders being rounded up
+    fn cluster_sizes(&self) -> Vec<usize> {
+        let weights = (0..self.nb_clusters)
+            .map(|k| ((k + 1) as f64).powf(-self.cluster_size_skew))
+            .collect::<Vec<f64>>();
+        let total = weights.iter().sum::<f64>();
+        let rest = self.nb_types - self.nb_clusters;
 
-        if let Some(output) = self.output.as_ref() {
-            File::create(output).unwrap().write_all(instance.as_bytes()).unwrap();
-        } else {
-            println!("{instance}");
+        let shares = weights.iter().map(|w| rest as f64 * w / total).collect::<Vec<f64>>();
+        let mut sizes = shares.iter().map(|s| 1 + s.floor() as usize).collect::<Vec<usize>>();
+        let mut order = (0..self.nb_clusters).collect::<Vec<usize>>();
+        order.sort_by(|a, b| shares[*b].fract().total_cmp(&shares[*a].fract()).then(a.cmp(b)));
+        let missing = self.nb_types - sizes.iter().sum::<usize>();
+        for k in order.into_iter().take(missing) {
+            sizes[k] += 1;
         }
+        sizes
     }
 
-    fn generate_stocking_costs(&self, rng: &mut impl Rng, nb_types_per_cluster: &Vec<usize>) -> Vec<usize> {
+    fn generate_stocking_costs(&self, rng: &mut impl Rng, nb_types_per_cluster: &Vec<usize>) -> Result<Vec<usize>, PspError> {
         let mut stocking_costs = vec![];
 
         let rand_centroid = Uniform::new_inclusive(self.min_stocking, self.max_stocking);
         for i in 0..self.nb_clusters {
             let centroid = rand_centroid.sample(rng);
-            let rand_stocking = Normal::new(centroid as f64, self.stocking_std_dev as f64).expect("cannot create normal dist");
+            let rand_stocking = Self::sampler(self.stocking_distribution, centroid as f64, self.stocking_std_dev as f64)?;
 
             for _ in 0..nb_types_per_cluster[i] {
                 stocking_costs.push(rand_stocking.sample(rng).round() as usize);
             }
         }
 
-        stocking_costs
+        Ok(stocking_costs)
     }
 
-    fn generate_changeover_costs(&self, rng: &mut impl Rng, nb_types_per_cluster: &Vec<usize>) -> Vec<Vec<usize>> {
+    fn generate_changeover_costs(&self, rng: &mut impl Rng, nb_types_per_cluster: &Vec<usize>) -> Result<Vec<Vec<usize>>, PspError> {
         let mut members = vec![vec![]; self.nb_clusters];
         let mut t = 0_usize;
         for (i, n) in nb_types_per_cluster.iter().copied().enumerate() {
@@ -115,7 +243,7 @@ impl PspGenerator {
         for a in 0..self.nb_clusters {
             let centroid_a = rand_centroid.sample(rng);
 
-            let rand_position_a = Normal::new(centroid_a as f64, self.changeover_position_std_dev as f64).expect("cannot create normal dist");
+            let rand_position_a = Self::sampler(self.changeover_distribution, centroid_a as f64, self.changeover_position_std_dev as f64)?;
             let positions_a = (0..nb_types_per_cluster[a]).map(|_| rand_position_a.sample(rng).round() as usize).collect::<Vec<usize>>();
 
             for b in 0..self.nb_clusters {
@@ -128,7 +256,7 @@ impl PspGenerator {
                 } else {
                     let centroid_b = rand_centroid.sample(rng);
         
-                    let rand_position_b = Normal::new(centroid_b as f64, self.changeover_position_std_dev as f64).expect("cannot create normal dist");
+                    let rand_position_b = Self::sampler(self.changeover_distribution, centroid_b as f64, self.changeover_position_std_dev as f64)?;
                     let positions_b = (0..nb_types_per_cluster[b]).map(|_| rand_position_b.sample(rng).round() as usize).collect::<Vec<usize>>();
 
                     for (i, ti) in members[a].iter().copied().enumerate() {
@@ -140,14 +268,49 @@ impl PspGenerator {
             }
         }
         
-        transition_costs
+        Ok(transition_costs)
+    }
+
+    /// Returns a sampler of the given distribution with the given mean and std deviation
+    fn sampler(distribution: CostDistribution, mean: f64, std_dev: f64) -> Result<CostSampler, PspError> {
+        let invalid = |e: String| PspError::Invalid(format!("cannot sample {distribution:?} costs of mean {mean} with std deviation {std_dev}: {e}"));
+        match distribution {
+            CostDistribution::Normal => Normal::new(mean, std_dev)
+                .map(CostSampler::Normal)
+                .map_err(|e| invalid(e.to_string())),
+            _ if mean <= 0.0 => Err(invalid("the mean must be positive".to_string())),
+            CostDistribution::Lognormal => {
+                let sigma2 = (1.0 + (std_dev / mean).powi(2)).ln();
+                LogNormal::new(mean.ln() - sigma2 / 2.0, sigma2.sqrt())
+                    .map(CostSampler::Lognormal)
+                    .map_err(|e| invalid(e.to_string()))
+            },
+            CostDistribution::Exponential => Exp::new(1.0 / mean)
+                .map(CostSampler::Exponential)
+                .map_err(|e| invalid(e.to_string())),
+        }
+    }
+
+    /// Returns the availability of the machine at each period, which is down during the
+    /// shutdown windows. The windows are placed uniformly and may overlap
+    fn generate_shutdowns(&self, rng: &mut impl Rng) -> Vec<bool> {
+        let mut availability = vec![true; self.nb_periods];
+        let max_length = self.max_shutdown_length.min(self.nb_periods);
+        for _ in 0..self.shutdowns {
+            let length = rng.gen_range(1..=max_length);
+            let start = rng.gen_range(0..=self.nb_periods - length);
+            availability[start..start + length].iter_mut().for_each(|up| *up = false);
+        }
+        availability
     }
 
-    fn generate_demands(&self, rng: &mut impl Rng) -> Vec<Vec<usize>> {
-        let mut feasibility_check = PspFeasibility::new(self.nb_periods);
+    /// Returns the demands, which can all be produced on time in the periods where the
+    /// machine is available: there are at most as many of them as such periods
+    fn generate_demands(&self, rng: &mut impl Rng, availability: Option<&[bool]>) -> Vec<Vec<usize>> {
+        let mut feasibility_check = PspFeasibility::new(self.nb_periods, availability);
 
         let mut demands = vec![vec![0; self.nb_periods]; self.nb_types];
-        let nb_demands = (self.density * self.nb_periods as f64).round() as usize;
+        let nb_demands = ((self.density * self.nb_periods as f64).round() as usize).min(feasibility_check.available.len());
         let mut count = 0;
 
         let rand_type = Uniform::new(0, self.nb_types);
@@ -166,6 +329,22 @@ impl PspGenerator {
         demands
     }
 
+    /// Returns the earliest service period of each demand. The windows of the demands
+    /// of an item are kept in the order of their periods
+    fn generate_windows(&self, rng: &mut impl Rng, demands: &[Vec<usize>]) -> Vec<Vec<usize>> {
+        let rand_lateness = Uniform::new_inclusive(0, self.max_lateness);
+        demands.iter().map(|demands| {
+            let mut first = 0;
+            (0..self.nb_periods).map(|t| {
+                if demands[t] == 0 {
+                    return t;
+                }
+                first = t.saturating_sub(rand_lateness.sample(rng)).max(first);
+                first
+            }).collect()
+        }).collect()
+    }
+
     fn rng(&self) -> impl Rng {
         let init = self.seed.unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis());
         let mut seed = [0_u8; 32];
@@ -181,9 +360,9 @@ struct PspFeasibility {
 }
 
 impl PspFeasibility {
-    fn new(nb_periods: usize) -> Self {
+    fn new(nb_periods: usize, availability: Option<&[bool]>) -> Self {
         PspFeasibility {
-            available: BTreeSet::from_iter(0..nb_periods)
+            available: (0..nb_periods).filter(|t| availability.map_or(true, |a| a[*t])).collect()
         }
     }
 
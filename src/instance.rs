@@ -1,12 +1,569 @@
 //! This module defines an abstract representation of a PSP instance.
+//!
+//! The stocking and changeover costs may be decimal numbers. They are scaled to integers
+//! by the smallest power of ten that makes all of them integral, with at most
+//! `PspInstance::MAX_PRECISION` decimals: costs that are more precise are rounded.
 
-use serde::{Serialize, Deserialize};
+use rand::Rng;
+use serde::{Serialize, Deserialize, Serializer};
+use sha2::{Digest, Sha256};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PspInstance {
     pub nb_types: usize,
     pub nb_periods: usize,
-    pub stocking: Vec<usize>,
-    pub changeover: Vec<Vec<usize>>,
+    #[serde(serialize_with = "serialize_costs")]
+    pub stocking: Vec<f64>,
+    #[serde(serialize_with = "serialize_cost_matrix")]
+    pub changeover: Vec<Vec<f64>>,
+    /// The additional cost of the changeover from item b to item c when b was switched to
+    /// from item a, indexed by a, b then c, if the changeovers depend on the last two items
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub second_order_changeover: Option<Vec<Vec<Vec<f64>>>>,
+    /// The demands of each item at each period, by their latest service period (empty
+    /// when the demands are given as lists)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub demands: Vec<Vec<usize>>,
+    /// The demands of each item, in increasing order of their latest service period,
+    /// instead of the demand table. This is much more compact on long horizons where
+    /// the demands are sparse
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sparse_demands: Option<Vec<Vec<SparseDemand>>>,
+    /// For each item and each period with a demand, the earliest period at which the
+    /// demand is served, if it can be served late: a unit produced before that period
+    /// is stocked until it, and a unit produced afterwards is served at once. The
+    /// windows of the demands of an item must be in the same order as their latest periods.
+    /// The windows of sparse demands are given with the demands
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub earliest: Option<Vec<Vec<usize>>>,
+    /// The name of each item, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub item_names: Option<Vec<String>>,
+    /// The label of each period, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period_labels: Option<Vec<String>>,
+    /// The maximum number of units of each item that can be in stock at the end of a
+    /// period, if bounded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_stock: Option<Vec<usize>>,
+    /// The maximum number of units of all items that can be in stock at the end of a
+    /// period, if bounded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage_capacity: Option<usize>,
+    /// Whether the machine keeps its setup through idle periods (kept by default)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_setup: Option<IdleSetup>,
+    /// Whether the machine is available at each period, or down for maintenance or for
+    /// lack of a shift (always available by default)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub availability: Option<Vec<bool>>,
+    /// The surcharge of producing a second unit of the item in overtime at a period, if
+    /// overtime is allowed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overtime_cost: Option<f64>,
+    /// The cost of each period where the machine is available but left idle, if idling
+    /// is penalized
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_cost: Option<f64>,
+    /// The pairs of items (i, j) such that j may never directly follow i
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forbidden_transitions: Option<Vec<(usize, usize)>>,
+    /// The pairs of demands such that the first one must be produced before the second
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub precedences: Option<Vec<Precedence>>,
+    /// The factor by which the costs of the original instance were multiplied, if they were
+    /// rescaled: the costs of the solutions divided by it are in the original units
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_scale: Option<f64>,
+}
+
+/// A demand of an item, in a sparse demand list
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SparseDemand {
+    /// The latest service period
+    pub period: usize,
+    pub quantity: usize,
+    /// The earliest service period, if the demand can be served late
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub earliest: Option<usize>,
+}
+
+/// The demand of an item at some period
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DemandRef {
+    pub item: usize,
+    pub period: usize,
+}
+
+/// A demand that must be produced before another one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Precedence {
+    pub before: DemandRef,
+    pub after: DemandRef,
+}
+
+/// What becomes of the setup of the machine during an idle period
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum IdleSetup {
+    /// The setup is carried over: producing after an idle period incurs the changeover
+    /// from the last item produced before it
+    #[default]
+    Kept,
+    /// The setup is lost: producing after an idle period incurs no changeover
+    Lost,
+}
+
+impl PspInstance {
+    /// The maximum number of decimals of the costs that are taken into account
+    pub const MAX_PRECISION: u32 = 6;
+
+    /// Returns the number of decimals needed to represent all the costs exactly,
+    /// up to `MAX_PRECISION`
+    pub fn precision(&self) -> u32 {
+        (0..Self::MAX_PRECISION)
+            .find(|p| self.costs().all(|c| is_integral(c * 10_f64.powi(*p as i32))))
+            .unwrap_or(Self::MAX_PRECISION)
+    }
+
+    /// Returns the demands of each item, with a positive quantity and in increasing order
+    /// of period, whether the instance gives them as a table or as lists
+    pub fn demand_lists(&self) -> Vec<Vec<SparseDemand>> {
+        if let Some(sparse) = self.sparse_demands.as_ref() {
+            return sparse.iter()
+                .map(|demands| demands.iter().copied().filter(|d| d.quantity > 0).collect())
+                .collect();
+        }
+        self.demands.iter().enumerate()
+            .map(|(i, row)| row.iter().enumerate()
+                .filter(|(_, q)| **q > 0)
+                .map(|(period, quantity)| SparseDemand {
+                    period,
+                    quantity: *quantity,
+                    earliest: self.earliest.as_ref().map(|e| e[i][period]),
+                })
+                .collect())
+            .collect()
+    }
+
+    /// Returns the demand of each item at each period, whether the instance gives them
+    /// as a table or as lists
+    pub fn demand_table(&self) -> Vec<Vec<usize>> {
+        let mut table = vec![vec![0; self.nb_periods]; self.nb_types];
+        for (i, demands) in self.demand_lists().iter().enumerate() {
+            for d in demands.iter() {
+                table[i][d.period] += d.quantity;
+            }
+        }
+        table
+    }
+
+    /// Returns the instance whose costs are multiplied by the given factor, and rounded to
+    /// the nearest integers if requested. The factor is recorded with the previous ones
+    pub fn rescaled(&self, factor: f64, round: bool) -> Self {
+        let scale = |c: f64| if round { (c * factor).round() } else { c * factor };
+        Self {
+            stocking: self.stocking.iter().copied().map(scale).collect(),
+            changeover: self.changeover.iter().map(|row| row.iter().copied().map(scale).collect()).collect(),
+            second_order_changeover: self.second_order_changeover.as_ref()
+                .map(|costs| costs.iter().map(|rows| rows.iter().map(|row| row.iter().copied().map(scale).collect()).collect()).collect()),
+            overtime_cost: self.overtime_cost.map(scale),
+            idle_cost: self.idle_cost.map(scale),
+            cost_scale: Some(self.cost_scale.unwrap_or(1.0) * factor),
+            ..self.clone()
+        }
+    }
+
+    /// Returns a copy of the instance whose items are given in the order of the given
+    /// permutation (item i becoming item order[i]), whose costs are multiplied by the
+    /// given factor, and without item names, period labels nor cost scale
+    pub fn anonymized(&self, order: &[usize], factor: f64) -> Self {
+        let permute = |values: &[usize]| {
+            let mut permuted = vec![0; values.len()];
+            values.iter().enumerate().for_each(|(i, v)| permuted[order[i]] = *v);
+            permuted
+        };
+        let permute_rows = |rows: &Vec<Vec<usize>>| {
+            let mut permuted = vec![vec![]; rows.len()];
+            rows.iter().enumerate().for_each(|(i, row)| permuted[order[i]] = row.clone());
+            permuted
+        };
+
+        let mut stocking = vec![0.0; self.nb_types];
+        let mut changeover = vec![vec![0.0; self.nb_types]; self.nb_types];
+        for a in 0..self.nb_types {
+            stocking[order[a]] = self.stocking[a] * factor;
+            for b in 0..self.nb_types {
+                changeover[order[a]][order[b]] = self.changeover[a][b] * factor;
+            }
+        }
+        let second_order_changeover = self.second_order_changeover.as_ref().map(|costs| {
+            let mut permuted = vec![vec![vec![0.0; self.nb_types]; self.nb_types]; self.nb_types];
+            for a in 0..self.nb_types {
+                for b in 0..self.nb_types {
+                    for c in 0..self.nb_types {
+                        permuted[order[a]][order[b]][order[c]] = costs[a][b][c] * factor;
+                    }
+                }
+            }
+            permuted
+        });
+        let demand = |d: DemandRef| DemandRef { item: order[d.item], period: d.period };
+
+        Self {
+            stocking,
+            changeover,
+            second_order_changeover,
+            overtime_cost: self.overtime_cost.map(|c| c * factor),
+            idle_cost: self.idle_cost.map(|c| c * factor),
+            demands: permute_rows(&self.demands),
+            sparse_demands: self.sparse_demands.as_ref().map(|lists| {
+                let mut permuted = vec![vec![]; lists.len()];
+                lists.iter().enumerate().for_each(|(i, list)| permuted[order[i]] = list.clone());
+                permuted
+            }),
+            earliest: self.earliest.as_ref().map(permute_rows),
+            item_names: None,
+            period_labels: None,
+            max_stock: self.max_stock.as_deref().map(permute),
+            forbidden_transitions: self.forbidden_transitions.as_ref()
+                .map(|pairs| pairs.iter().map(|(a, b)| (order[*a], order[*b])).collect()),
+            precedences: self.precedences.as_ref()
+                .map(|precedences| precedences.iter().map(|p| Precedence { before: demand(p.before), after: demand(p.after) }).collect()),
+            cost_scale: None,
+            ..self.clone()
+        }
+    }
+
+    /// Returns the instance whose horizon is repeated the given number of times: its
+    /// demands and precedences are repeated in each copy of the horizon
+    pub fn tiled(&self, times: usize) -> Self {
+        let h = self.nb_periods;
+        let lists = self.demand_lists().into_iter()
+            .map(|demands| (0..times)
+                .flat_map(|k| demands.iter().map(move |d| SparseDemand {
+                    period: d.period + k * h,
+                    quantity: d.quantity,
+                    earliest: d.earliest.map(|e| e + k * h),
+                }))
+                .collect())
+            .collect();
+        let shift = |d: DemandRef, k: usize| DemandRef { item: d.item, period: d.period + k * h };
+
+        Self {
+            nb_periods: h * times,
+            availability: self.availability.as_ref().map(|a| a.repeat(times)),
+            demands: vec![],
+            sparse_demands: Some(lists),
+            earliest: None,
+            period_labels: self.period_labels.as_ref()
+                .map(|labels| (0..times).flat_map(|k| labels.iter().map(move |l| if k == 0 { l.clone() } else { format!("{l}+{k}") })).collect()),
+            precedences: self.precedences.as_ref()
+                .map(|precedences| (0..times)
+                    .flat_map(|k| precedences.iter().map(move |p| Precedence { before: shift(p.before, k), after: shift(p.after, k) }))
+                    .collect()),
+            ..self.clone()
+        }
+    }
+
+    /// Returns the instance where each item is replaced by a cluster of the given number
+    /// of copies, which serve its demands in turn, so that the load of the machine is
+    /// unchanged. The costs of the copies are those of the item, multiplied by random
+    /// factors between 1 - jitter and 1 + jitter, and switching between two copies of
+    /// an item costs about its cheapest changeover to another item. The second-order
+    /// changeovers of the copies are those of their items
+    pub fn with_copies(&self, copies: usize, jitter: f64, rng: &mut impl Rng) -> Self {
+        let n = self.nb_types;
+        let copies = copies.max(1);
+        let item = |c: usize, i: usize| c * n + i;
+        let mut noise = || if jitter > 0.0 { 1.0 + rng.gen_range(-jitter..=jitter) } else { 1.0 };
+
+        let cheapest = (0..n)
+            .map(|a| (0..n).filter(|b| *b != a).map(|b| self.changeover[a][b]).fold(f64::INFINITY, f64::min))
+            .map(|c| if c.is_finite() { c } else { 0.0 })
+            .collect::<Vec<f64>>();
+        let mut stocking = vec![0.0; n * copies];
+        let mut changeover = vec![vec![0.0; n * copies]; n * copies];
+        for c in 0..copies {
+            for a in 0..n {
+                stocking[item(c, a)] = self.stocking[a] * if c == 0 { 1.0 } else { noise() };
+                for d in 0..copies {
+                    for b in 0..n {
+                        changeover[item(c, a)][item(d, b)] = match (a == b, c == d) {
+                            (true, true) => self.changeover[a][a],
+                            (true, false) => cheapest[a] * noise(),
+                            (false, _) if c == 0 && d == 0 => self.changeover[a][b],
+                            (false, _) => self.changeover[a][b] * noise(),
+                        };
+                    }
+                }
+            }
+        }
+
+        // the k-th demand of an item goes to its copy k modulo the number of copies
+        let mut lists = vec![vec![]; n * copies];
+        let mut owner = std::collections::HashMap::new();
+        for (i, demands) in self.demand_lists().into_iter().enumerate() {
+            for (k, d) in demands.into_iter().enumerate() {
+                owner.insert((i, d.period), item(k % copies, i));
+                lists[item(k % copies, i)].push(d);
+            }
+        }
+        let demand = |d: DemandRef| DemandRef { item: owner.get(&(d.item, d.period)).copied().unwrap_or(d.item), period: d.period };
+
+        Self {
+            nb_types: n * copies,
+            stocking,
+            changeover,
+            second_order_changeover: self.second_order_changeover.as_ref()
+                .map(|costs| (0..n * copies)
+                    .map(|a| (0..n * copies).map(|b| (0..n * copies).map(|c| costs[a % n][b % n][c % n]).collect()).collect())
+                    .collect()),
+            demands: vec![],
+            sparse_demands: Some(lists),
+            earliest: None,
+            item_names: self.item_names.as_ref()
+                .map(|names| (0..copies).flat_map(|c| names.iter().map(move |name| if c == 0 { name.clone() } else { format!("{name}#{c}") })).collect()),
+            max_stock: self.max_stock.as_ref().map(|max| (0..copies).flat_map(|_| max.iter().copied()).collect()),
+            forbidden_transitions: self.forbidden_transitions.as_ref()
+                .map(|pairs| pairs.iter()
+                    .flat_map(|(a, b)| (0..copies).flat_map(move |c| (0..copies).map(move |d| (item(c, *a), item(d, *b)))))
+                    .collect()),
+            precedences: self.precedences.as_ref()
+                .map(|precedences| precedences.iter().map(|p| Precedence { before: demand(p.before), after: demand(p.after) }).collect()),
+            ..self.clone()
+        }
+    }
+
+    /// Returns the SHA-256 hash of the content of the instance that defines its schedules
+    /// and their costs, in hexadecimal. It does not depend on the representation of the
+    /// demands, the order of the constraints, nor the names of the items and periods
+    pub fn fingerprint(&self) -> String {
+        let mut forbidden_transitions = self.forbidden_transitions.clone().unwrap_or_default();
+        forbidden_transitions.sort_unstable();
+        forbidden_transitions.dedup();
+        let mut precedences = self.precedences.clone().unwrap_or_default();
+        precedences.sort_unstable_by_key(|p| (p.before.item, p.before.period, p.after.item, p.after.period));
+        precedences.dedup();
+
+        // the negative zeros are written as the positive ones
+        let cost = |c: &f64| c + 0.0;
+        let canonical = Canonical {
+            nb_types: self.nb_types,
+            nb_periods: self.nb_periods,
+            stocking: self.stocking.iter().map(cost).collect(),
+            changeover: self.changeover.iter().map(|row| row.iter().map(cost).collect()).collect(),
+            second_order_changeover: self.second_order_changeover.as_ref()
+                .filter(|costs| costs.iter().flatten().flatten().any(|c| *c != 0.0))
+                .map(|costs| costs.iter().map(|rows| rows.iter().map(|row| row.iter().map(cost).collect()).collect()).collect()),
+            demands: self.demand_lists().into_iter()
+                .map(|demands| demands.into_iter()
+                    .map(|d| SparseDemand { earliest: d.earliest.filter(|e| *e != d.period), ..d })
+                    .collect())
+                .collect(),
+            max_stock: self.max_stock.clone().unwrap_or_else(|| vec![usize::MAX; self.nb_types]),
+            storage_capacity: self.storage_capacity.unwrap_or(usize::MAX),
+            idle_setup: self.idle_setup.unwrap_or_default(),
+            availability: self.availability.clone().filter(|a| a.iter().any(|up| !up)),
+            overtime_cost: self.overtime_cost.as_ref().map(cost),
+            idle_cost: self.idle_cost.as_ref().map(cost),
+            forbidden_transitions,
+            precedences,
+        };
+        let json = serde_json::to_vec(&canonical).expect("an instance can always be serialized");
+        Sha256::digest(json).iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    /// Returns all the stocking, changeover, overtime and idle costs
+    pub fn costs(&self) -> impl Iterator<Item = f64> + '_ {
+        self.stocking.iter().chain(self.changeover.iter().flatten())
+            .chain(self.second_order_changeover.iter().flatten().flatten().flatten())
+            .chain(self.overtime_cost.iter()).chain(self.idle_cost.iter()).copied()
+    }
+}
+
+/// The content of an instance that is hashed by its fingerprint, with the default values
+/// made explicit
+#[derive(Serialize)]
+struct Canonical {
+    nb_types: usize,
+    nb_periods: usize,
+    stocking: Vec<f64>,
+    changeover: Vec<Vec<f64>>,
+    /// Only hashed when some changeover depends on the last two items
+    #[serde(skip_serializing_if = "Option::is_none")]
+    second_order_changeover: Option<Vec<Vec<Vec<f64>>>>,
+    demands: Vec<Vec<SparseDemand>>,
+    max_stock: Vec<usize>,
+    storage_capacity: usize,
+    idle_setup: IdleSetup,
+    /// Only hashed when the machine is down at some period, so that the fingerprints of
+    /// the instances where it is always available do not change
+    #[serde(skip_serializing_if = "Option::is_none")]
+    availability: Option<Vec<bool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    overtime_cost: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    idle_cost: Option<f64>,
+    forbidden_transitions: Vec<(usize, usize)>,
+    precedences: Vec<Precedence>,
+}
+
+fn is_integral(x: f64) -> bool {
+    (x - x.round()).abs() <= 1e-9 * x.abs().max(1.0)
+}
+
+/// A cost that is written as an integer whenever it is integral
+struct Cost(f64);
+
+impl Serialize for Cost {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.0.fract() == 0.0 && self.0.abs() < 1e15 {
+            serializer.serialize_i64(self.0 as i64)
+        } else {
+            serializer.serialize_f64(self.0)
+        }
+    }
+}
+
+fn serialize_costs<S: Serializer>(costs: &[f64], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(costs.iter().copied().map(Cost))
+}
+
+fn serialize_cost_matrix<S: Serializer>(costs: &[Vec<f64>], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(costs.iter().map(|row| row.iter().copied().map(Cost).collect::<Vec<Cost>>()))
+}
+
+/// The instances shared by the tests of the modules
+#[cfg(test)]
+pub mod fixtures {
+    use serde_json::{json, Value};
+
+    use super::PspInstance;
+
+    /// Two items with changeovers of 10 and unit stocking costs over 4 periods: the first
+    /// item is demanded at periods 1 and 3, the second one at period 2. The cheapest
+    /// schedule is [1, 0, -1, 0], of cost 12
+    pub fn instance() -> Value {
+        json!({
+            "nb_types": 2,
+            "nb_periods": 4,
+            "stocking": [1, 1],
+            "changeover": [[0, 10], [10, 0]],
+            "demands": [[0, 1, 0, 1], [0, 0, 1, 0]],
+        })
+    }
+
+    /// Returns the instance with the given fields replaced
+    pub fn with(mut instance: Value, fields: Value) -> Value {
+        instance.as_object_mut().unwrap().extend(fields.as_object().unwrap().clone());
+        instance
+    }
+
+    /// Reads the given instance
+    pub fn parse(instance: Value) -> PspInstance {
+        serde_json::from_value(instance).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// Two items over 4 periods, the first one demanded at periods 1 and 3 and the second
+    /// one at period 2
+    fn instance() -> PspInstance {
+        serde_json::from_value(json!({
+            "nb_types": 2,
+            "nb_periods": 4,
+            "stocking": [1, 2],
+            "changeover": [[0, 10], [20, 0]],
+            "demands": [[0, 1, 0, 1], [0, 0, 1, 0]],
+        })).unwrap()
+    }
+
+    #[test]
+    fn unbounded_max_stocks_keep_the_fingerprint() {
+        let bounded = PspInstance { max_stock: Some(vec![1, usize::MAX]), ..instance() };
+        let unbounded = PspInstance { max_stock: Some(vec![usize::MAX; 2]), ..instance() };
+        assert_eq!(unbounded.fingerprint(), instance().fingerprint());
+        assert_ne!(bounded.fingerprint(), instance().fingerprint());
+        assert_eq!(bounded.anonymized(&[1, 0], 1.0).max_stock, Some(vec![usize::MAX, 1]));
+    }
+
+    #[test]
+    fn storage_capacity_is_tiled() {
+        let capped = PspInstance { storage_capacity: Some(1), ..instance() };
+        assert_ne!(capped.fingerprint(), instance().fingerprint());
+        assert_eq!(capped.tiled(2).storage_capacity, Some(1));
+        assert_eq!(capped.tiled(2).demand_table()[1], vec![0, 0, 1, 0, 0, 0, 1, 0]);
+    }
+
+    #[test]
+    fn the_setup_is_kept_by_default() {
+        let kept = PspInstance { idle_setup: Some(IdleSetup::Kept), ..instance() };
+        let lost = PspInstance { idle_setup: Some(IdleSetup::Lost), ..instance() };
+        assert_eq!(kept.fingerprint(), instance().fingerprint());
+        assert_ne!(lost.fingerprint(), instance().fingerprint());
+        assert_eq!(serde_json::to_value(&lost).unwrap()["idle_setup"], json!("lost"));
+    }
+
+    #[test]
+    fn sequencing_constraints_follow_the_items() {
+        let before = DemandRef { item: 0, period: 3 };
+        let after = DemandRef { item: 1, period: 2 };
+        let constrained = PspInstance {
+            forbidden_transitions: Some(vec![(1, 0), (0, 1), (1, 0)]),
+            precedences: Some(vec![Precedence { before, after }]),
+            ..instance()
+        };
+        let reordered = PspInstance { forbidden_transitions: Some(vec![(0, 1), (1, 0)]), ..constrained.clone() };
+        assert_eq!(constrained.fingerprint(), reordered.fingerprint());
+
+        let anonymized = constrained.anonymized(&[1, 0], 1.0);
+        assert_eq!(anonymized.forbidden_transitions, Some(vec![(0, 1), (1, 0), (0, 1)]));
+        assert_eq!(anonymized.precedences, Some(vec![Precedence { before: DemandRef { item: 1, period: 3 }, after: DemandRef { item: 0, period: 2 } }]));
+    }
+
+    #[test]
+    fn availability_is_only_hashed_with_downtime() {
+        let available = PspInstance { availability: Some(vec![true; 4]), ..instance() };
+        let down = PspInstance { availability: Some(vec![true, false, true, true]), ..instance() };
+        assert_eq!(available.fingerprint(), instance().fingerprint());
+        assert_ne!(down.fingerprint(), instance().fingerprint());
+        assert_eq!(down.tiled(2).availability, Some(vec![true, false, true, true, true, false, true, true]));
+    }
+
+    #[test]
+    fn overtime_cost_is_scaled() {
+        let overtime = PspInstance { overtime_cost: Some(0.5), ..instance() };
+        assert_eq!(instance().precision(), 0);
+        assert_eq!(overtime.precision(), 1);
+        assert_eq!(overtime.rescaled(4.0, false).overtime_cost, Some(2.0));
+        assert_eq!(overtime.anonymized(&[1, 0], 2.0).overtime_cost, Some(1.0));
+    }
+
+    #[test]
+    fn idle_cost_is_scaled() {
+        let idle = PspInstance { idle_cost: Some(0.25), ..instance() };
+        assert_eq!(idle.precision(), 2);
+        assert_eq!(idle.rescaled(4.0, false).idle_cost, Some(1.0));
+        assert_ne!(idle.fingerprint(), instance().fingerprint());
+    }
+
+    #[test]
+    fn second_order_changeovers_follow_the_items() {
+        let mut costs = vec![vec![vec![0.0; 2]; 2]; 2];
+        let zero = PspInstance { second_order_changeover: Some(costs.clone()), ..instance() };
+        costs[0][1][0] = 7.0;
+        let second_order = PspInstance { second_order_changeover: Some(costs), ..instance() };
+        assert_eq!(zero.fingerprint(), instance().fingerprint());
+        assert_ne!(second_order.fingerprint(), instance().fingerprint());
+
+        let anonymized = second_order.anonymized(&[1, 0], 2.0).second_order_changeover.unwrap();
+        assert_eq!(anonymized[1][0][1], 14.0);
+        assert_eq!(anonymized.iter().flatten().flatten().sum::<f64>(), 14.0);
+    }
 }
@@ -0,0 +1,11 @@
+//! The library behind the psp binary: the representation of the instances, their
+//! generation and conversion, and their resolution. The benchmarks use it to measure
+//! the resolution in their own process.
+
+pub mod error;
+pub mod instance;
+pub mod solution;
+pub mod constraints;
+pub mod generate;
+pub mod convert;
+pub mod resolution;
@@ -1,10 +1,14 @@
-use clap::{Parser, Subcommand};
-use generate::PspGenerator;
-use resolution::Solve;
+use std::{fs::File, process::ExitCode, sync::Mutex};
 
-mod instance;
-mod generate;
-mod resolution;
+use clap::{Parser, Subcommand};
+use tracing::Level;
+use tracing_subscriber::{filter::{LevelFilter, Targets}, prelude::*};
+use psp::error::PspError;
+use psp::generate::PspGenerator;
+use psp::convert::{Convert, Import};
+use psp::resolution::{Solve, Bound, Analyze, Compare, Validate, Tune, Bench, Selftest, Admissibility, Whatif, Simulate, Split, Stitch, EVENTS};
+#[cfg(feature = "serve")]
+use psp::resolution::Serve;
 
 #[derive(Debug, Parser)]
 #[command(author, version, about, long_about = None)]
@@ -12,18 +16,105 @@ mod resolution;
 struct PspTools {
     #[command(subcommand)]
     command: Command,
+    /// Logs more details (-v for debug, -vv for trace)
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+    /// Only logs the errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+    /// Logs in the json format
+    #[arg(long, global = true)]
+    log_json: bool,
+}
+
+impl PspTools {
+    /// Installs the subscriber writing the logs to stderr, with the requested verbosity,
+    /// and the events of the resolution to the event log of the solve subcommand, if any
+    fn init_logging(&self) -> Result<(), PspError> {
+        let level = match (self.quiet, self.verbose) {
+            (true, _) => Level::ERROR,
+            (false, 0) => Level::INFO,
+            (false, 1) => Level::DEBUG,
+            (false, _) => Level::TRACE,
+        };
+        let logs = tracing_subscriber::fmt::layer().with_writer(std::io::stderr);
+        let logs = if self.log_json { logs.json().boxed() } else { logs.boxed() };
+        let logs = logs.with_filter(Targets::new().with_default(level).with_target(EVENTS, LevelFilter::OFF));
+
+        let events = match &self.command {
+            Command::Solve(solve) => solve.events.as_ref(),
+            _ => None,
+        };
+        let events = events
+            .map(|path| File::create(path).map_err(|source| PspError::Io { path: path.clone(), source }))
+            .transpose()?
+            .map(|file| tracing_subscriber::fmt::layer()
+                .json()
+                .flatten_event(true)
+                .with_writer(Mutex::new(file))
+                .with_filter(Targets::new().with_target(EVENTS, Level::INFO)));
+
+        tracing_subscriber::registry().with(logs).with(events).init();
+        Ok(())
+    }
 }
 
 #[derive(Debug, Subcommand)]
 enum Command {
     Generate(PspGenerator),
-    Solve(Solve)
+    #[command(after_help = "Exit codes: 0 optimal, 3 feasible but not proved optimal, 4 infeasible, 5 no solution found, 1 error")]
+    Solve(Solve),
+    Bound(Bound),
+    Analyze(Analyze),
+    Compare(Compare),
+    Validate(Validate),
+    Convert(Convert),
+    Import(Import),
+    Tune(Tune),
+    Bench(Bench),
+    #[command(after_help = "Exit codes: 0 if the solvers always agree, 1 otherwise or on error")]
+    Selftest(Selftest),
+    #[command(after_help = "Exit codes: 0 if all the bounds are admissible, 1 otherwise or on error")]
+    Admissibility(Admissibility),
+    Whatif(Whatif),
+    Simulate(Simulate),
+    Split(Split),
+    Stitch(Stitch),
+    #[cfg(feature = "serve")]
+    Serve(Serve),
 }
 
-fn main() {
+fn main() -> ExitCode {
     let cli = PspTools::parse();
-    match cli.command {
-        Command::Generate(mut generate) => generate.generate(),
-        Command::Solve(solve) => solve.solve()
+    if let Err(error) = cli.init_logging() {
+        eprintln!("{error}");
+        return ExitCode::from(error.exit_code());
+    }
+    let result = match cli.command {
+        Command::Generate(mut generate) => generate.generate().map(|_| 0),
+        Command::Solve(solve) => solve.solve().map(|status| status.exit_code()),
+        Command::Bound(bound) => bound.bound().map(|_| 0),
+        Command::Analyze(analyze) => analyze.analyze().map(|_| 0),
+        Command::Compare(compare) => compare.compare().map(|_| 0),
+        Command::Validate(validate) => validate.validate().map(|_| 0),
+        Command::Convert(convert) => convert.convert().map(|_| 0),
+        Command::Import(import) => import.import().map(|_| 0),
+        Command::Tune(tune) => tune.tune().map(|_| 0),
+        Command::Bench(bench) => bench.bench().map(|_| 0),
+        Command::Selftest(selftest) => selftest.selftest().map(|mismatches| u8::from(mismatches > 0)),
+        Command::Admissibility(admissibility) => admissibility.check().map(|failures| u8::from(failures > 0)),
+        Command::Whatif(whatif) => whatif.whatif().map(|_| 0),
+        Command::Simulate(simulate) => simulate.simulate().map(|_| 0),
+        Command::Split(split) => split.split().map(|_| 0),
+        Command::Stitch(stitch) => stitch.stitch().map(|_| 0),
+        #[cfg(feature = "serve")]
+        Command::Serve(serve) => serve.serve().map(|_| 0),
+    };
+    match result {
+        Ok(code) => ExitCode::from(code),
+        Err(error) => {
+            tracing::error!("{error}");
+            ExitCode::from(error.exit_code())
+        },
     }
 }
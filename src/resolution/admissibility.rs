@@ -0,0 +1,103 @@
+//! This module implements a debugging subcommand that checks the admissibility of the
+//! bounds of the relaxation. Subproblems are sampled by random walks in the DP model from
+//! the root, and the optimal completion of each of them is computed by brute force. The
+//! rough upper bound of the state reached, and the relaxed DD bound of the problem whose
+//! decisions of the walk are fixed, must never be below the value of that completion.
+
+use clap::Args;
+use ddo::{Decision, NoCutoff, Problem, Relaxation};
+use rand::{Rng, SeedableRng, seq::SliceRandom};
+use rand_chacha::ChaChaRng;
+use tracing::info;
+
+use crate::error::PspError;
+use crate::resolution::exhaustive::{exhaustive, EXHAUSTIVE_MAX_PERIODS};
+use crate::resolution::model::{Psp, PspState, PspRanking};
+use crate::resolution::relaxed::relaxed_dd;
+use crate::resolution::solve::Solve;
+
+#[derive(Debug, Args)]
+pub struct Admissibility {
+    /// The path to the instance file (- for the standard input)
+    #[clap(short, long)]
+    pub instance: String,
+    /// The number of sampled subproblems
+    #[clap(long, default_value="100")]
+    pub samples: usize,
+    /// The seed of the random walks
+    #[clap(long, default_value="0")]
+    pub seed: u64,
+    /// The options of the solve subcommand that define the relaxation and the width of
+    /// the relaxed DDs, e.g. "-w 2 --lagrangian"
+    #[clap(long, default_value = "", allow_hyphen_values = true)]
+    pub options: String,
+}
+
+/// A subproblem reached by a random walk
+struct Sample {
+    state: PspState,
+    /// The value of the walk from the root
+    value: isize,
+    /// The problem whose decisions of the walk are fixed
+    problem: Psp,
+}
+
+impl Admissibility {
+    /// Checks the bounds on the sampled subproblems, and prints those that are not
+    /// admissible. Returns their number
+    pub fn check(&self) -> Result<usize, PspError> {
+        let problem = Psp::from_file(&self.instance)?;
+        let mut args = vec!["admissibility".to_string(), "-i".to_string(), self.instance.clone()];
+        args.extend(self.options.split_whitespace().map(|a| a.to_string()));
+        let solve = Solve::from_args(args).map_err(|e| PspError::Invalid(format!("invalid solve options: {e}")))?;
+        let relaxation = solve.relaxation(&problem)?;
+
+        let mut rng = ChaChaRng::seed_from_u64(self.seed);
+        let (mut checked, mut failures) = (0, 0);
+        for k in 0..self.samples {
+            // the brute force enumerates the periods that are not fixed by the walk
+            let free = rng.gen_range(0..=problem.horizon.min(EXHAUSTIVE_MAX_PERIODS));
+            let Some(sample) = Self::walk(&problem, problem.horizon - free, &mut rng) else {
+                continue;
+            };
+            let (_, best) = exhaustive(&sample.problem, &NoCutoff);
+            let Some((best, _)) = best else {
+                continue;
+            };
+            checked += 1;
+
+            let completion = best - sample.value;
+            let rough = relaxation.fast_upper_bound(&sample.state);
+            if rough < completion {
+                failures += 1;
+                println!("sample {k}: the rough upper bound {rough} at time {} is below the best completion {completion}", sample.state.time);
+            }
+            let relaxed = relaxed_dd(&sample.problem, &relaxation, &PspRanking, &solve.selection, solve.width, &NoCutoff);
+            if relaxed.map_or(true, |bound| bound < best) {
+                failures += 1;
+                println!("sample {k}: the relaxed DD bound {relaxed:?} at time {} is below the best value {best}", sample.state.time);
+            }
+        }
+
+        info!(samples = self.samples, checked, failures, "admissibility checked");
+        Ok(failures)
+    }
+
+    /// Takes the given number of random decisions from the root of the problem. Returns
+    /// None if the walk reaches a state without decision
+    fn walk(problem: &Psp, length: usize, rng: &mut ChaChaRng) -> Option<Sample> {
+        let mut sample = Sample { state: problem.initial_state(), value: problem.initial_value(), problem: problem.clone() };
+        sample.problem.clear_symmetries();
+        for depth in 0..length {
+            let variable = problem.next_variable(depth, &mut std::iter::once(&sample.state))?;
+            let mut decisions = vec![];
+            problem.for_each_in_domain(variable, &sample.state, &mut |decision: Decision| decisions.push(decision));
+            let decision = *decisions.choose(rng)?;
+
+            sample.value += problem.transition_cost(&sample.state, decision);
+            sample.state = problem.transition(&sample.state, decision);
+            sample.problem.fixed[variable.id()] = Some(decision.value);
+        }
+        Some(sample)
+    }
+}
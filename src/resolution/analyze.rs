@@ -0,0 +1,154 @@
+use std::time::Duration;
+
+use clap::Args;
+use ddo::{Problem, Relaxation, TimeBudget};
+
+use crate::error::PspError;
+use crate::resolution::compression::{CompressionArgs, CompressedSolutionBound, PspCompression};
+use crate::resolution::heuristic::greedy;
+use crate::resolution::model::{Psp, PspRelax};
+
+#[derive(Debug, Args)]
+pub struct Analyze {
+    /// The path to the instance file (- for the standard input)
+    #[clap(short, long)]
+    pub instance: String,
+    /// If present, the clustering of the compression is described
+    #[clap(long)]
+    pub compression: bool,
+    /// max number of nodes in a layer of the restricted DD that decompresses the meta-solutions
+    #[clap(short, long, default_value="100")]
+    pub width: usize,
+    /// timeout of each decompression
+    #[clap(short, long, default_value="60")]
+    pub timeout: u64,
+    #[clap(flatten)]
+    pub compression_args: CompressionArgs,
+    /// If present, only the fingerprint of the instance is printed
+    #[clap(long)]
+    pub fingerprint: bool,
+}
+
+impl Analyze {
+    pub fn analyze(&self) -> Result<(), PspError> {
+        let problem = Psp::from_file(&self.instance)?;
+        if self.fingerprint {
+            println!("{}", problem.fingerprint);
+            return Ok(());
+        }
+
+        Self::report_instance(&problem);
+
+        if self.compression {
+            let compressions = self.compression_args.compressions(&problem)?;
+            let mut previous = &problem;
+            for (level, compression) in compressions.iter().enumerate() {
+                println!("compression level {level}");
+                self.report_compression(previous, compression);
+                previous = &compression.meta_problem;
+            }
+        }
+        Ok(())
+    }
+
+    /// Prints the size of the instance, its demands, its costs and trivial bounds on its optimal cost
+    fn report_instance(problem: &Psp) {
+        let n = problem.n_items;
+        println!("items {n}");
+        println!("horizon {}", problem.horizon);
+        println!("fingerprint {}", problem.fingerprint);
+
+        let counts = (0..n).map(|i| problem.demands_of(i).len()).collect::<Vec<usize>>();
+        let total = counts.iter().sum::<usize>();
+        println!("demands {total}");
+        println!("demand density {:.3}", total as f64 / (n * problem.horizon).max(1) as f64);
+        println!("demands per item {counts:?}");
+
+        let stocking = Spread::of(problem.stocking.iter().copied());
+        let changeover = Spread::of((0..n).flat_map(|a| (0..n).filter(move |b| a != *b).map(move |b| problem.changeover(a, b))));
+        println!("stocking costs {}", stocking.format(problem));
+        println!("changeover costs {}", changeover.format(problem));
+        println!("symmetric changeovers {}", (0..n).all(|a| (0..n).all(|b| problem.changeover(a, b) == problem.changeover(b, a))));
+        println!("triangle inequality {}", problem.satisfies_triangle_inequality());
+        if !problem.second_order.is_empty() {
+            let triples = (0..n).flat_map(|a| (0..n).filter(move |b| a != *b).flat_map(move |b| (0..n).filter(move |c| b != *c).map(move |c| (a, b, c))));
+            println!("second-order changeover costs {}", Spread::of(triples.map(|(a, b, c)| problem.second_order(a, b, c))).format(problem));
+        }
+
+        let root = problem.initial_state();
+        println!("trivial lower bound {}", problem.format_cost(-PspRelax::new(problem.clone()).fast_upper_bound(&root)));
+        match greedy(problem) {
+            Some((value, _)) => println!("greedy upper bound {}", problem.format_cost(-value)),
+            None => println!("greedy upper bound none"),
+        }
+    }
+
+    /// Describes the clusters of the given compression of the problem, the bound it gives at 
+    /// the root, and the cost of the decompressed meta-solution
+    fn report_compression(&self, problem: &Psp, compression: &PspCompression) {
+        let meta = &compression.meta_problem;
+        let members = |m: usize| (0..problem.n_items).filter(move |i| compression.membership[*i] == m);
+
+        println!("  meta-items {}", meta.n_items);
+        for m in 0..meta.n_items {
+            let items = members(m).collect::<Vec<usize>>();
+            let units = items.iter().map(|i| problem.demands_of(*i).iter().map(|d| d.quantity).sum::<usize>()).sum::<usize>();
+            let periods = meta.demands_of(m).len();
+            let within = Spread::of(items.iter().flat_map(|a| items.iter().filter(move |b| a != *b).map(move |b| problem.changeover(*a, *b))));
+
+            let representative = compression.representatives.as_ref().map_or(String::new(), |r| format!(", representative {}", r[m]));
+            println!("  meta-item {m}: items {items:?}{representative}, demanded units {units}, demanded periods {periods}, stocking {}, within changeover {}",
+                problem.format_cost(meta.stocking[m] as isize), within.format(problem));
+        }
+
+        let between = Spread::of((0..problem.n_items).flat_map(|a| (0..problem.n_items)
+            .filter(move |b| compression.membership[a] != compression.membership[*b])
+            .map(move |b| problem.changeover(a, b))));
+        println!("  between changeover {}", between.format(problem));
+
+        let bound = CompressedSolutionBound::new(compression.clone());
+        let root = problem.initial_state();
+        // the meta-problem of medoids is not a relaxation, its value is not a bound
+        let label = if compression.representatives.is_some() { "root meta value" } else { "root bound" };
+        match bound.get_ub(&root) {
+            Some(value) => println!("  {label} {}", problem.format_cost(-value)),
+            None => println!("  {label} none (infeasible)"),
+        }
+
+        let cutoff = TimeBudget::new(Duration::from_secs(self.timeout));
+        let decompressed = bound.meta_solution(&root)
+            .and_then(|solution| compression.decompress(problem, &solution, self.width, &cutoff));
+        match decompressed {
+            Some((value, _)) => println!("  decompressed solution {}", problem.format_cost(-value)),
+            None => println!("  decompressed solution none"),
+        }
+    }
+}
+
+/// The range and the average of some changeover costs
+struct Spread {
+    min: usize,
+    max: usize,
+    sum: usize,
+    count: usize,
+}
+
+impl Spread {
+    fn of(costs: impl Iterator<Item = usize>) -> Self {
+        costs.fold(Spread { min: usize::MAX, max: 0, sum: 0, count: 0 }, |s, c| Spread {
+            min: s.min.min(c),
+            max: s.max.max(c),
+            sum: s.sum.saturating_add(c),
+            count: s.count + 1,
+        })
+    }
+
+    fn format(&self, problem: &Psp) -> String {
+        if self.count == 0 {
+            "none".to_string()
+        } else {
+            let avg = self.sum as isize / self.count as isize;
+            format!("min {} avg {} max {}", problem.format_cost(self.min as isize), problem.format_cost(avg), problem.format_cost(self.max as isize))
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! This module implements a simulated annealing that starts from a feasible schedule
+//! and explores it by swapping the decisions of two random periods or by shifting a
+//! production to the closest idle period before or after it.
+
+use ddo::{Solution, Cutoff};
+use rand::Rng;
+
+use crate::resolution::local_search::swap_delta;
+use crate::resolution::model::{Psp, IDLE};
+
+/// The parameters of the temperature schedule
+#[derive(Debug, Clone, Copy)]
+pub struct Temperature {
+    /// The initial temperature
+    pub initial: f64,
+    /// The factor by which the temperature is multiplied after each move
+    pub cooling: f64,
+    /// The temperature under which the search stops
+    pub min: f64,
+}
+
+/// Runs the simulated annealing from the given solution and returns the value and the
+/// decisions of the best schedule encountered
+pub fn simulated_annealing(problem: &Psp, value: isize, solution: &Solution, temperature: Temperature, rng: &mut impl Rng, cutoff: &dyn Cutoff) -> (isize, Solution) {
+    let movable = (0..problem.horizon).filter(|t| problem.fixed[*t].is_none()).collect::<Vec<usize>>();
+    // the cost variations of the moves do not account for the units produced in overtime
+    if movable.len() < 2 || problem.overtime.is_some() {
+        return (value, solution.clone());
+    }
+
+    let mut schedule = problem.schedule(solution);
+    let mut cost = -value;
+    let mut best = (cost, schedule.clone());
+    let mut current = temperature.initial;
+    let mut iteration = 0_usize;
+
+    while current > temperature.min {
+        if iteration % 1000 == 0 && cutoff.must_stop() {
+            break;
+        }
+        iteration += 1;
+        current *= temperature.cooling;
+
+        let candidate = if rng.gen_bool(0.5) {
+            random_swap(&movable, rng)
+        } else {
+            random_shift(problem, &schedule, &movable, rng)
+        };
+        let Some((t1, t2)) = candidate else {
+            continue;
+        };
+        if schedule[t1] == schedule[t2] {
+            continue;
+        }
+
+        let delta = swap_delta(problem, &mut schedule, t1, t2);
+        if delta > 0 && rng.gen::<f64>() >= (-(delta as f64) / current).exp() {
+            continue;
+        }
+
+        schedule.swap(t1, t2);
+        let (moved_earlier, moved_later) = (schedule[t1], schedule[t2]);
+        if (moved_later != IDLE && !problem.is_item_feasible(&schedule, moved_later as usize))
+            || (moved_earlier != IDLE && !problem.respects_max_stock(&schedule, moved_earlier as usize))
+            || !problem.respects_capacity(&schedule)
+            || !problem.respects_sequencing(&schedule)
+            || problem.forbidden.contains(&schedule) {
+            schedule.swap(t1, t2);
+            continue;
+        }
+
+        cost += delta;
+        if cost < best.0 {
+            best = (cost, schedule.clone());
+        }
+    }
+
+    (-best.0, problem.solution(&best.1))
+}
+
+/// Returns two distinct movable periods in increasing order
+fn random_swap(movable: &[usize], rng: &mut impl Rng) -> Option<(usize, usize)> {
+    let a = movable[rng.gen_range(0..movable.len())];
+    let b = movable[rng.gen_range(0..movable.len())];
+    match a.cmp(&b) {
+        std::cmp::Ordering::Less => Some((a, b)),
+        std::cmp::Ordering::Greater => Some((b, a)),
+        std::cmp::Ordering::Equal => None,
+    }
+}
+
+/// Returns a movable production period and the closest movable idle period in a random
+/// direction, in increasing order
+fn random_shift(problem: &Psp, schedule: &[isize], movable: &[usize], rng: &mut impl Rng) -> Option<(usize, usize)> {
+    let t = movable[rng.gen_range(0..movable.len())];
+    if schedule[t] == IDLE {
+        return None;
+    }
+
+    let is_free = |u: &usize| schedule[*u] == IDLE && problem.fixed[*u].is_none();
+    if rng.gen_bool(0.5) {
+        (0..t).rev().find(is_free).map(|u| (u, t))
+    } else {
+        ((t + 1)..problem.horizon).find(is_free).map(|u| (t, u))
+    }
+}
@@ -0,0 +1,202 @@
+//! This module implements the arenas where the states of the restricted DDs and the
+//! subproblems of the fringe of the branch-and-bound are stored. Both are slabs of slots
+//! that are reused once their content is dropped, so that a wide layer or a large fringe
+//! does not allocate and free one node per state: the nodes of the layers only refer to
+//! the slots of their states, and the heap of the fringe to the slots of its subproblems.
+
+use std::{cmp::Ordering, collections::BinaryHeap, sync::Arc};
+
+use ddo::{Fringe, StateRanking, SubProblem};
+
+use crate::resolution::hashing::StateMap;
+use crate::resolution::model::{PspState, PspRanking};
+
+/// The states of the layers of a restricted DD being compiled
+#[derive(Default)]
+pub struct StateArena {
+    slots: Vec<Option<PspState>>,
+    free: Vec<usize>,
+}
+
+impl StateArena {
+    /// Stores the given state in a free slot, and returns that slot
+    pub fn alloc(&mut self, state: PspState) -> usize {
+        match self.free.pop() {
+            Some(slot) => {
+                self.slots[slot] = Some(state);
+                slot
+            },
+            None => {
+                self.slots.push(Some(state));
+                self.slots.len() - 1
+            },
+        }
+    }
+
+    /// Returns the state of the given slot
+    pub fn get(&self, slot: usize) -> &PspState {
+        self.slots[slot].as_ref().expect("the slots of the nodes are occupied")
+    }
+
+    /// Frees the given slot, whose state is dropped
+    pub fn release(&mut self, slot: usize) {
+        self.slots[slot] = None;
+        self.free.push(slot);
+    }
+}
+
+/// An entry of the heap of the fringe. It is stale once its slot is freed, or once the
+/// subproblem of its slot is improved by a duplicate, which pushes another entry
+struct Entry {
+    ub: isize,
+    value: isize,
+    state: Arc<PspState>,
+    slot: usize,
+}
+
+impl Entry {
+    fn new(node: &SubProblem<PspState>, slot: usize) -> Self {
+        Entry { ub: node.ub, value: node.value, state: node.state.clone(), slot }
+    }
+
+    /// Returns true iff the entry is the last one pushed for the given subproblem. The entry
+    /// holds its state, whose address cannot be given to the subproblem of a reused slot
+    fn is_current(&self, node: &SubProblem<PspState>) -> bool {
+        Arc::ptr_eq(&self.state, &node.state) && self.ub == node.ub && self.value == node.value
+    }
+}
+
+/// The subproblems with the largest upper bound come first, then those with the largest
+/// value, like with the MaxUB ranking of ddo
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.ub.cmp(&other.ub)
+            .then_with(|| self.value.cmp(&other.value))
+            .then_with(|| PspRanking.compare(&self.state, &other.state))
+    }
+}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Entry {}
+
+/// A fringe that pops the subproblems with the largest upper bound first, and merges the
+/// subproblems of the same state like the NoDupFringe of ddo: the best value and its path
+/// are kept, with the largest of their upper bounds
+#[derive(Default)]
+pub struct ArenaFringe {
+    slots: Vec<Option<SubProblem<PspState>>>,
+    free: Vec<usize>,
+    /// The slot of the subproblem of each state of the fringe
+    index: StateMap<Arc<PspState>, usize>,
+    heap: BinaryHeap<Entry>,
+}
+
+impl ArenaFringe {
+    /// Returns the largest upper bound of the subproblems of the fringe, if any
+    pub fn best_ub(&self) -> Option<isize> {
+        self.slots.iter().flatten().map(|node| node.ub).max()
+    }
+}
+
+impl Fringe for ArenaFringe {
+    type State = PspState;
+
+    fn push(&mut self, node: SubProblem<Self::State>) {
+        if let Some(slot) = self.index.get(node.state.as_ref()).copied() {
+            let old = self.slots[slot].as_mut().expect("the slots of the index are occupied");
+            let ub = old.ub.max(node.ub);
+            if node.value > old.value {
+                *old = node;
+            } else if ub == old.ub {
+                return;
+            }
+            old.ub = ub;
+            self.heap.push(Entry::new(old, slot));
+            return;
+        }
+
+        let slot = match self.free.pop() {
+            Some(slot) => slot,
+            None => {
+                self.slots.push(None);
+                self.slots.len() - 1
+            },
+        };
+        self.index.insert(node.state.clone(), slot);
+        self.heap.push(Entry::new(&node, slot));
+        self.slots[slot] = Some(node);
+    }
+
+    fn pop(&mut self) -> Option<SubProblem<Self::State>> {
+        while let Some(entry) = self.heap.pop() {
+            if !self.slots[entry.slot].as_ref().map_or(false, |node| entry.is_current(node)) {
+                continue;
+            }
+            let node = self.slots[entry.slot].take()?;
+            self.index.remove(node.state.as_ref());
+            self.free.push(entry.slot);
+            return Some(node);
+        }
+        None
+    }
+
+    fn clear(&mut self) {
+        self.slots.clear();
+        self.free.clear();
+        self.index.clear();
+        self.heap.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ddo::Problem;
+
+    use crate::instance::fixtures::{instance, parse};
+    use crate::resolution::model::Psp;
+    use super::*;
+
+    fn node(state: &PspState, value: isize, ub: isize) -> SubProblem<PspState> {
+        SubProblem { state: Arc::new(state.clone()), value, path: vec![], ub, depth: 0 }
+    }
+
+    #[test]
+    fn the_subproblems_of_a_state_are_merged() {
+        let root = Psp::try_from(parse(instance())).unwrap().initial_state();
+        let other = PspState { time: root.time - 1, ..root.clone() };
+
+        let mut fringe = ArenaFringe::default();
+        fringe.push(node(&root, -5, 0));
+        fringe.push(node(&other, -3, -1));
+        fringe.push(node(&root, -4, -2));
+        assert_eq!(fringe.len(), 2);
+
+        let first = fringe.pop().unwrap();
+        assert_eq!((first.value, first.ub), (-4, 0));
+        assert_eq!(fringe.pop().unwrap().value, -3);
+        assert!(fringe.pop().is_none());
+
+        // the slots of the popped subproblems are reused
+        fringe.push(node(&root, -5, 0));
+        assert_eq!(fringe.slots.len(), 2);
+    }
+}
@@ -0,0 +1,234 @@
+//! This module implements the benchmark subcommand: every instance is solved several 
+//! times with the same options, and the outcome of each run is printed in the csv format.
+//! The results can also be summarized in a LaTeX table, with one row per instance, and 
+//! stored in a SQLite database when the sqlite feature is enabled. The times can be
+//! compared to those of a previous benchmark, e.g. of another build of the crate.
+
+use std::{collections::HashMap, fmt::Write, time::Instant};
+
+use clap::{Args, ValueEnum};
+use tracing::{info, warn};
+
+use crate::error::{PspError, read_file, write_file};
+#[cfg(feature = "sqlite")]
+use crate::resolution::database::ResultsDb;
+use crate::resolution::model::Psp;
+use crate::resolution::monitor::Monitor;
+use crate::resolution::solve::Solve;
+use crate::solution::SolveStatus;
+
+/// A column of the LaTeX table
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Column {
+    /// the name of the instance file
+    Instance,
+    /// the status of the resolution
+    Status,
+    /// the best cost found
+    Best,
+    /// the best lower bound proved
+    Bound,
+    /// the relative gap between the best cost and the bound
+    Gap,
+    /// the average resolution time
+    Time,
+    /// the average number of nodes explored by the branch-and-bound
+    Nodes,
+}
+
+#[derive(Debug, Args)]
+pub struct Bench {
+    /// The paths to the instance files
+    #[clap(required = true)]
+    pub instances: Vec<String>,
+    /// The options of the solve subcommand used for every run, e.g. "-w 100 -t 10 --solver lns"
+    #[clap(long, default_value = "", allow_hyphen_values = true)]
+    pub options: String,
+    /// The number of runs on each instance
+    #[clap(long, default_value = "1", value_parser = clap::value_parser!(u32).range(1..))]
+    pub runs: u32,
+    /// If present, the path where to write the table of the results, in the LaTeX format
+    #[clap(long)]
+    pub latex: Option<String>,
+    /// The columns of the LaTeX table
+    #[clap(long, value_enum, value_delimiter = ',', default_values_t = [Column::Instance, Column::Best, Column::Bound, Column::Gap, Column::Time, Column::Nodes])]
+    pub columns: Vec<Column>,
+    /// If present, the path of a SQLite database where a row is stored for each run. A run
+    /// that is repeated with the same instance, options and crate version replaces the 
+    /// previous one
+    #[cfg(feature = "sqlite")]
+    #[clap(long)]
+    pub db: Option<String>,
+    /// If present, the path to the csv output of a previous benchmark: the average time
+    /// on each instance is compared to the one of that benchmark
+    #[clap(long)]
+    pub baseline: Option<String>,
+}
+
+/// The outcome of a single run
+pub struct Run {
+    pub status: SolveStatus,
+    /// The (scaled) cost of the best solution found
+    pub cost: Option<isize>,
+    /// The (scaled) lower bound on the cost of the solutions
+    pub bound: Option<isize>,
+    pub gap: Option<f64>,
+    pub time: f64,
+    pub nodes: usize,
+}
+
+impl Bench {
+    pub fn bench(&self) -> Result<(), PspError> {
+        let mut results = vec![];
+        #[cfg(feature = "sqlite")]
+        let db = self.db.as_deref().map(ResultsDb::open).transpose()?;
+
+        println!("instance,run,status,cost,bound,gap,time,nodes,fingerprint");
+        for path in self.instances.iter() {
+            let solve = self.solve(path)?;
+            let problem = Psp::from_file(path)?;
+
+            let mut runs = vec![];
+            for run in 0..self.runs {
+                let monitor = Monitor::new();
+                let start = Instant::now();
+                let resolution = solve.solve_problem(&problem, Some(&monitor))?;
+                let result = Run {
+                    status: resolution.status(),
+                    cost: resolution.incumbent.as_ref().map(|(value, _)| -value),
+                    bound: resolution.lower_bound(),
+                    gap: resolution.gap(),
+                    time: start.elapsed().as_secs_f64(),
+                    nodes: monitor.explored(),
+                };
+
+                let format = |cost: Option<isize>| cost.map_or(String::new(), |c| problem.format_cost(c));
+                println!("{path},{run},{},{},{},{},{:.3},{},{}", result.status.name(), format(result.cost), format(result.bound),
+                    result.gap.map_or(String::new(), |g| format!("{g:.6}")), result.time, result.nodes, problem.fingerprint);
+                #[cfg(feature = "sqlite")]
+                if let Some(db) = db.as_ref() {
+                    db.insert(path, &self.options, run, &problem, &result)?;
+                }
+                runs.push(result);
+            }
+            results.push((path.clone(), problem, runs));
+        }
+
+        if let Some(path) = self.latex.as_ref() {
+            write_file(path, self.latex_table(&results))?;
+        }
+        if let Some(path) = self.baseline.as_ref() {
+            Self::compare_times(&read_file(path)?, &results);
+        }
+        Ok(())
+    }
+
+    /// Logs the speedup of the average time on each instance with respect to the given
+    /// csv output of a previous benchmark, and their geometric mean
+    fn compare_times(baseline: &str, results: &[(String, Psp, Vec<Run>)]) {
+        let mut times: HashMap<&str, (f64, usize)> = HashMap::new();
+        for line in baseline.lines().skip(1) {
+            let cells = line.split(',').collect::<Vec<&str>>();
+            if let (Some(instance), Some(Ok(time))) = (cells.first(), cells.get(6).map(|t| t.parse::<f64>())) {
+                let entry = times.entry(instance).or_default();
+                entry.0 += time;
+                entry.1 += 1;
+            }
+        }
+
+        let mut log_speedups = vec![];
+        for (path, _, runs) in results.iter() {
+            let Some((total, count)) = times.get(path.as_str()) else {
+                warn!("{path} is not in the baseline");
+                continue;
+            };
+            let baseline = total / *count as f64;
+            let time = runs.iter().map(|r| r.time).sum::<f64>() / runs.len().max(1) as f64;
+            let speedup = baseline / time.max(1e-9);
+            info!("{path}: {time:.3}s instead of {baseline:.3}s (speedup {speedup:.3})");
+            log_speedups.push(speedup.ln());
+        }
+        if !log_speedups.is_empty() {
+            let mean = (log_speedups.iter().sum::<f64>() / log_speedups.len() as f64).exp();
+            info!("geometric mean speedup {mean:.3} over {} instances", log_speedups.len());
+        }
+    }
+
+    /// Parses the options of the solve subcommand for the given instance
+    fn solve(&self, instance: &str) -> Result<Solve, PspError> {
+        let mut args = vec!["bench".to_string(), "-i".to_string(), instance.to_string()];
+        args.extend(self.options.split_whitespace().map(|a| a.to_string()));
+        Solve::from_args(args).map_err(|e| PspError::Invalid(format!("invalid solve options: {e}")))
+    }
+
+    /// Returns a booktabs table with one row per instance, which gives the best cost and 
+    /// the best bound over all runs, and the average time and number of nodes
+    fn latex_table(&self, results: &[(String, Psp, Vec<Run>)]) -> String {
+        let mut out = String::new();
+        let align = self.columns.iter()
+            .map(|c| if *c == Column::Instance || *c == Column::Status { 'l' } else { 'r' })
+            .collect::<String>();
+        let header = self.columns.iter()
+            .map(|c| match c {
+                Column::Instance => "Instance",
+                Column::Status => "Status",
+                Column::Best => "Best",
+                Column::Bound => "Bound",
+                Column::Gap => "Gap (\\%)",
+                Column::Time => "Time (s)",
+                Column::Nodes => "Nodes",
+            })
+            .collect::<Vec<&str>>()
+            .join(" & ");
+
+        writeln!(out, "\\begin{{tabular}}{{{align}}}").unwrap();
+        writeln!(out, "\\toprule").unwrap();
+        writeln!(out, "{header} \\\\").unwrap();
+        writeln!(out, "\\midrule").unwrap();
+
+        for (path, problem, runs) in results.iter() {
+            let best = runs.iter().filter_map(|r| r.cost).min();
+            let bound = runs.iter().filter_map(|r| r.bound).max();
+            let gap = match (best, bound) {
+                (Some(0), Some(_)) => Some(0.0),
+                (Some(best), Some(bound)) => Some((best - bound).max(0) as f64 / best as f64),
+                _ => None,
+            };
+            let status = if runs.iter().any(|r| r.status == SolveStatus::Optimal) {
+                SolveStatus::Optimal
+            } else {
+                runs.iter().map(|r| r.status).find(|s| *s == SolveStatus::Feasible).unwrap_or(runs[0].status)
+            };
+            let count = runs.len().max(1) as f64;
+            let format = |cost: Option<isize>| cost.map_or("--".to_string(), |c| problem.format_cost(c));
+
+            let cells = self.columns.iter()
+                .map(|c| match c {
+                    Column::Instance => Self::escape(path.rsplit('/').next().unwrap_or(path)),
+                    Column::Status => status.name().to_string(),
+                    Column::Best => format(best),
+                    Column::Bound => format(bound),
+                    Column::Gap => gap.map_or("--".to_string(), |g| format!("{:.2}", g * 100.0)),
+                    Column::Time => format!("{:.2}", runs.iter().map(|r| r.time).sum::<f64>() / count),
+                    Column::Nodes => format!("{:.0}", runs.iter().map(|r| r.nodes).sum::<usize>() as f64 / count),
+                })
+                .collect::<Vec<String>>()
+                .join(" & ");
+            writeln!(out, "{cells} \\\\").unwrap();
+        }
+
+        writeln!(out, "\\bottomrule").unwrap();
+        writeln!(out, "\\end{{tabular}}").unwrap();
+        out
+    }
+
+    /// Escapes the characters of the given text that are special in LaTeX
+    fn escape(text: &str) -> String {
+        text.chars()
+            .map(|c| match c {
+                '_' | '&' | '%' | '$' | '#' | '{' | '}' => format!("\\{c}"),
+                c => c.to_string(),
+            })
+            .collect()
+    }
+}
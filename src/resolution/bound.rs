@@ -0,0 +1,130 @@
+use std::time::{Duration, Instant};
+
+use clap::{Args, ValueEnum};
+use ddo::{Problem, TimeBudget};
+use smallbitset::Set32;
+
+use crate::error::PspError;
+use crate::instance::IdleSetup;
+use crate::resolution::compression::CompressionArgs;
+use crate::resolution::lagrangian::LagrangianBound;
+#[cfg(feature = "lp")]
+use crate::resolution::mip::lp_bound;
+use crate::resolution::model::{Psp, PspRelax, PspRanking, MergeStrategy, ChangeoverBound, StockingBound};
+use crate::resolution::relaxed::{relaxed_dd, SelectionArgs};
+use crate::resolution::ub_utils::mst;
+
+/// The method used to compute the lower bound
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BoundMethod {
+    /// Lagrangian relaxation of the demand constraints, plus a spanning tree of the changeover costs
+    Lagrangian,
+    /// Relaxed DD of the given width compiled from the root
+    RelaxedDd,
+    /// Optimal value of the compressed problem
+    Compression,
+    /// LP relaxation of the time-indexed formulation
+    #[cfg(feature = "lp")]
+    Lp,
+}
+
+#[derive(Debug, Args)]
+pub struct Bound {
+    /// The path to the instance file (- for the standard input)
+    #[clap(short, long)]
+    pub instance: String,
+    /// The method used to compute the lower bound
+    #[clap(short, long, value_enum, default_value_t=BoundMethod::Lagrangian)]
+    pub method: BoundMethod,
+    /// The number of subgradient iterations of the Lagrangian relaxation
+    #[clap(long, default_value="100")]
+    pub iterations: usize,
+    /// The maximum number of nodes in a layer of the relaxed DD
+    #[clap(short, long, default_value="100")]
+    pub width: usize,
+    /// The way the states are merged in the relaxed DD
+    #[clap(long, value_enum, default_value_t=MergeStrategy::Min)]
+    pub merge: MergeStrategy,
+    /// The lower bound on the changeover costs used by the relaxed DD
+    #[clap(long, value_enum, default_value_t=ChangeoverBound::Max)]
+    pub changeover_bound: ChangeoverBound,
+    /// The lower bound on the stocking costs used by the relaxed DD
+    #[clap(long, value_enum, default_value_t=StockingBound::Greedy)]
+    pub stocking_bound: StockingBound,
+    #[clap(flatten)]
+    pub selection: SelectionArgs,
+    /// The time limit of the relaxed DD compilation, in seconds
+    #[clap(short, long, default_value="60")]
+    pub timeout: u64,
+    #[clap(flatten)]
+    pub compression: CompressionArgs,
+    /// Whether the setup is kept through idle periods, overriding the instance
+    #[clap(long, value_enum)]
+    pub idle_setup: Option<IdleSetup>,
+}
+
+impl Bound {
+    pub fn bound(&self) -> Result<(), PspError> {
+        let mut problem = Psp::from_file(&self.instance)?;
+        if let Some(idle_setup) = self.idle_setup {
+            problem.idle_setup = idle_setup;
+        }
+
+        if (problem.windows || problem.overtime.is_some()) && self.method == BoundMethod::Lagrangian {
+            return Err(PspError::Invalid("the Lagrangian bound does not support service windows nor overtime".to_string()));
+        }
+        #[cfg(feature = "lp")]
+        if self.method == BoundMethod::Lp && (problem.windows || !problem.precedences.is_empty() || problem.overtime.is_some()) {
+            return Err(PspError::Invalid("the LP bound does not support service windows, precedences nor overtime".to_string()));
+        }
+
+        // the bound is None when the relaxation is infeasible, or when it could not be
+        // computed within the time limit
+        let start = Instant::now();
+        let bound = match self.method {
+            BoundMethod::Lagrangian => {
+                let lagrangian = LagrangianBound::new(&problem, self.iterations);
+                Some(Self::changeover_bound(&problem) + problem.idle_bound(&problem.initial_state()) + lagrangian.root_bound())
+            },
+            BoundMethod::RelaxedDd => {
+                let cutoff = TimeBudget::new(Duration::from_secs(self.timeout));
+                let relaxation = PspRelax::new(problem.clone())
+                    .with_merge(self.merge)
+                    .with_changeover_bound(self.changeover_bound)
+                    .with_stocking_bound(self.stocking_bound);
+                relaxed_dd(&problem, &relaxation, &PspRanking, &self.selection, self.width, &cutoff).map(|value| -value)
+            },
+            // the root is infeasible as soon as one compression of the ensemble proves it
+            BoundMethod::Compression => self.compression.bounds(&problem)?.iter()
+                .map(|bound| bound.get_ub(&problem.initial_state()))
+                .collect::<Option<Vec<isize>>>()
+                .and_then(|values| values.into_iter().min())
+                .map(|value| -value),
+            #[cfg(feature = "lp")]
+            BoundMethod::Lp => lp_bound(&problem),
+        };
+
+        println!("method {:?}", self.method);
+        match bound {
+            Some(bound) => println!("lower bound {}", problem.format_cost(bound)),
+            None => println!("lower bound none"),
+        }
+        println!("time {:.3}", start.elapsed().as_secs_f64());
+        Ok(())
+    }
+
+    /// Returns the cost of a minimum spanning tree over the items that must be produced,
+    /// or 0 if the setup is lost and some period can be idle
+    fn changeover_bound(problem: &Psp) -> isize {
+        if !problem.keeps_setup() && problem.idle_periods(&problem.initial_state()) > 0 {
+            return 0;
+        }
+        let mut members = Set32::empty();
+        for i in 0..problem.n_items {
+            if problem.deadlines(i).next().is_some() {
+                members = members.insert(i as u8);
+            }
+        }
+        mst(members, problem.n_items, &problem.changeover) as isize
+    }
+}
@@ -0,0 +1,146 @@
+//! This module implements the caches shared by all the compilations of a run of the
+//! solver. The subproblems explored by the branch-and-bound differ in a few components of
+//! their states, so the same (depth, state) pairs are expanded over and over; the depth
+//! is the time of the state, which is part of its key.
+//!
+//! The rough upper bounds of the states are cached by the relaxation, and disabled with
+//! --no-cache. The successors of the states are cached by the problem that the solvers
+//! of ddo compile, so that the prefixes of the layers shared by the subproblems are only
+//! computed once: the decisions, the next states and the transition costs of a state are
+//! replayed from the cache when it is expanded again. They are disabled with --no-layer-cache.
+
+use std::{hash::{Hash, Hasher}, sync::{Arc, Mutex, atomic::{AtomicUsize, Ordering}}};
+
+use ddo::{Decision, Problem, Variable};
+
+use crate::resolution::hashing::{StateMap, StateHasher};
+use crate::resolution::model::{Psp, PspState};
+
+/// The number of independently locked parts of the cache, so that the threads of the
+/// solver seldom wait for each other
+const NB_SHARDS: usize = 64;
+
+/// A concurrent map from the states to some value, with a bounded size
+pub struct StateCache<V> {
+    shards: Vec<Mutex<StateMap<PspState, V>>>,
+    /// The maximum number of entries of a shard, which is emptied when it is full
+    shard_capacity: usize,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+/// A concurrent map from the states to their rough upper bounds
+pub type BoundCache = StateCache<isize>;
+
+impl<V: Clone> StateCache<V> {
+    /// Creates an empty cache holding at most about the given number of states
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            shards: (0..NB_SHARDS).map(|_| Mutex::new(StateMap::default())).collect(),
+            shard_capacity: capacity.div_ceil(NB_SHARDS).max(1),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the value of the given state, computed with the given function if it is
+    /// not cached yet
+    pub fn get_or_insert_with(&self, state: &PspState, value: impl FnOnce() -> V) -> V {
+        let mut hasher = StateHasher::default();
+        state.hash(&mut hasher);
+        // the maps of the shards index their buckets with the low bits of the same hash
+        let shard = &self.shards[(hasher.finish() >> 32) as usize % NB_SHARDS];
+
+        if let Some(value) = shard.lock().unwrap().get(state).cloned() {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return value;
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        // the value is computed without holding the lock
+        let value = value();
+        let mut shard = shard.lock().unwrap();
+        if shard.len() >= self.shard_capacity {
+            shard.clear();
+        }
+        shard.insert(state.clone(), value.clone());
+        value
+    }
+
+    /// Returns the number of lookups that found the state, and of those that did not
+    pub fn stats(&self) -> (usize, usize) {
+        (self.hits.load(Ordering::Relaxed), self.misses.load(Ordering::Relaxed))
+    }
+}
+
+/// The successors of an expanded state: the value of each decision of its domain, in
+/// their order, with the state it leads to and its cost
+type Successors = Arc<[(isize, PspState, isize)]>;
+
+/// The problem compiled by the solvers, whose states are expanded once per run: their
+/// successors are cached, and reused by all the compilations that reach them again
+pub struct CachedExpansions<'a> {
+    problem: &'a Psp,
+    cache: StateCache<Successors>,
+}
+
+impl<'a> CachedExpansions<'a> {
+    /// Caches the successors of at most about the given number of states of the problem
+    pub fn new(problem: &'a Psp, capacity: usize) -> Self {
+        Self { problem, cache: StateCache::new(capacity) }
+    }
+
+    /// Returns the number of expansions found in the cache, and of those that were computed
+    pub fn stats(&self) -> (usize, usize) {
+        self.cache.stats()
+    }
+
+    fn successors(&self, variable: Variable, state: &PspState) -> Successors {
+        self.cache.get_or_insert_with(state, || {
+            let mut successors = vec![];
+            self.problem.for_each_in_domain(variable, state, &mut |decision: Decision| {
+                let cost = self.problem.transition_cost(state, decision);
+                successors.push((decision.value, self.problem.transition(state, decision), cost));
+            });
+            successors.into()
+        })
+    }
+}
+
+impl Problem for CachedExpansions<'_> {
+    type State = PspState;
+
+    fn nb_variables(&self) -> usize {
+        self.problem.nb_variables()
+    }
+
+    fn initial_state(&self) -> Self::State {
+        self.problem.initial_state()
+    }
+
+    fn initial_value(&self) -> isize {
+        self.problem.initial_value()
+    }
+
+    fn transition(&self, state: &Self::State, decision: Decision) -> Self::State {
+        self.successors(decision.variable, state).iter()
+            .find(|(value, _, _)| *value == decision.value)
+            .map_or_else(|| self.problem.transition(state, decision), |(_, next, _)| next.clone())
+    }
+
+    fn transition_cost(&self, state: &Self::State, decision: Decision) -> isize {
+        self.successors(decision.variable, state).iter()
+            .find(|(value, _, _)| *value == decision.value)
+            .map_or_else(|| self.problem.transition_cost(state, decision), |(_, _, cost)| *cost)
+    }
+
+    fn next_variable(&self, depth: usize, next_layer: &mut dyn Iterator<Item = &Self::State>) -> Option<Variable> {
+        self.problem.next_variable(depth, next_layer)
+    }
+
+    fn for_each_in_domain(&self, variable: Variable, state: &Self::State, f: &mut dyn ddo::DecisionCallback) {
+        for (value, _, _) in self.successors(variable, state).iter() {
+            f.apply(Decision { variable, value: *value });
+        }
+    }
+}
@@ -0,0 +1,236 @@
+//! This module implements the clustering algorithms used to group similar items
+//! into the meta-items of a compressed problem. The centers of k-means and k-medoids are
+//! seeded with k-means++ by default, and their empty clusters are repaired after each
+//! assignment, so that every cluster gets a member whenever there are enough elements.
+
+use clap::ValueEnum;
+use rand::Rng;
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::seq::index::sample;
+
+/// An element that can be clustered, seen as a point in a space of fixed dimension
+pub trait Elem {
+    fn dimensions(&self) -> usize;
+    fn at(&self, i: usize) -> f64;
+}
+
+/// The clustering algorithm
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ClusteringMethod {
+    /// Lloyd's k-means algorithm
+    Kmeans,
+    /// k-medoids with alternating assignment and medoid update steps
+    Kmedoids,
+    /// bottom-up average-linkage hierarchical clustering
+    Agglomerative,
+}
+
+/// How the initial centers of k-means and k-medoids are chosen
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Seeding {
+    /// k-means++: each center is drawn with a probability proportional to the squared
+    /// distance to the closest center already chosen
+    #[default]
+    PlusPlus,
+    /// Distinct elements drawn uniformly
+    Random,
+}
+
+/// The relative decrease of the sum of the squared distances to the centers below which
+/// k-means stops
+const TOLERANCE: f64 = 1e-6;
+
+/// Partitions the elements in at most k clusters and returns the cluster of each element
+pub fn cluster<E: Elem>(elems: &[E], k: usize, method: ClusteringMethod, seeding: Seeding, iterations: usize, rng: &mut impl Rng) -> Vec<usize> {
+    if elems.is_empty() {
+        return vec![];
+    }
+    let k = k.clamp(1, elems.len());
+    match method {
+        ClusteringMethod::Kmeans => kmeans(elems, k, seeding, iterations, rng),
+        ClusteringMethod::Kmedoids => kmedoids(elems, k, seeding, iterations, rng),
+        ClusteringMethod::Agglomerative => agglomerative(elems, k),
+    }
+}
+
+fn point<E: Elem>(elem: &E) -> Vec<f64> {
+    (0..elem.dimensions()).map(|i| elem.at(i)).collect()
+}
+
+fn distance(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b.iter()).map(|(x, y)| (x - y) * (x - y)).sum::<f64>().sqrt()
+}
+
+/// Returns the medoid of each of the k clusters of the given membership: the member that
+/// minimizes the sum of the distances to the other members. Returns None for an empty cluster
+pub fn medoids<E: Elem>(elems: &[E], membership: &[usize], k: usize) -> Vec<Option<usize>> {
+    let points = elems.iter().map(point).collect::<Vec<Vec<f64>>>();
+    (0..k)
+        .map(|c| {
+            let members = (0..points.len()).filter(|i| membership[*i] == c).collect::<Vec<usize>>();
+            let cost = |m: usize| members.iter().map(|i| distance(&points[*i], &points[m])).sum::<f64>();
+            members.iter().copied().min_by(|a, b| cost(*a).total_cmp(&cost(*b)))
+        })
+        .collect()
+}
+
+/// Returns the index of the closest center to the given point
+fn closest(point: &[f64], centers: &[Vec<f64>]) -> usize {
+    let mut best = (f64::INFINITY, 0);
+    for (c, center) in centers.iter().enumerate() {
+        let d = distance(point, center);
+        if d < best.0 {
+            best = (d, c);
+        }
+    }
+    best.1
+}
+
+/// Returns the indices of k distinct points chosen as initial centers
+fn seed(points: &[Vec<f64>], k: usize, seeding: Seeding, rng: &mut impl Rng) -> Vec<usize> {
+    if seeding == Seeding::Random {
+        return sample(rng, points.len(), k).into_vec();
+    }
+
+    let mut seeds = vec![rng.gen_range(0..points.len())];
+    let mut nearest = points.iter().map(|p| distance(p, &points[seeds[0]]).powi(2)).collect::<Vec<f64>>();
+    while seeds.len() < k {
+        // when all the points coincide with a center, the next one is drawn uniformly among the others
+        let next = match WeightedIndex::new(&nearest) {
+            Ok(weights) => weights.sample(rng),
+            Err(_) => {
+                let others = (0..points.len()).filter(|i| !seeds.contains(i)).collect::<Vec<usize>>();
+                others[rng.gen_range(0..others.len())]
+            },
+        };
+        seeds.push(next);
+        for (p, d) in points.iter().zip(nearest.iter_mut()) {
+            *d = d.min(distance(p, &points[next]).powi(2));
+        }
+        nearest[next] = 0.0;
+    }
+    seeds
+}
+
+/// Gives a member to every empty cluster: the point that is the farthest from its center,
+/// among the clusters of several points, becomes the only member of the empty cluster.
+/// Returns the clusters that were repaired
+fn repair(points: &[Vec<f64>], centers: &[Vec<f64>], membership: &mut [usize]) -> Vec<usize> {
+    let mut sizes = vec![0; centers.len()];
+    membership.iter().for_each(|c| sizes[*c] += 1);
+
+    let mut repaired = vec![];
+    for empty in (0..centers.len()).filter(|c| sizes[*c] == 0).collect::<Vec<usize>>() {
+        let farthest = (0..points.len())
+            .filter(|i| sizes[membership[*i]] > 1)
+            .max_by(|a, b| distance(&points[*a], &centers[membership[*a]]).total_cmp(&distance(&points[*b], &centers[membership[*b]])));
+        let Some(farthest) = farthest else {
+            break;
+        };
+        sizes[membership[farthest]] -= 1;
+        sizes[empty] += 1;
+        membership[farthest] = empty;
+        repaired.push(empty);
+    }
+    repaired
+}
+
+/// Returns the sum of the squared distances of the points to the centers of their clusters
+fn inertia(points: &[Vec<f64>], centers: &[Vec<f64>], membership: &[usize]) -> f64 {
+    points.iter().zip(membership.iter()).map(|(p, c)| distance(p, &centers[*c]).powi(2)).sum()
+}
+
+fn kmeans<E: Elem>(elems: &[E], k: usize, seeding: Seeding, iterations: usize, rng: &mut impl Rng) -> Vec<usize> {
+    let points = elems.iter().map(point).collect::<Vec<Vec<f64>>>();
+    let mut centers = seed(&points, k, seeding, rng).into_iter()
+        .map(|i| points[i].clone())
+        .collect::<Vec<Vec<f64>>>();
+    let mut membership = points.iter().map(|p| closest(p, &centers)).collect::<Vec<usize>>();
+    repair(&points, &centers, &mut membership);
+    let mut previous = f64::INFINITY;
+
+    for _ in 0..iterations {
+        for (c, center) in centers.iter_mut().enumerate() {
+            let members = points.iter().zip(membership.iter()).filter(|(_, m)| **m == c).map(|(p, _)| p).collect::<Vec<&Vec<f64>>>();
+            if !members.is_empty() {
+                for (d, x) in center.iter_mut().enumerate() {
+                    *x = members.iter().map(|p| p[d]).sum::<f64>() / members.len() as f64;
+                }
+            }
+        }
+
+        let mut next = points.iter().map(|p| closest(p, &centers)).collect::<Vec<usize>>();
+        let repaired = repair(&points, &centers, &mut next);
+        let current = inertia(&points, &centers, &next);
+        if repaired.is_empty() && (next == membership || previous - current <= TOLERANCE * previous) {
+            membership = next;
+            break;
+        }
+        membership = next;
+        previous = current;
+    }
+
+    membership
+}
+
+fn kmedoids<E: Elem>(elems: &[E], k: usize, seeding: Seeding, iterations: usize, rng: &mut impl Rng) -> Vec<usize> {
+    let points = elems.iter().map(point).collect::<Vec<Vec<f64>>>();
+    let mut medoids = seed(&points, k, seeding, rng);
+    let assign = |medoids: &[usize]| {
+        let centers = medoids.iter().map(|m| points[*m].clone()).collect::<Vec<Vec<f64>>>();
+        let mut membership = points.iter().map(|p| closest(p, &centers)).collect::<Vec<usize>>();
+        // the medoids of the repaired clusters are their only members
+        repair(&points, &centers, &mut membership);
+        membership
+    };
+    let mut membership = assign(&medoids);
+
+    for _ in 0..iterations {
+        for (c, medoid) in medoids.iter_mut().enumerate() {
+            let members = (0..points.len()).filter(|i| membership[*i] == c).collect::<Vec<usize>>();
+            let cost = |m: usize| members.iter().map(|i| distance(&points[*i], &points[m])).sum::<f64>();
+            if let Some(best) = members.iter().copied().min_by(|a, b| cost(*a).total_cmp(&cost(*b))) {
+                *medoid = best;
+            }
+        }
+
+        let next = assign(&medoids);
+        if next == membership {
+            break;
+        }
+        membership = next;
+    }
+
+    membership
+}
+
+fn agglomerative<E: Elem>(elems: &[E], k: usize) -> Vec<usize> {
+    let points = elems.iter().map(point).collect::<Vec<Vec<f64>>>();
+    let mut clusters = (0..points.len()).map(|i| vec![i]).collect::<Vec<Vec<usize>>>();
+    let linkage = |a: &[usize], b: &[usize]| {
+        a.iter().map(|i| b.iter().map(|j| distance(&points[*i], &points[*j])).sum::<f64>()).sum::<f64>()
+            / (a.len() * b.len()) as f64
+    };
+
+    while clusters.len() > k {
+        let mut best = (f64::INFINITY, 0, 1);
+        for a in 0..clusters.len() {
+            for b in (a + 1)..clusters.len() {
+                let d = linkage(&clusters[a], &clusters[b]);
+                if d < best.0 {
+                    best = (d, a, b);
+                }
+            }
+        }
+        let merged = clusters.swap_remove(best.2);
+        clusters[best.1].extend(merged);
+    }
+
+    let mut membership = vec![0; points.len()];
+    for (c, members) in clusters.iter().enumerate() {
+        for i in members.iter() {
+            membership[*i] = c;
+        }
+    }
+    membership
+}
@@ -0,0 +1,54 @@
+use clap::Args;
+
+use crate::error::{PspError, read_json};
+use crate::resolution::model::Psp;
+use crate::solution::PspSolution;
+
+#[derive(Debug, Args)]
+pub struct Compare {
+    /// The path to the instance file (- for the standard input)
+    #[clap(short, long)]
+    pub instance: String,
+    /// The path to the first solution file
+    pub first: String,
+    /// The path to the second solution file
+    pub second: String,
+}
+
+impl Compare {
+    pub fn compare(&self) -> Result<(), PspError> {
+        let problem = Psp::from_file(&self.instance)?;
+        let first: PspSolution = read_json(&self.first)?;
+        let second: PspSolution = read_json(&self.second)?;
+
+        let a = Self::report(&problem, "first", &first.schedule);
+        let b = Self::report(&problem, "second", &second.schedule);
+
+        if let (Some(a), Some(b)) = (a, b) {
+            println!("difference: cost {}, changeover {}, stocking {}", 
+                problem.format_cost(b.0 - a.0), problem.format_cost(b.1 - a.1), problem.format_cost(b.2 - a.2));
+        }
+
+        let periods = (0..problem.horizon)
+            .filter(|t| first.schedule.get(*t) != second.schedule.get(*t))
+            .collect::<Vec<usize>>();
+        println!("differing periods {} {periods:?}", periods.len());
+        Ok(())
+    }
+
+    /// Prints whether the schedule is feasible and its costs, which are returned if it is
+    fn report(problem: &Psp, name: &str, schedule: &[isize]) -> Option<(isize, isize, isize)> {
+        let cost = match problem.evaluate(schedule) {
+            Ok(cost) => cost,
+            Err(reason) => {
+                println!("{name}: infeasible, {reason}");
+                return None;
+            },
+        };
+        let changeover = problem.changeover_cost(schedule);
+        let stocking = cost - changeover - problem.overtime_cost(schedule) - problem.idle_cost(schedule);
+        println!("{name}: feasible, cost {}, changeover {}, stocking {}", 
+            problem.format_cost(cost), problem.format_cost(changeover), problem.format_cost(stocking));
+        Some((cost, changeover, stocking))
+    }
+}
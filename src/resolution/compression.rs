@@ -0,0 +1,798 @@
+//! This module implements the compression of a PSP instance: the items are grouped
+//! into clusters of similar items, called meta-items, which yields a much smaller
+//! meta-problem. Each meta-item has the cheapest stocking cost of its members, the
+//! changeover costs between meta-items are the cheapest ones between their members,
+//! and a meta-item is demanded whenever one of its members is. The second-order
+//! changeover of meta-items (A, B, C) is the cheapest one from a member of B to a member
+//! of C, after a member of A or another member of B, since the productions of a meta-item
+//! may stand for several members. The meta-problem is 
+//! thus a relaxation of the original one: solving it exactly from the compression of 
+//! a state yields an upper bound on the value of that state.
+//!
+//! Alternatively, each meta-item can take the costs of a representative member, the
+//! medoid of its cluster. Such a meta-problem is no longer a relaxation, but its
+//! solutions are closer to real schedules, which makes them easier to decompress.
+//!
+//! Compressions can be chained: each level clusters the meta-items of the previous one,
+//! so that every level is a relaxation of the previous one. The coarse levels are cheap
+//! to solve, and they are used to discard the meta-states of the finer levels that have
+//! no feasible completion.
+//!
+//! The values of the meta-states only depend on the meta-problem, so they can be cached
+//! on disk and reused by later runs that build the same compression of the same instance.
+
+use std::{collections::{BTreeMap, HashMap}, fs, hash::{Hash, Hasher}, path::Path, str::FromStr, sync::Mutex};
+
+use clap::{Args, Parser, ValueEnum};
+use ddo::{Cutoff, Decision, Problem, Solution, Variable};
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{debug, warn};
+
+use crate::error::{PspError, read_json, write_json};
+use crate::instance::SparseDemand;
+use crate::resolution::clustering::{cluster, medoids, ClusteringMethod, Elem, Seeding};
+use crate::resolution::hashing::{StateMap, StateHasher};
+use crate::resolution::heuristic::restricted_dd_with;
+use crate::resolution::model::{Psp, PspState, PspRanking, Period, IDLE};
+
+/// The options of the compression
+#[derive(Debug, Clone, Args)]
+pub struct CompressionArgs {
+    /// The number of meta-items of the compressed problem. Several decreasing numbers
+    /// (e.g. 20,10,5) yield a chain of compressions of the previous level
+    #[clap(long, default_value="5", value_delimiter=',', num_args=1..)]
+    pub n_meta_items: Vec<usize>,
+    /// The clustering algorithm used to group the items into meta-items
+    #[clap(long, value_enum, default_value_t=ClusteringMethod::Kmeans)]
+    pub compression_method: ClusteringMethod,
+    /// How the clustering algorithm chooses its initial centers
+    #[clap(long, value_enum, default_value_t=Seeding::PlusPlus)]
+    pub compression_seeding: Seeding,
+    /// The maximum number of iterations of the clustering algorithm
+    #[clap(long, default_value="1000")]
+    pub compression_iterations: usize,
+    /// The seed of the random number generator used by the clustering algorithm
+    #[clap(long, default_value="0")]
+    pub compression_seed: u64,
+    /// The number of times the clustering is run with different random initializations:
+    /// the clustering that gives the tightest bound at the root is kept
+    #[clap(long, default_value="1")]
+    pub compression_restarts: usize,
+    /// The weights of the changeover and stocking features of the items in the clustering,
+    /// e.g. changeover=1,stocking=0.2
+    #[clap(long, default_value="changeover=1,stocking=1")]
+    pub compression_weights: FeatureWeights,
+    /// How the costs of a meta-item are derived from those of its members. The medoid
+    /// costs do not yield a bound, only meta-solutions to decompress
+    #[clap(long, value_enum, default_value_t=Aggregation::Min)]
+    pub compression_aggregation: Aggregation,
+    /// How each group of features is rescaled before it is weighted, so that the
+    /// changeover and stocking costs are compared on the same scale
+    #[clap(long, value_enum, default_value_t=Normalization::None)]
+    pub compression_normalization: Normalization,
+    /// The weight of the demand pattern of the items in the clustering, so that items
+    /// demanded at different times are not grouped (0 ignores the demands)
+    #[clap(long, default_value="0")]
+    pub demand_weight: f64,
+    /// The number of slices of the horizon in which the demands of each item are counted
+    #[clap(long, default_value="10")]
+    pub demand_bins: usize,
+    /// The options of another compression whose bound is combined with this one, e.g.
+    /// "--n-meta-items 8 --compression-seed 3". It can be repeated, and the tightest of
+    /// the bounds of all the compressions is used for each state
+    #[clap(long, allow_hyphen_values = true)]
+    pub compression_ensemble: Vec<String>,
+    /// If present, the path where to save the clusters of the compression
+    #[clap(long)]
+    pub save_compression: Option<String>,
+    /// If present, the path of a file saved with --save-compression: its clusters are used
+    /// instead of running the clustering
+    #[clap(long, conflicts_with="save_compression")]
+    pub load_compression: Option<String>,
+    /// If present, the directory where the values of the meta-states of each level are
+    /// cached, in a file named after a hash of its meta-problem
+    #[clap(long)]
+    pub compression_cache: Option<String>,
+}
+
+/// Parses the options of a compression of the ensemble from a list of arguments
+#[derive(Debug, Parser)]
+struct CompressionParser {
+    #[clap(flatten)]
+    compression: CompressionArgs,
+}
+
+/// The weights of the groups of features of the items in the clustering
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FeatureWeights {
+    pub changeover: f64,
+    pub stocking: f64,
+}
+
+impl FromStr for FeatureWeights {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut weights = Self { changeover: 1.0, stocking: 1.0 };
+        for pair in s.split(',').filter(|p| !p.trim().is_empty()) {
+            let (feature, weight) = pair.split_once('=').ok_or(format!("expected feature=weight, got {pair}"))?;
+            let weight = weight.trim().parse::<f64>()
+                .ok()
+                .filter(|w| w.is_finite() && *w >= 0.0)
+                .ok_or(format!("the weight of {feature} must be a non-negative number"))?;
+            match feature.trim() {
+                "changeover" => weights.changeover = weight,
+                "stocking" => weights.stocking = weight,
+                other => return Err(format!("unknown feature {other}, expected changeover or stocking")),
+            }
+        }
+        Ok(weights)
+    }
+}
+
+/// How the costs of the meta-items are computed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Aggregation {
+    /// The cheapest costs of the members, which makes the meta-problem a relaxation
+    Min,
+    /// The costs of the medoid of each cluster, a real item
+    Medoid,
+}
+
+/// How a group of features is rescaled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Normalization {
+    /// The raw costs and quantities
+    None,
+    /// Divided by the range of the values of the group
+    Range,
+    /// Divided by the standard deviation of the values of the group
+    Std,
+}
+
+impl Normalization {
+    /// Rescales the values of a group of features in place
+    fn apply(&self, values: &mut [&mut f64]) {
+        let n = values.len() as f64;
+        let scale = match self {
+            Normalization::None => return,
+            Normalization::Range => {
+                let max = values.iter().map(|v| **v).fold(f64::NEG_INFINITY, f64::max);
+                let min = values.iter().map(|v| **v).fold(f64::INFINITY, f64::min);
+                max - min
+            },
+            Normalization::Std => {
+                let mean = values.iter().map(|v| **v).sum::<f64>() / n;
+                (values.iter().map(|v| (**v - mean).powi(2)).sum::<f64>() / n).sqrt()
+            },
+        };
+        // a constant group does not discriminate the items either way
+        if scale > 0.0 {
+            values.iter_mut().for_each(|v| **v /= scale);
+        }
+    }
+}
+
+/// The clusters of each level of a chain of compressions, as saved on disk. The meta-problems
+/// are cheap to rebuild from them
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedCompression {
+    nb_types: usize,
+    levels: Vec<Vec<usize>>,
+}
+
+impl CompressionArgs {
+    /// Clusters the items of the problem into the given number of meta-items and builds
+    /// the corresponding compression
+    pub fn compress(&self, problem: &Psp, n_meta_items: usize) -> PspCompression {
+        let elems = self.elements(problem);
+        let mut rng = ChaChaRng::seed_from_u64(self.compression_seed);
+        let restarts = match self.compression_method {
+            ClusteringMethod::Agglomerative => 1,
+            _ => self.compression_restarts.max(1),
+        };
+
+        let mut best: Option<(Option<isize>, PspCompression)> = None;
+        for _ in 0..restarts {
+            let membership = cluster(&elems, n_meta_items, self.compression_method, self.compression_seeding, self.compression_iterations, &mut rng);
+            let compression = self.aggregate(problem, &elems, &membership);
+            if restarts == 1 {
+                return compression;
+            }
+
+            // a compression without feasible completion at the root gives the tightest bound
+            let bound = CompressedSolutionBound::new(compression.clone()).get_ub(&problem.initial_state());
+            let is_tighter = best.as_ref().map_or(true, |(b, _)| match (bound, *b) {
+                (None, _) => true,
+                (Some(_), None) => false,
+                (Some(x), Some(y)) => x < y,
+            });
+            if is_tighter {
+                best = Some((bound, compression));
+            }
+        }
+        best.map(|(_, c)| c).expect("at least one clustering is computed")
+    }
+
+    /// Builds the compression of the given clusters with the chosen aggregation of the costs
+    fn aggregate(&self, problem: &Psp, elems: &[ItemElem], clusters: &[usize]) -> PspCompression {
+        match self.compression_aggregation {
+            Aggregation::Min => PspCompression::new(problem, clusters),
+            Aggregation::Medoid => {
+                let k = clusters.iter().max().map_or(0, |c| c + 1);
+                let representatives = medoids(elems, clusters, k);
+                PspCompression::with_representatives(problem, clusters, &representatives)
+            },
+        }
+    }
+
+    /// Returns the points representing the items in the clustering. Each group of
+    /// features (changeovers, stocking, demands) is normalized over all the items, then
+    /// weighted
+    fn elements(&self, problem: &Psp) -> Vec<ItemElem> {
+        let n = problem.n_items;
+        let mut changeovers = (0..n)
+            .map(|item| (0..n).map(|i| problem.changeover(item, i) as f64).collect::<Vec<f64>>())
+            .collect::<Vec<Vec<f64>>>();
+        let mut stocking = problem.stocking.iter().map(|s| *s as f64).collect::<Vec<f64>>();
+        let bins = self.demand_bins.clamp(1, problem.horizon.max(1));
+        let mut histograms = (0..n)
+            .map(|item| {
+                let mut histogram = vec![0.0; bins];
+                if self.demand_weight > 0.0 {
+                    for d in problem.demands_of(item).iter() {
+                        histogram[d.period * bins / problem.horizon] += d.quantity as f64;
+                    }
+                }
+                histogram
+            })
+            .collect::<Vec<Vec<f64>>>();
+
+        self.compression_normalization.apply(&mut changeovers.iter_mut().flatten().collect::<Vec<&mut f64>>());
+        self.compression_normalization.apply(&mut stocking.iter_mut().collect::<Vec<&mut f64>>());
+        self.compression_normalization.apply(&mut histograms.iter_mut().flatten().collect::<Vec<&mut f64>>());
+
+        (0..n)
+            .map(|item| {
+                let mut features = changeovers[item].iter().map(|c| c * self.compression_weights.changeover).collect::<Vec<f64>>();
+                features.push(stocking[item] * self.compression_weights.stocking);
+                if self.demand_weight > 0.0 {
+                    features.extend(histograms[item].iter().map(|h| h * self.demand_weight));
+                }
+                ItemElem { features }
+            })
+            .collect()
+    }
+
+    /// Builds the chain of compressions, from the finest level to the coarsest one
+    pub fn compressions(&self, problem: &Psp) -> Result<Vec<PspCompression>, PspError> {
+        if let Some(path) = self.load_compression.as_ref() {
+            return self.load(problem, path);
+        }
+
+        let mut compressions: Vec<PspCompression> = vec![];
+        for n_meta_items in self.n_meta_items.iter().copied() {
+            let previous = compressions.last().map_or(problem, |c| &c.meta_problem);
+            let compression = self.compress(previous, n_meta_items);
+            compressions.push(compression);
+        }
+
+        if let Some(path) = self.save_compression.as_ref() {
+            let saved = SavedCompression {
+                nb_types: problem.n_items,
+                levels: compressions.iter().map(|c| c.membership.clone()).collect(),
+            };
+            write_json(path, &saved)?;
+        }
+        Ok(compressions)
+    }
+
+    /// Rebuilds the chain of compressions saved in the given file
+    fn load(&self, problem: &Psp, path: &str) -> Result<Vec<PspCompression>, PspError> {
+        let saved: SavedCompression = read_json(path)?;
+        let mismatch = || PspError::Invalid(format!("{path}: the saved compression is not one of this instance"));
+        if saved.nb_types != problem.n_items {
+            return Err(mismatch());
+        }
+
+        let mut compressions: Vec<PspCompression> = vec![];
+        for membership in saved.levels.iter() {
+            let previous = compressions.last().map_or(problem, |c| &c.meta_problem);
+            if membership.len() != previous.n_items {
+                return Err(mismatch());
+            }
+            let compression = self.aggregate(previous, &self.elements(previous), membership);
+            compressions.push(compression);
+        }
+        Ok(compressions)
+    }
+
+    /// Builds the bound of the chain of compressions of the problem, followed by those of
+    /// the other compressions of the ensemble
+    pub fn bounds(&self, problem: &Psp) -> Result<Vec<CompressedSolutionBound>, PspError> {
+        let mut bounds = vec![self.bound(problem)?];
+        for options in self.compression_ensemble.iter() {
+            let args = std::iter::once("compression".to_string()).chain(options.split_whitespace().map(|a| a.to_string()));
+            let member = CompressionParser::try_parse_from(args)
+                .map_err(|e| PspError::Invalid(format!("invalid compression options {options:?}: {e}")))?
+                .compression;
+            if !member.compression_ensemble.is_empty() {
+                return Err(PspError::Invalid("the compressions of an ensemble cannot be ensembles".to_string()));
+            }
+            bounds.push(member.bound(problem)?);
+        }
+        Ok(bounds)
+    }
+
+    /// Builds the bound of the chain of compressions of the problem
+    pub fn bound(&self, problem: &Psp) -> Result<CompressedSolutionBound, PspError> {
+        if self.compression_aggregation == Aggregation::Medoid {
+            return Err(PspError::Invalid("the medoid compression is not a relaxation, it cannot bound the problem".to_string()));
+        }
+        self.compressions(problem)?.into_iter().rev()
+            .fold(None, |coarser, compression| {
+                let mut bound = CompressedSolutionBound::new(compression);
+                if let Some(dir) = self.compression_cache.as_ref() {
+                    bound = bound.with_cache(dir);
+                }
+                Some(match coarser {
+                    Some(coarser) => bound.with_coarser(coarser),
+                    None => bound,
+                })
+            })
+            .ok_or_else(|| PspError::Invalid("at least one number of meta-items is required".to_string()))
+    }
+}
+
+/// An item seen as a point to cluster: its changeover costs to every other item, 
+/// its stocking cost and possibly the weighted histogram of its demands over time
+struct ItemElem {
+    features: Vec<f64>,
+}
+
+impl Elem for ItemElem {
+    fn dimensions(&self) -> usize {
+        self.features.len()
+    }
+
+    fn at(&self, i: usize) -> f64 {
+        self.features[i]
+    }
+}
+
+/// The compression of a problem: its meta-problem, the meta-item of each item, and the
+/// representative of each meta-item if their costs are not the cheapest ones
+#[derive(Debug, Clone)]
+pub struct PspCompression {
+    pub meta_problem: Psp,
+    pub membership: Vec<usize>,
+    pub representatives: Option<Vec<usize>>,
+    /// For each item, the unit of its meta-item demanded at the deadline of each of its units
+    meta_units: Vec<Vec<Period>>,
+}
+
+impl PspCompression {
+    /// Builds the meta-problem whose meta-items are the given clusters of items
+    pub fn new(problem: &Psp, clusters: &[usize]) -> Self {
+        Self::build(problem, clusters, None)
+    }
+
+    /// Builds the meta-problem whose meta-items are the given clusters of items, with the
+    /// stocking and changeover costs of the given representative of each cluster. The
+    /// first member stands for a cluster without representative
+    pub fn with_representatives(problem: &Psp, clusters: &[usize], representatives: &[Option<usize>]) -> Self {
+        Self::build(problem, clusters, Some(representatives))
+    }
+
+    fn build(problem: &Psp, clusters: &[usize], representatives: Option<&[Option<usize>]>) -> Self {
+        // renumber the non-empty clusters from 0
+        let mut labels = HashMap::new();
+        let membership = clusters.iter()
+            .map(|c| { let n = labels.len(); *labels.entry(*c).or_insert(n) })
+            .collect::<Vec<usize>>();
+        let n_meta = labels.len();
+        let representatives = representatives.map(|r| {
+            let mut meta = vec![None; n_meta];
+            for (c, m) in labels.iter() {
+                meta[*m] = r.get(*c).copied().flatten();
+            }
+            for (i, m) in membership.iter().enumerate() {
+                meta[*m].get_or_insert(i);
+            }
+            meta.into_iter().map(|r| r.expect("every meta-item has a member")).collect::<Vec<usize>>()
+        });
+
+        let mut stocking = vec![usize::MAX; n_meta];
+        let mut changeover = vec![usize::MAX; n_meta * n_meta];
+        // the earliest service period of each demanded period of each meta-item
+        let mut periods = vec![BTreeMap::new(); n_meta];
+        for (i, a) in membership.iter().copied().enumerate() {
+            stocking[a] = stocking[a].min(problem.stocking[i]);
+            for (j, b) in membership.iter().copied().enumerate() {
+                let c = &mut changeover[a * n_meta + b];
+                *c = (*c).min(problem.changeover(i, j));
+            }
+            for d in problem.demands_of(i).iter() {
+                let earliest = periods[a].entry(d.period).or_insert(d.earliest);
+                *earliest = d.earliest.min(*earliest);
+            }
+        }
+
+        // the service windows of a meta-item must be in the order of their latest periods,
+        // which holds once each window starts at the earliest of the later ones
+        let lists = periods.iter().map(|periods| {
+            let mut first = problem.horizon;
+            let mut demands = periods.iter().rev()
+                .map(|(period, earliest)| {
+                    first = first.min(*earliest);
+                    SparseDemand { period: *period, quantity: 1, earliest: Some(first) }
+                })
+                .collect::<Vec<SparseDemand>>();
+            demands.reverse();
+            demands
+        }).collect::<Vec<Vec<SparseDemand>>>();
+
+        let mut second_order = if problem.second_order.is_empty() { vec![] } else { vec![usize::MAX; n_meta * n_meta * n_meta] };
+        let triple = |a: usize, b: usize, c: usize| (a * n_meta + b) * n_meta + c;
+        if representatives.is_none() && !second_order.is_empty() {
+            let n = problem.n_items;
+            for (p, b, c) in (0..n).flat_map(|p| (0..n).flat_map(move |b| (0..n).map(move |c| (p, b, c)))) {
+                let (mp, mb, mc) = (membership[p], membership[b], membership[c]);
+                if p == b || b == c || mb == mc {
+                    continue;
+                }
+                let cost = problem.second_order(p, b, c);
+                // a member of B switched to from another member stands for B switched to
+                // from any meta-item
+                for a in (0..n_meta).filter(|a| *a != mb && (mp == mb || *a == mp)) {
+                    let s = &mut second_order[triple(a, mb, mc)];
+                    *s = (*s).min(cost);
+                }
+            }
+            // the triples of meta-items that do not switch are never charged
+            second_order.iter_mut().filter(|s| **s == usize::MAX).for_each(|s| *s = 0);
+        }
+
+        match representatives.as_ref() {
+            Some(representatives) => for (a, r) in representatives.iter().enumerate() {
+                stocking[a] = problem.stocking[*r];
+                for (b, s) in representatives.iter().enumerate() {
+                    changeover[a * n_meta + b] = problem.changeover(*r, *s);
+                    if !second_order.is_empty() {
+                        for (c, u) in representatives.iter().enumerate() {
+                            second_order[triple(a, b, c)] = problem.second_order(*r, *s, *u);
+                        }
+                    }
+                }
+            },
+            // a meta-item may replace several productions of the original schedule, hence the
+            // changeover costs must satisfy the triangle inequality to remain a relaxation
+            None => for k in 0..n_meta {
+                for a in 0..n_meta {
+                    for b in 0..n_meta {
+                        let through = changeover[a * n_meta + k].saturating_add(changeover[k * n_meta + b]);
+                        if through < changeover[a * n_meta + b] {
+                            changeover[a * n_meta + b] = through;
+                        }
+                    }
+                }
+            },
+        }
+
+        let meta_problem = Psp {
+            n_items: n_meta,
+            horizon: problem.horizon,
+            precision: problem.precision,
+            stocking,
+            changeover,
+            second_order,
+            demands: Psp::compute_demands(&lists),
+            windows: problem.windows,
+            max_stock: vec![usize::MAX; n_meta],
+            capacity: usize::MAX,
+            idle_setup: problem.idle_setup,
+            available: problem.available.clone(),
+            overtime: problem.overtime,
+            // the idle periods of the meta-problem are filled by the other members of its
+            // meta-items, so the idle cost is bounded separately
+            idle: 0,
+            incompatible: vec![false; n_meta * n_meta],
+            precedences: vec![],
+            forbidden: vec![],
+            fixed: vec![None; problem.horizon],
+            banned_productions: vec![],
+            banned_transitions: vec![],
+            twins: vec![],
+            offset: 0,
+            fingerprint: problem.fingerprint.clone(),
+        }.with_symmetries();
+
+        // a meta-item is demanded once at each period where one of its members is
+        let meta_units = (0..problem.n_items)
+            .map(|i| {
+                let meta = &lists[membership[i]];
+                problem.deadlines(i)
+                    .map(|t| meta.binary_search_by_key(&t, |d| d.period).expect("the meta-item is demanded with its members") as Period)
+                    .collect()
+            })
+            .collect();
+
+        Self { meta_problem, membership, representatives, meta_units }
+    }
+
+    /// Returns the meta-state corresponding to the given state: each meta-item must satisfy 
+    /// the earliest deadline of the last remaining units of its members
+    pub fn compress(&self, state: &PspState) -> PspState {
+        let mut prev_demands = vec![Period::MAX; self.meta_problem.n_items];
+        for (i, p) in state.prev_demands.iter().enumerate() {
+            let meta = &mut prev_demands[self.membership[i]];
+            let unit = if p < 0 { -1 } else { self.meta_units[i][p as usize] };
+            *meta = (*meta).min(unit);
+        }
+
+        let meta = |item: isize| if item == -1 { -1 } else { self.membership[item as usize] as isize };
+        // the meta-items after the next one are unknown when its member follows a member
+        // of the same meta-item
+        let (next, after_next) = (meta(state.next), meta(state.after_next));
+        PspState {
+            time: state.time,
+            next,
+            after_next: if after_next == next { -1 } else { after_next },
+            prev_demands: prev_demands.into(),
+            matching: 0,
+        }
+    }
+
+    /// Maps a solution of the meta-problem to a solution of the problem it compresses. 
+    /// Whenever a meta-item is produced, one of its members must be produced, and the 
+    /// other productions are scheduled in the periods where the meta-problem is idle. 
+    /// The best such assignment is searched with a restricted DD of the given width.
+    /// Returns None if no assignment was found
+    pub fn decompress(&self, problem: &Psp, solution: &Solution, width: usize, cutoff: &dyn Cutoff) -> Option<(isize, Solution)> {
+        let meta_schedule = self.meta_problem.schedule(solution);
+        let follows = |d: Decision| match meta_schedule[d.variable.id()] {
+            IDLE => true,
+            meta => d.value != IDLE && self.membership[problem.item_of(d.value) as usize] == self.meta_problem.item_of(meta) as usize,
+        };
+        restricted_dd_with(problem, &PspRanking, width, cutoff, &follows).best
+    }
+}
+
+/// The number of independently locked parts of the memo, so that the threads of the
+/// solver seldom wait for each other
+const NB_SHARDS: usize = 64;
+
+/// The memoized values of the meta-states, split into shards that are locked independently
+struct Memo {
+    shards: Vec<Mutex<StateMap<PspState, Option<isize>>>>,
+}
+
+impl Memo {
+    fn new() -> Self {
+        Self { shards: (0..NB_SHARDS).map(|_| Mutex::new(StateMap::default())).collect() }
+    }
+
+    fn shard(&self, state: &PspState) -> &Mutex<StateMap<PspState, Option<isize>>> {
+        let mut hasher = StateHasher::default();
+        state.hash(&mut hasher);
+        // the maps of the shards index their buckets with the low bits of the same hash
+        &self.shards[(hasher.finish() >> 32) as usize % NB_SHARDS]
+    }
+
+    /// Returns the memoized value of the given meta-state, if any
+    fn get(&self, state: &PspState) -> Option<Option<isize>> {
+        self.shard(state).lock().unwrap().get(state).copied()
+    }
+
+    fn insert(&self, state: PspState, value: Option<isize>) {
+        self.shard(&state).lock().unwrap().insert(state, value);
+    }
+
+    fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.lock().unwrap().len()).sum()
+    }
+}
+
+/// A meta-state whose completions are being explored
+struct Frame {
+    state: PspState,
+    /// The decisions that remain to explore
+    decisions: Vec<Decision>,
+    /// The best value of the completions explored so far
+    best: Option<isize>,
+    /// The cost of the transition from the parent meta-state
+    cost: isize,
+}
+
+impl Frame {
+    fn improve(&mut self, value: Option<isize>) {
+        if let Some(value) = value {
+            self.best = Some(self.best.map_or(value, |b| b.max(value)));
+        }
+    }
+}
+
+/// The upper bound given by the exact resolution of the meta-problem from the
+/// compression of each state. The values of the meta-states are memoized.
+/// The bound of the next level of compression, if any, is coarser
+pub struct CompressedSolutionBound {
+    compression: PspCompression,
+    coarser: Option<Box<CompressedSolutionBound>>,
+    memo: Memo,
+    /// The file where the memoized values are cached, and the number of values read from it
+    cache: Option<(String, usize)>,
+}
+
+/// The version of the layout of the cached meta-states, which is part of the key of their
+/// files: the caches of the meta-states that stored periods instead of units are ignored
+const CACHE_VERSION: u32 = 2;
+
+/// A memoized value of a meta-state, as cached on disk
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedValue {
+    time: usize,
+    next: isize,
+    after_next: isize,
+    prev_demands: Vec<Period>,
+    value: Option<isize>,
+}
+
+impl CompressedSolutionBound {
+    pub fn new(compression: PspCompression) -> Self {
+        Self { compression, coarser: None, memo: Memo::new(), cache: None }
+    }
+
+    /// Reads the values of the meta-states cached in the given directory for this
+    /// meta-problem, if any, and caches them there again with save
+    pub fn with_cache(mut self, dir: &str) -> Self {
+        let key = Sha256::digest(format!("{CACHE_VERSION} {:?}", self.compression.meta_problem)).iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<String>();
+        let path = Path::new(dir).join(format!("{key}.json")).to_string_lossy().to_string();
+
+        let mut loaded = 0;
+        if Path::new(&path).exists() {
+            match read_json::<Vec<CachedValue>>(&path) {
+                Ok(values) => {
+                    for v in values {
+                        let state = PspState { time: v.time, next: v.next, after_next: v.after_next, prev_demands: v.prev_demands.into(), matching: 0 };
+                        self.memo.insert(state, v.value);
+                    }
+                    loaded = self.memo.len();
+                    debug!(path = %path, loaded, "compression cache read");
+                },
+                Err(e) => warn!(path = %path, "the compression cache is ignored: {e}"),
+            }
+        }
+        self.cache = Some((path, loaded));
+        self
+    }
+
+    /// Writes the values of the meta-states of every level to their cache, if they are
+    /// cached and new values were computed
+    pub fn save(&self) -> Result<(), PspError> {
+        if let Some((path, loaded)) = self.cache.as_ref() {
+            if self.memo.len() > *loaded {
+                if let Some(dir) = Path::new(path).parent() {
+                    fs::create_dir_all(dir).map_err(|source| PspError::Io { path: dir.to_string_lossy().to_string(), source })?;
+                }
+                let shards = self.memo.shards.iter().map(|shard| shard.lock().unwrap()).collect::<Vec<_>>();
+                let values = shards.iter()
+                    .flat_map(|shard| shard.iter())
+                    .map(|(state, value)| CachedValue { time: state.time, next: state.next, after_next: state.after_next, prev_demands: state.prev_demands.iter().collect(), value: *value })
+                    .collect::<Vec<CachedValue>>();
+                write_json(path, &values)?;
+                debug!(path = %path, saved = values.len(), "compression cache written");
+            }
+        }
+        match self.coarser.as_ref() {
+            Some(coarser) => coarser.save(),
+            None => Ok(()),
+        }
+    }
+
+    /// Uses the given bound, on a compression of the meta-problem, to discard the
+    /// meta-states that have no feasible completion
+    pub fn with_coarser(mut self, coarser: CompressedSolutionBound) -> Self {
+        self.coarser = Some(Box::new(coarser));
+        self
+    }
+
+    /// Returns an upper bound on the value of the best completion of the given state,
+    /// or None if its compression has no feasible completion. This is the tightest 
+    /// bound of all levels of compression
+    pub fn get_ub(&self, state: &PspState) -> Option<isize> {
+        let meta_state = self.compression.compress(state);
+        let value = self.value(&meta_state)?;
+        match self.coarser.as_ref().map(|c| c.get_ub(&meta_state)) {
+            Some(None) => None,
+            Some(Some(coarser)) => Some(value.min(coarser)),
+            None => Some(value),
+        }
+    }
+
+    /// Returns an optimal solution of the meta-problem from the compression of the given state,
+    /// if there is any
+    pub fn meta_solution(&self, state: &PspState) -> Option<Solution> {
+        let problem = &self.compression.meta_problem;
+        let mut state = self.compression.compress(state);
+        let mut solution = vec![];
+        while state.time > 0 {
+            let mut decisions = vec![];
+            problem.for_each_in_domain(Variable(state.time - 1), &state, &mut |d: Decision| decisions.push(d));
+
+            let mut best: Option<(isize, Decision, PspState)> = None;
+            for decision in decisions {
+                let next = problem.transition(&state, decision);
+                if self.is_infeasible(&next) {
+                    continue;
+                }
+                if let Some(value) = self.value(&next) {
+                    let value = value + problem.transition_cost(&state, decision);
+                    if best.as_ref().map_or(true, |b| value > b.0) {
+                        best = Some((value, decision, next));
+                    }
+                }
+            }
+
+            let (_, decision, next) = best?;
+            solution.push(decision);
+            state = next;
+        }
+        Some(solution)
+    }
+
+    /// Returns true iff the coarser level proves that the meta-state has no feasible completion
+    fn is_infeasible(&self, state: &PspState) -> bool {
+        self.coarser.as_ref().map_or(false, |c| c.get_ub(state).is_none())
+    }
+
+    /// Returns the value of the best completion of the given meta-state. The meta-states
+    /// are explored depth-first with an explicit stack, whose depth is the horizon, and no
+    /// lock is held during the exploration: two threads may compute the same value
+    fn value(&self, state: &PspState) -> Option<isize> {
+        if state.time == 0 {
+            return Some(0);
+        }
+        if let Some(value) = self.memo.get(state) {
+            return value;
+        }
+
+        let problem = &self.compression.meta_problem;
+        let expand = |state: PspState, cost: isize| {
+            let mut decisions = vec![];
+            problem.for_each_in_domain(Variable(state.time - 1), &state, &mut |d: Decision| decisions.push(d));
+            Frame { state, decisions, best: None, cost }
+        };
+
+        let mut stack = vec![expand(state.clone(), 0)];
+        loop {
+            let frame = stack.last_mut().expect("the stack holds the given meta-state until it is done");
+            match frame.decisions.pop() {
+                Some(decision) => {
+                    let next = problem.transition(&frame.state, decision);
+                    if self.is_infeasible(&next) {
+                        continue;
+                    }
+                    let cost = problem.transition_cost(&frame.state, decision);
+                    let known = if next.time == 0 { Some(Some(0)) } else { self.memo.get(&next) };
+                    match known {
+                        Some(value) => frame.improve(value.map(|v| v + cost)),
+                        None => stack.push(expand(next, cost)),
+                    }
+                },
+                None => {
+                    let frame = stack.pop().expect("the frame is on the stack");
+                    self.memo.insert(frame.state, frame.best);
+                    match stack.last_mut() {
+                        Some(parent) => parent.improve(frame.best.map(|v| v + frame.cost)),
+                        None => return frame.best,
+                    }
+                },
+            }
+        }
+    }
+}
@@ -0,0 +1,70 @@
+//! This module stores the results of the benchmarks in a SQLite database, with one row
+//! per instance, options of the solver, crate version and run. The fingerprint of the
+//! instance is recorded so that the files that were modified can be told apart.
+
+use rusqlite::{params, Connection};
+
+use crate::error::PspError;
+use crate::resolution::bench::Run;
+use crate::resolution::model::Psp;
+
+pub struct ResultsDb {
+    path: String,
+    connection: Connection,
+}
+
+impl ResultsDb {
+    /// Opens the given database, and creates its results table if needed
+    pub fn open(path: &str) -> Result<Self, PspError> {
+        let error = |source| PspError::Database { path: path.to_string(), source };
+        let connection = Connection::open(path).map_err(error)?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS results (
+                instance TEXT NOT NULL,
+                options  TEXT NOT NULL,
+                version  TEXT NOT NULL,
+                run      INTEGER NOT NULL,
+                status   TEXT NOT NULL,
+                cost     REAL,
+                bound    REAL,
+                gap      REAL,
+                time     REAL NOT NULL,
+                nodes    INTEGER NOT NULL,
+                date     TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                fingerprint TEXT,
+                PRIMARY KEY (instance, options, version, run)
+            );"
+        ).map_err(error)?;
+        // the databases created before the fingerprints were recorded lack their column
+        let fingerprinted = connection.prepare("SELECT 1 FROM pragma_table_info('results') WHERE name = 'fingerprint'")
+            .and_then(|mut statement| statement.exists([]))
+            .map_err(error)?;
+        if !fingerprinted {
+            connection.execute_batch("ALTER TABLE results ADD COLUMN fingerprint TEXT;").map_err(error)?;
+        }
+        Ok(ResultsDb { path: path.to_string(), connection })
+    }
+
+    /// Stores the outcome of the given run, replacing the one of an identical run
+    pub fn insert(&self, instance: &str, options: &str, run: u32, problem: &Psp, result: &Run) -> Result<(), PspError> {
+        let options = options.split_whitespace().collect::<Vec<&str>>().join(" ");
+        self.connection.execute(
+            "INSERT OR REPLACE INTO results (instance, options, version, run, status, cost, bound, gap, time, nodes, fingerprint)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                instance,
+                options,
+                env!("CARGO_PKG_VERSION"),
+                run,
+                result.status.name(),
+                result.cost.map(|c| problem.unscale(c)),
+                result.bound.map(|b| problem.unscale(b)),
+                result.gap,
+                result.time,
+                result.nodes as i64,
+                problem.fingerprint,
+            ],
+        ).map_err(|source| PspError::Database { path: self.path.clone(), source })?;
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+//! This module implements a dominance check between the subproblems of the
+//! branch-and-bound. A state dominates another one at the same time and with the same
+//! next item when each of its items has no more remaining demands, when it is not
+//! subject to more forbidden solutions, and when its value is no worse. Any completion
+//! of the dominated state can then be turned into a completion of the dominating one
+//! that is no more expensive by dropping the extra productions, provided that the
+//! changeover costs satisfy the triangle inequality.
+//!
+//! The check is performed by the fringe: dominated subproblems are simply never pushed.
+
+
+use ddo::{Fringe, SubProblem};
+
+use crate::resolution::hashing::StateMap;
+use crate::resolution::model::PspState;
+use crate::resolution::prev_demands::PrevDemands;
+
+/// A fringe that discards the subproblems dominated by a subproblem that was
+/// previously pushed in the inner fringe
+pub struct DominanceFringe<F> {
+    inner: F,
+    /// The remaining demands, forbidden solutions and value of the states pushed so far,
+    /// grouped by time and next item
+    archive: StateMap<(usize, isize), Vec<(PrevDemands, u64, isize)>>,
+}
+
+impl<F> DominanceFringe<F> {
+    pub fn new(inner: F) -> Self {
+        DominanceFringe { inner, archive: StateMap::default() }
+    }
+
+    fn dominates(a: &(PrevDemands, u64, isize), prev_demands: &PrevDemands, matching: u64, value: isize) -> bool {
+        a.2 >= value 
+            && a.1 & !matching == 0
+            && a.0.iter().zip(prev_demands.iter()).all(|(x, y)| x <= y)
+    }
+}
+
+impl<F: Fringe<State = PspState>> Fringe for DominanceFringe<F> {
+    type State = PspState;
+
+    fn push(&mut self, node: SubProblem<Self::State>) {
+        let state = node.state.as_ref();
+        let archived = self.archive.entry((state.time, state.next)).or_default();
+
+        if archived.iter().any(|a| Self::dominates(a, &state.prev_demands, state.matching, node.value)) {
+            return;
+        }
+
+        archived.retain(|a| !Self::dominates(&(state.prev_demands.clone(), state.matching, node.value), &a.0, a.1, a.2));
+        archived.push((state.prev_demands.clone(), state.matching, node.value));
+        self.inner.push(node);
+    }
+
+    fn pop(&mut self) -> Option<SubProblem<Self::State>> {
+        self.inner.pop()
+    }
+
+    fn clear(&mut self) {
+        self.archive.clear();
+        self.inner.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+}
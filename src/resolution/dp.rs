@@ -0,0 +1,68 @@
+//! This module implements the classical dynamic program over the states of the model
+//! (period, next item and remaining demands), layer by layer and without any width
+//! limit. Each layer keeps all its distinct states, so that this exact baseline only
+//! fits in memory for medium instances.
+
+use std::collections::hash_map::Entry;
+
+use tracing::debug;
+use ddo::{Problem, Decision, Solution, Cutoff};
+
+use crate::resolution::hashing::StateMap;
+use crate::resolution::model::{Psp, PspState};
+
+/// The best way found to reach a state of a layer
+struct Label {
+    value: isize,
+    /// The index of the parent state in the previous layer
+    parent: usize,
+    decision: Decision,
+}
+
+/// Solves the problem to optimality unless the cutoff is reached. Returns whether the
+/// resolution completed, with the value and the decisions of the optimal solution
+pub fn dynamic_programming(problem: &Psp, cutoff: &dyn Cutoff) -> (bool, Option<(isize, Solution)>) {
+    let mut layer = vec![(problem.initial_state(), problem.initial_value())];
+    // the parent and the decision of each state of each layer after the root
+    let mut labels: Vec<Vec<(usize, Decision)>> = vec![];
+
+    for depth in 0..problem.nb_variables() {
+        let Some(variable) = problem.next_variable(depth, &mut layer.iter().map(|(s, _)| s)) else {
+            break;
+        };
+
+        let mut next: StateMap<PspState, Label> = StateMap::default();
+        for (parent, (state, value)) in layer.iter().enumerate() {
+            if cutoff.must_stop() {
+                return (false, None);
+            }
+            problem.for_each_in_domain(variable, state, &mut |decision: Decision| {
+                let value = value + problem.transition_cost(state, decision);
+                match next.entry(problem.transition(state, decision)) {
+                    Entry::Occupied(mut e) if e.get().value < value => { e.insert(Label { value, parent, decision }); },
+                    Entry::Occupied(_) => {},
+                    Entry::Vacant(e) => { e.insert(Label { value, parent, decision }); },
+                }
+            });
+        }
+
+        debug!(depth, states = next.len(), "dp layer computed");
+        let (states, layer_labels): (Vec<(PspState, isize)>, Vec<(usize, Decision)>) = next.into_iter()
+            .map(|(state, label)| ((state, label.value), (label.parent, label.decision)))
+            .unzip();
+        layer = states;
+        labels.push(layer_labels);
+    }
+
+    let Some((mut index, value)) = layer.iter().enumerate().max_by_key(|(_, (_, v))| *v).map(|(i, (_, v))| (i, *v)) else {
+        return (true, None);
+    };
+    let mut solution = Vec::with_capacity(labels.len());
+    for layer_labels in labels.iter().rev() {
+        let (parent, decision) = layer_labels[index];
+        solution.push(decision);
+        index = parent;
+    }
+    solution.reverse();
+    (true, Some((value, solution)))
+}
@@ -0,0 +1,89 @@
+//! This module implements a brute-force reference solver for tiny instances: every
+//! schedule is enumerated and the cheapest feasible one is returned. It does not rely on
+//! the DP model nor on its relaxation, so that they can be cross-checked with it.
+
+use ddo::{Solution, Cutoff};
+
+use crate::resolution::model::{Psp, IDLE};
+
+/// The largest horizon for which the enumeration is allowed
+pub const EXHAUSTIVE_MAX_PERIODS: usize = 12;
+
+/// Enumerates the schedules of the problem and returns whether the enumeration completed,
+/// with the value and the decisions of the best feasible schedule found
+pub fn exhaustive(problem: &Psp, cutoff: &dyn Cutoff) -> (bool, Option<(isize, Solution)>) {
+    let demanded = (0..problem.n_items)
+        .map(|i| problem.demands_of(i).iter().map(|d| d.quantity).sum::<usize>())
+        .collect::<Vec<usize>>();
+    let mut search = Search {
+        problem,
+        cutoff,
+        schedule: vec![IDLE; problem.horizon],
+        remaining: demanded,
+        best: None,
+        stopped: false,
+    };
+    search.enumerate(0);
+
+    let best = search.best.map(|(cost, schedule)| (-cost, problem.solution(&schedule)));
+    (!search.stopped, best)
+}
+
+struct Search<'a> {
+    problem: &'a Psp,
+    cutoff: &'a dyn Cutoff,
+    schedule: Vec<isize>,
+    /// The number of units of each item that can still be produced
+    remaining: Vec<usize>,
+    /// The cost of the best schedule found and that schedule
+    best: Option<(isize, Vec<isize>)>,
+    stopped: bool,
+}
+
+impl Search<'_> {
+    /// Tries every decision at period t, the schedule being fixed before t. The items
+    /// are never produced more than they are demanded, as such schedules are infeasible
+    fn enumerate(&mut self, t: usize) {
+        if self.stopped {
+            return;
+        }
+        if t == self.problem.horizon {
+            self.evaluate();
+            return;
+        }
+
+        for value in IDLE..=self.problem.max_value() {
+            if self.problem.fixed[t].map_or(false, |v| v != value) || (value != IDLE && self.problem.is_down(t)) {
+                continue;
+            }
+            let (item, units) = (self.problem.item_of(value), self.problem.units_of(value));
+            if value != IDLE {
+                if self.remaining[item as usize] < units {
+                    continue;
+                }
+                self.remaining[item as usize] -= units;
+            }
+            self.schedule[t] = value;
+            self.enumerate(t + 1);
+            if value != IDLE {
+                self.remaining[item as usize] += units;
+            }
+        }
+        self.schedule[t] = IDLE;
+    }
+
+    fn evaluate(&mut self) {
+        if self.cutoff.must_stop() {
+            self.stopped = true;
+            return;
+        }
+        if self.problem.forbidden.contains(&self.schedule) {
+            return;
+        }
+        if let Some(cost) = self.problem.cost(&self.schedule) {
+            if self.best.as_ref().map_or(true, |(best, _)| cost < *best) {
+                self.best = Some((cost, self.schedule.clone()));
+            }
+        }
+    }
+}
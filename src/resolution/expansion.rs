@@ -0,0 +1,59 @@
+//! This module expands the nodes of a layer of the decision diagrams compiled by the
+//! crate. With the rayon feature, the successors of the nodes of a wide layer are
+//! computed by several threads. They are always returned in the order of their parents
+//! and of their decisions, so that the layers that are built from them do not depend on
+//! the number of threads. The diagrams compiled by the solvers of ddo are not affected.
+
+use ddo::{Decision, Problem, Variable};
+#[cfg(all(feature = "rayon", not(feature = "sequential")))]
+use rayon::prelude::*;
+
+use crate::resolution::model::{Psp, PspState};
+
+/// The minimum number of nodes of a layer for its expansion to be parallelized
+#[cfg(all(feature = "rayon", not(feature = "sequential")))]
+const MIN_PARALLEL_NODES: usize = 64;
+
+/// A transition from a node of a layer
+pub struct Successor {
+    /// The index of the parent in the expanded nodes
+    pub parent: usize,
+    pub decision: Decision,
+    pub state: PspState,
+    pub cost: isize,
+}
+
+/// Returns the successors of the given states by the decisions on the given variable
+/// that are accepted by the filter, in the order of the states
+pub fn expand(problem: &Psp, variable: Variable, states: &[&PspState], filter: &(dyn Fn(Decision) -> bool + Sync)) -> Vec<Successor> {
+    let mut successors = vec![];
+    expand_into(problem, variable, states, filter, &mut successors);
+    successors
+}
+
+/// Appends the successors of the given states to the given buffer like `expand`, so that
+/// the compilations of consecutive layers reuse the same buffer
+pub fn expand_into(problem: &Psp, variable: Variable, states: &[&PspState], filter: &(dyn Fn(Decision) -> bool + Sync), successors: &mut Vec<Successor>) {
+    #[cfg(all(feature = "rayon", not(feature = "sequential")))]
+    if states.len() >= MIN_PARALLEL_NODES {
+        successors.par_extend(states.par_iter().enumerate().flat_map_iter(|(parent, state)| {
+            let mut successors = vec![];
+            push_successors(problem, variable, parent, state, filter, &mut successors);
+            successors
+        }));
+        return;
+    }
+    for (parent, state) in states.iter().enumerate() {
+        push_successors(problem, variable, parent, state, filter, successors);
+    }
+}
+
+/// Appends the successors of the given state to the given buffer
+fn push_successors(problem: &Psp, variable: Variable, parent: usize, state: &PspState, filter: &(dyn Fn(Decision) -> bool + Sync), successors: &mut Vec<Successor>) {
+    problem.for_each_in_domain(variable, state, &mut |decision: Decision| {
+        if filter(decision) {
+            let cost = problem.transition_cost(state, decision);
+            successors.push(Successor { parent, decision, state: problem.transition(state, decision), cost });
+        }
+    });
+}
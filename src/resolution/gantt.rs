@@ -0,0 +1,64 @@
+//! This module draws a schedule as a Gantt chart in the SVG format: there is one row
+//! per item with its production blocks and its due dates, and the changeovers are
+//! marked on the time axis.
+
+use std::fmt::Write;
+
+use crate::resolution::model::{Psp, IDLE};
+
+const CELL: usize = 20;
+const MARGIN: usize = 60;
+
+/// Returns the Gantt chart of the given schedule
+pub fn gantt_svg(problem: &Psp, schedule: &[isize]) -> String {
+    let width = MARGIN + CELL * problem.horizon + CELL;
+    let height = CELL * (problem.n_items + 2);
+    let row = |item: usize| CELL * (item + 1);
+    let col = |t: usize| MARGIN + CELL * t;
+    let color = |item: usize| format!("hsl({}, 70%, 55%)", item * 360 / problem.n_items.max(1));
+
+    let mut out = String::new();
+    writeln!(out, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" font-family="sans-serif" font-size="10">"#).unwrap();
+    writeln!(out, r#"<rect width="{width}" height="{height}" fill="white"/>"#).unwrap();
+
+    for item in 0..problem.n_items {
+        let y = row(item);
+        writeln!(out, r#"<text x="4" y="{}">item {item}</text>"#, y + CELL * 2 / 3).unwrap();
+        writeln!(out, r#"<line x1="{MARGIN}" y1="{y}" x2="{}" y2="{y}" stroke="lightgray"/>"#, col(problem.horizon)).unwrap();
+    }
+    for t in 0..problem.horizon {
+        if t % 5 == 0 {
+            writeln!(out, r#"<text x="{}" y="{}">{t}</text>"#, col(t) + 2, height - CELL / 3).unwrap();
+        }
+    }
+
+    let (mut prev, mut before) = (IDLE, IDLE);
+    for (t, v) in schedule.iter().copied().enumerate() {
+        if v == IDLE {
+            if !problem.keeps_setup() {
+                (prev, before) = (IDLE, IDLE);
+            }
+            continue;
+        }
+        let item = problem.item_of(v) as usize;
+        let overtime = if problem.units_of(v) > 1 { " in overtime" } else { "" };
+        writeln!(out, r#"<rect x="{}" y="{}" width="{CELL}" height="{CELL}" fill="{}" stroke="black"><title>t {t}: item {item}{overtime}</title></rect>"#,
+            col(t), row(item), color(item)).unwrap();
+        if prev != IDLE && prev != item as isize {
+            writeln!(out, r#"<line x1="{x}" y1="{}" x2="{x}" y2="{}" stroke="red" stroke-width="2"><title>changeover {prev} to {item}: {}</title></line>"#,
+                CELL / 2, height - CELL, problem.format_cost(problem.changeover_after(before, prev as usize, item) as isize), x = col(t)).unwrap();
+            before = prev;
+        }
+        prev = item as isize;
+    }
+
+    for item in 0..problem.n_items {
+        for d in problem.demands_of(item) {
+            writeln!(out, r#"<circle cx="{}" cy="{}" r="3" fill="black"><title>item {item} due at {}: {} units</title></circle>"#,
+                col(d.period) + CELL - 3, row(item) + CELL / 2, d.period, d.quantity).unwrap();
+        }
+    }
+
+    writeln!(out, "</svg>").unwrap();
+    out
+}
@@ -0,0 +1,14 @@
+//! This module defines the maps keyed by states. With the fxhash feature, they use the
+//! FxHash function, which is much faster than the default SipHash on the short keys of
+//! the states but offers no protection against crafted collisions.
+
+#[cfg(feature = "fxhash")]
+pub type StateMap<K, V> = fxhash::FxHashMap<K, V>;
+#[cfg(not(feature = "fxhash"))]
+pub type StateMap<K, V> = std::collections::HashMap<K, V>;
+
+/// The hash function of the maps keyed by states
+#[cfg(feature = "fxhash")]
+pub type StateHasher = fxhash::FxHasher;
+#[cfg(not(feature = "fxhash"))]
+pub type StateHasher = std::collections::hash_map::DefaultHasher;
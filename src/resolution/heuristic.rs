@@ -0,0 +1,286 @@
+//! This module implements fast primal heuristics that are used when optimality
+//! needs not be proved, or to provide initial solutions to the other methods.
+//!
+//! The paths and the states of the nodes of the restricted DDs are allocated in arenas,
+//! and the buffers of a layer are reused by the next one, which the benchmark
+//! benches/restricted_dd.rs measures.
+
+use std::collections::hash_map::Entry;
+
+use tracing::trace;
+use ddo::{Problem, Relaxation, StateRanking, Decision, Solution, Cutoff};
+
+use crate::resolution::arena::StateArena;
+use crate::resolution::expansion::{expand, expand_into};
+use crate::resolution::hashing::StateMap;
+use crate::resolution::model::{Psp, PspState, PspRelax, PspRanking, IDLE};
+use crate::resolution::viz::{Diagram, VizNode, NodeKind, Edge};
+
+/// A node of a restricted decision diagram
+struct Node {
+    /// The slot of the state of the node in the arena
+    state: usize,
+    value: isize,
+    /// The entry of the last decision of the path to the node in the arena
+    path: usize,
+}
+
+/// The entry of the empty path of the root
+const ROOT_PATH: usize = usize::MAX;
+
+/// The decisions of the paths to the nodes of a restricted DD. The paths share their
+/// prefixes, so each decision is stored once with the entry of the decision before it,
+/// instead of copying the whole path at each transition
+#[derive(Default)]
+struct PathArena {
+    entries: Vec<(usize, Decision)>,
+}
+
+impl PathArena {
+    /// Appends the given decision to the given path, and returns the entry of the new path
+    fn push(&mut self, path: usize, decision: Decision) -> usize {
+        self.entries.push((path, decision));
+        self.entries.len() - 1
+    }
+
+    /// Returns the decisions of the given path, from the root
+    fn solution(&self, mut path: usize) -> Solution {
+        let mut solution = vec![];
+        while path != ROOT_PATH {
+            let (parent, decision) = self.entries[path];
+            solution.push(decision);
+            path = parent;
+        }
+        solution.reverse();
+        solution
+    }
+}
+
+/// The outcome of the compilation of a restricted decision diagram
+pub struct Restriction {
+    /// The value and the decisions of the best solution found, if any
+    pub best: Option<(isize, Solution)>,
+    /// True iff no node had to be dropped, which means the best solution is optimal
+    pub is_exact: bool,
+}
+
+/// Compiles a restricted DD of the given width from the root of the problem. When a
+/// layer is too wide, only the nodes with the best value are kept (ties are broken with
+/// the ranking).
+pub fn restricted_dd(problem: &Psp, ranking: &PspRanking, width: usize, cutoff: &dyn Cutoff) -> Restriction {
+    restricted_dd_with(problem, ranking, width, cutoff, &|_| true)
+}
+
+/// Compiles a restricted DD like `restricted_dd`, where only the decisions accepted 
+/// by the given filter are taken
+pub fn restricted_dd_with(problem: &Psp, ranking: &PspRanking, width: usize, cutoff: &dyn Cutoff, filter: &(dyn Fn(Decision) -> bool + Sync)) -> Restriction {
+    let mut is_exact = true;
+    let mut arena = PathArena::default();
+    let mut states = StateArena::default();
+    let mut layer = vec![Node {
+        state: states.alloc(problem.initial_state()),
+        value: problem.initial_value(),
+        path: ROOT_PATH,
+    }];
+    // the buffers of a layer are reused by the next one
+    let mut successors = vec![];
+    let mut next: StateMap<PspState, (isize, usize, Decision)> = StateMap::default();
+    let mut nodes: Vec<(Node, Decision)> = vec![];
+
+    for depth in 0..problem.nb_variables() {
+        if cutoff.must_stop() {
+            return Restriction { best: None, is_exact: false };
+        }
+
+        let Some(variable) = problem.next_variable(depth, &mut layer.iter().map(|n| states.get(n.state))) else {
+            break;
+        };
+
+        // the decisions are only stored in the arena once the layer is restricted
+        let parents = layer.iter().map(|n| states.get(n.state)).collect::<Vec<&PspState>>();
+        expand_into(problem, variable, &parents, filter, &mut successors);
+        for successor in successors.drain(..) {
+            let node = &layer[successor.parent];
+            let value = node.value + successor.cost;
+
+            match next.get_mut(&successor.state) {
+                Some(best) if best.0 >= value => {},
+                Some(best) => *best = (value, node.path, successor.decision),
+                None => {
+                    next.insert(successor.state, (value, node.path, successor.decision));
+                },
+            }
+        }
+        for node in layer.drain(..) {
+            states.release(node.state);
+        }
+
+        nodes.extend(next.drain().map(|(state, (value, path, decision))| (Node { state: states.alloc(state), value, path }, decision)));
+        if nodes.len() > width {
+            is_exact = false;
+            nodes.sort_unstable_by(|(a, _), (b, _)| b.value.cmp(&a.value).then_with(|| ranking.compare(states.get(b.state), states.get(a.state))));
+            for (node, _) in nodes.drain(width..) {
+                states.release(node.state);
+            }
+        }
+        layer.extend(nodes.drain(..).map(|(node, decision)| Node { path: arena.push(node.path, decision), ..node }));
+
+        trace!(depth, nodes = layer.len(), "restricted layer compiled");
+    }
+
+    let best = layer.into_iter()
+        .max_by_key(|n| n.value)
+        .map(|n| (n.value, arena.solution(n.path)));
+
+    Restriction { best, is_exact }
+}
+
+/// Compiles a restricted DD like `restricted_dd` and returns all its layers, including
+/// the nodes that were dropped. Returns None if the cutoff is reached
+pub fn compile_restricted(problem: &Psp, ranking: &PspRanking, width: usize, cutoff: &dyn Cutoff) -> Option<Diagram> {
+    let width = width.max(1);
+    let mut diagram = Diagram { layers: vec![vec![VizNode::root(problem)]] };
+
+    for depth in 0..problem.nb_variables() {
+        if cutoff.must_stop() {
+            return None;
+        }
+
+        let layer = diagram.layers.last().expect("the root layer is always present");
+        let mut states = layer.iter().filter(|n| n.kind != NodeKind::Deleted).map(|n| &n.state);
+        let Some(variable) = problem.next_variable(depth, &mut states) else {
+            break;
+        };
+
+        let mut next: StateMap<PspState, VizNode> = StateMap::default();
+        let alive = (0..layer.len()).filter(|i| layer[*i].kind != NodeKind::Deleted).collect::<Vec<usize>>();
+        let states = alive.iter().map(|i| &layer[*i].state).collect::<Vec<&PspState>>();
+        for successor in expand(problem, variable, &states, &|_| true) {
+            let i = alive[successor.parent];
+            let value = layer[i].value + successor.cost;
+            let edge = Edge { parent: i, decision: successor.decision.value, cost: successor.cost };
+
+            match next.entry(successor.state) {
+                Entry::Occupied(mut e) => {
+                    let n = e.get_mut();
+                    n.value = n.value.max(value);
+                    n.parents.push(edge);
+                },
+                Entry::Vacant(e) => {
+                    let state = e.key().clone();
+                    e.insert(VizNode { state, value, kind: NodeKind::Exact, parents: vec![edge], merged_into: None });
+                },
+            }
+        }
+
+        let mut nodes = next.into_values().collect::<Vec<VizNode>>();
+        if nodes.len() > width {
+            nodes.sort_unstable_by(|a, b| b.value.cmp(&a.value).then_with(|| ranking.compare(&b.state, &a.state)));
+            nodes[width..].iter_mut().for_each(|n| n.kind = NodeKind::Deleted);
+        }
+
+        trace!(depth, nodes = nodes.len(), "restricted layer compiled");
+        diagram.layers.push(nodes);
+    }
+
+    Some(diagram)
+}
+
+/// Builds a schedule backwards in time, producing a pending demand whenever possible
+/// (as late as possible) and choosing the item whose changeover and stocking costs are
+/// the cheapest. Returns None if the schedule reaches a dead end.
+pub fn greedy(problem: &Psp) -> Option<(isize, Solution)> {
+    let mut state = problem.initial_state();
+    let mut value = problem.initial_value();
+    let mut path = vec![];
+
+    for depth in 0..problem.nb_variables() {
+        let variable = problem.next_variable(depth, &mut std::iter::once(&state))?;
+
+        let mut best: Option<(isize, Decision)> = None;
+        let mut can_idle = false;
+        problem.for_each_in_domain(variable, &state, &mut |decision: Decision| {
+            if decision.value == IDLE {
+                can_idle = true;
+            } else {
+                let cost = problem.transition_cost(&state, decision);
+                if best.map_or(true, |(c, _)| cost > c) {
+                    best = Some((cost, decision));
+                }
+            }
+        });
+
+        let decision = match best {
+            Some((_, decision)) => decision,
+            None if can_idle => Decision { variable, value: IDLE },
+            None => return None,
+        };
+
+        value += problem.transition_cost(&state, decision);
+        state = problem.transition(&state, decision);
+        path.push(decision);
+    }
+
+    Some((value, path))
+}
+
+/// Builds a schedule backwards in time like greedy, but each decision is the one that
+/// starts the best sequence of the given number of decisions, whose value is completed
+/// by the rough upper bound of the state it reaches. The productions are preferred to
+/// the idle periods on ties. Without lookahead, this is the greedy heuristic
+pub fn lookahead_greedy(problem: &Psp, lookahead: usize) -> Option<(isize, Solution)> {
+    if lookahead == 0 {
+        return greedy(problem);
+    }
+    let relaxation = PspRelax::new(problem.clone());
+    let mut state = problem.initial_state();
+    let mut value = problem.initial_value();
+    let mut path = vec![];
+
+    for depth in 0..problem.nb_variables() {
+        let variable = problem.next_variable(depth, &mut std::iter::once(&state))?;
+
+        let mut best: Option<(isize, Decision)> = None;
+        problem.for_each_in_domain(variable, &state, &mut |decision: Decision| {
+            let next = problem.transition(&state, decision);
+            let Some(ahead) = look_ahead(problem, &relaxation, depth + 1, &next, lookahead - 1) else {
+                return;
+            };
+            let score = problem.transition_cost(&state, decision).saturating_add(ahead);
+            let is_better = best.map_or(true, |(s, d)| score > s || (score == s && d.value == IDLE && decision.value != IDLE));
+            if is_better {
+                best = Some((score, decision));
+            }
+        });
+
+        let (_, decision) = best?;
+        value += problem.transition_cost(&state, decision);
+        state = problem.transition(&state, decision);
+        path.push(decision);
+    }
+
+    Some((value, path))
+}
+
+/// Returns the best value of the sequences of at most the given number of decisions from
+/// the given state, plus the rough upper bound of the state they reach, or None if they
+/// all reach a dead end
+fn look_ahead(problem: &Psp, relaxation: &PspRelax, depth: usize, state: &PspState, remaining: usize) -> Option<isize> {
+    if depth == problem.nb_variables() {
+        return Some(0);
+    }
+    if remaining == 0 {
+        return Some(relaxation.fast_upper_bound(state));
+    }
+    let variable = problem.next_variable(depth, &mut std::iter::once(state))?;
+
+    let mut best: Option<isize> = None;
+    problem.for_each_in_domain(variable, state, &mut |decision: Decision| {
+        let next = problem.transition(state, decision);
+        if let Some(ahead) = look_ahead(problem, relaxation, depth + 1, &next, remaining - 1) {
+            let value = problem.transition_cost(state, decision).saturating_add(ahead);
+            best = Some(best.map_or(value, |b| b.max(value)));
+        }
+    });
+    best
+}
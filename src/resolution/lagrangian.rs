@@ -0,0 +1,194 @@
+//! This module implements a Lagrangian lower bound on the stocking costs. Each demand
+//! constraint (the k-th unit of an item must be produced before its deadline) and
+//! the number of productions of each item are dualized, which leaves a subproblem where
+//! each period independently produces the item of smallest reduced cost. The multipliers
+//! are optimized with a subgradient method at the root. Because any set of multipliers
+//! yields a valid bound, the same multipliers are reused to bound the remaining stocking
+//! costs of any state.
+
+use crate::resolution::heuristic::greedy;
+use crate::resolution::model::Psp;
+use crate::resolution::prev_demands::PrevDemands;
+
+pub struct LagrangianBound {
+    horizon: usize,
+    stocking: Vec<f64>,
+    /// The deadline of each unit of each item, in chronological order
+    deadlines: Vec<Vec<usize>>,
+    /// For each item and period p, the number of units whose deadline is before p
+    count: Vec<Vec<usize>>,
+    /// The multiplier of the k-th unit of each item
+    lambda: Vec<Vec<f64>>,
+    /// The multiplier of the number of productions of each item
+    nu: Vec<f64>,
+    /// The prefix sums of the multipliers of the demands of each item
+    lambda_sums: Vec<Vec<f64>>,
+    /// The constant part of the Lagrangian function for the first m demands of each item
+    constants: Vec<Vec<f64>>,
+    /// The best bound obtained at the root
+    root: f64,
+}
+
+impl LagrangianBound {
+    /// Computes the multipliers with the given number of subgradient iterations
+    pub fn new(problem: &Psp, iterations: usize) -> Self {
+        let deadlines = (0..problem.n_items)
+            .map(|i| problem.deadlines(i).collect::<Vec<usize>>())
+            .collect::<Vec<Vec<usize>>>();
+        let count = deadlines.iter()
+            .map(|d| (0..=problem.horizon).map(|p| d.iter().filter(|t| **t < p).count()).collect())
+            .collect();
+
+        let mut bound = LagrangianBound {
+            horizon: problem.horizon,
+            stocking: problem.stocking.iter().map(|h| *h as f64).collect(),
+            lambda: deadlines.iter().map(|d| vec![0.0; d.len()]).collect(),
+            nu: vec![0.0; problem.n_items],
+            lambda_sums: vec![],
+            constants: vec![],
+            deadlines,
+            count,
+            root: 0.0,
+        };
+        bound.refresh();
+
+        let target = greedy(problem)
+            .and_then(|(_, solution)| {
+                let schedule = problem.schedule(&solution);
+                problem.cost(&schedule)
+                    .map(|c| c - problem.changeover_cost(&schedule) - problem.idle_cost(&schedule) - problem.offset as isize)
+            })
+            .map(|c| c as f64);
+        bound.optimize(iterations, target);
+        bound
+    }
+
+    /// Returns the bound obtained at the root
+    pub fn root_bound(&self) -> isize {
+        Self::round(self.root)
+    }
+
+    /// Returns a lower bound on the stocking costs of the units that remain to be
+    /// produced in the periods before the given time
+    pub fn stocking_bound(&self, time: usize, prev_demands: &PrevDemands) -> isize {
+        let remaining = prev_demands.iter()
+            .map(|prev| (prev as isize + 1) as usize)
+            .collect::<Vec<usize>>();
+        Self::round(self.solve(time, &remaining).0)
+    }
+
+    fn round(value: f64) -> isize {
+        (value - 1e-6).ceil().max(0.0) as isize
+    }
+
+    /// Maximizes the Lagrangian function with a subgradient method using Polyak steps
+    /// towards the given target value
+    fn optimize(&mut self, iterations: usize, target: Option<f64>) {
+        let all = self.deadlines.iter().map(|d| d.len()).collect::<Vec<usize>>();
+        let mut best = (f64::MIN, self.lambda.clone(), self.nu.clone());
+        let mut beta = 2.0;
+        let mut stall = 0;
+
+        for _ in 0..iterations {
+            let (value, choices) = self.solve(self.horizon, &all);
+            if value > best.0 + 1e-9 {
+                best = (value, self.lambda.clone(), self.nu.clone());
+                stall = 0;
+            } else {
+                stall += 1;
+                if stall >= 10 {
+                    beta /= 2.0;
+                    stall = 0;
+                }
+            }
+
+            let mut g_lambda = self.deadlines.iter().map(|d| vec![0.0; d.len()]).collect::<Vec<Vec<f64>>>();
+            let mut g_nu = vec![0.0; self.nu.len()];
+            let mut norm = 0.0;
+            for (i, deadlines) in self.deadlines.iter().enumerate() {
+                let produced = choices.iter().enumerate()
+                    .filter(|(_, c)| **c == Some(i))
+                    .map(|(p, _)| p)
+                    .collect::<Vec<usize>>();
+                for (k, d) in deadlines.iter().enumerate() {
+                    let before = produced.iter().filter(|p| **p <= *d).count();
+                    g_lambda[i][k] = (k + 1) as f64 - before as f64;
+                    norm += g_lambda[i][k] * g_lambda[i][k];
+                }
+                g_nu[i] = deadlines.len() as f64 - produced.len() as f64;
+                norm += g_nu[i] * g_nu[i];
+            }
+
+            if norm == 0.0 {
+                break;
+            }
+
+            let gap = target.map_or(value.abs().max(1.0), |t| (t - value).max(1.0));
+            let step = beta * gap / norm;
+            for i in 0..self.nu.len() {
+                for k in 0..self.lambda[i].len() {
+                    self.lambda[i][k] = (self.lambda[i][k] + step * g_lambda[i][k]).max(0.0);
+                }
+                self.nu[i] += step * g_nu[i];
+            }
+            self.refresh();
+        }
+
+        self.root = best.0.max(0.0);
+        self.lambda = best.1;
+        self.nu = best.2;
+        self.refresh();
+    }
+
+    /// Recomputes the prefix sums that depend on the multipliers
+    fn refresh(&mut self) {
+        self.lambda_sums = self.lambda.iter()
+            .map(|l| {
+                let mut sums = vec![0.0];
+                for v in l.iter() {
+                    sums.push(sums.last().unwrap() + v);
+                }
+                sums
+            })
+            .collect();
+
+        self.constants = (0..self.nu.len())
+            .map(|i| {
+                let mut constants = vec![0.0];
+                for (k, d) in self.deadlines[i].iter().enumerate() {
+                    let c = self.lambda[i][k] * (k + 1) as f64 + self.stocking[i] * *d as f64 + self.nu[i];
+                    constants.push(constants.last().unwrap() + c);
+                }
+                constants
+            })
+            .collect();
+    }
+
+    /// Solves the Lagrangian subproblem over the periods before the given time, when only
+    /// the given number of (first) units remain to be produced for each item. Returns its
+    /// value as well as the item produced at each period
+    fn solve(&self, time: usize, remaining: &[usize]) -> (f64, Vec<Option<usize>>) {
+        let mut value = remaining.iter().enumerate().map(|(i, m)| self.constants[i][*m]).sum::<f64>();
+        let mut choices = vec![None; time];
+
+        for (p, choice) in choices.iter_mut().enumerate() {
+            let mut best = 0.0;
+            for (i, m) in remaining.iter().copied().enumerate() {
+                if m == 0 || p > self.deadlines[i][m - 1] {
+                    continue;
+                }
+                let served = self.count[i][p].min(m);
+                let coef = -self.stocking[i] * p as f64 
+                    - (self.lambda_sums[i][m] - self.lambda_sums[i][served]) 
+                    - self.nu[i];
+                if coef < best {
+                    best = coef;
+                    *choice = Some(i);
+                }
+            }
+            value += best;
+        }
+
+        (value, choices)
+    }
+}
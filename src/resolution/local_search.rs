@@ -0,0 +1,93 @@
+//! This module implements a local search that improves a feasible schedule by swapping
+//! the decisions taken at two periods (which shifts a production when one of them
+//! is idle), until a local optimum is reached.
+
+use ddo::{Solution, Cutoff};
+
+use crate::resolution::model::{Psp, IDLE};
+
+/// Applies the first improving swap move until none remains, and returns the value
+/// and the decisions of the resulting schedule
+pub fn local_search(problem: &Psp, value: isize, solution: &Solution, cutoff: &dyn Cutoff) -> (isize, Solution) {
+    // the cost variations of the swaps do not account for the units produced in overtime
+    if problem.overtime.is_some() {
+        return (value, solution.clone());
+    }
+    let mut schedule = problem.schedule(solution);
+    let mut cost = -value;
+    let mut improved = true;
+
+    while improved && !cutoff.must_stop() {
+        improved = false;
+        for t1 in 0..problem.horizon {
+            for t2 in (t1 + 1)..problem.horizon {
+                if schedule[t1] == schedule[t2] || problem.fixed[t1].is_some() || problem.fixed[t2].is_some() {
+                    continue;
+                }
+
+                let delta = swap_delta(problem, &mut schedule, t1, t2);
+                if delta >= 0 {
+                    continue;
+                }
+
+                schedule.swap(t1, t2);
+                let (moved_earlier, moved_later) = (schedule[t1], schedule[t2]);
+                if (moved_later == IDLE || problem.is_item_feasible(&schedule, moved_later as usize))
+                    && (moved_earlier == IDLE || problem.respects_max_stock(&schedule, moved_earlier as usize))
+                    && problem.respects_capacity(&schedule)
+                    && problem.respects_sequencing(&schedule)
+                    && !problem.forbidden.contains(&schedule) {
+                    cost += delta;
+                    improved = true;
+                } else {
+                    schedule.swap(t1, t2);
+                }
+            }
+        }
+    }
+
+    (-cost, problem.solution(&schedule))
+}
+
+/// Returns the cost variation of swapping the decisions at periods t1 < t2, assuming
+/// the resulting schedule is feasible. Only the changeovers between t1 and t2 and their
+/// surrounding productions are evaluated, and with second-order changeovers the items
+/// switched from and to around them. When demands can be served late, the stocking
+/// costs of the two items are not linear in their production periods and are recomputed.
+pub fn swap_delta(problem: &Psp, schedule: &mut [isize], t1: usize, t2: usize) -> isize {
+    let mut from = (0..t1).rev().find(|t| schedule[*t] != IDLE).unwrap_or(t1);
+    let mut to = ((t2 + 1)..problem.horizon).find(|t| schedule[*t] != IDLE).unwrap_or(t2);
+    if !problem.second_order.is_empty() {
+        let (first, last) = (schedule[from], schedule[to]);
+        let other = |t: &usize, item: isize| schedule[*t] != IDLE && schedule[*t] != item;
+        from = (0..from).rev().find(|t| other(t, first)).unwrap_or(from);
+        to = ((to + 1)..problem.horizon).find(|t| other(t, last)).unwrap_or(to);
+    }
+
+    let items = [schedule[t1], schedule[t2]];
+    let stocking_cost = |schedule: &[isize]| items.iter()
+        .filter(|v| **v != IDLE)
+        .map(|v| problem.stocking_cost(schedule, *v as usize))
+        .sum::<isize>();
+
+    let before = problem.changeover_cost(&schedule[from..=to]);
+    let stocking_before = if problem.windows { stocking_cost(schedule) } else { 0 };
+    schedule.swap(t1, t2);
+    let after = problem.changeover_cost(&schedule[from..=to]);
+    let stocking_after = if problem.windows { stocking_cost(schedule) } else { 0 };
+    schedule.swap(t1, t2);
+
+    if problem.windows {
+        return after - before + stocking_after - stocking_before;
+    }
+
+    let shift = (t2 - t1) as isize;
+    let mut delta = after - before;
+    if schedule[t1] != IDLE {
+        delta -= problem.stocking[schedule[t1] as usize] as isize * shift;
+    }
+    if schedule[t2] != IDLE {
+        delta += problem.stocking[schedule[t2] as usize] as isize * shift;
+    }
+    delta
+}
@@ -0,0 +1,173 @@
+//! This module solves the PSP with a time-indexed MIP formulation (or its LP relaxation),
+//! so that the results of the decision diagram approach can be compared with those of 
+//! a MIP solver.
+//!
+//! For each item i and period t, the formulation uses a production variable x[i][t],
+//! a setup variable y[i][t] telling whether the machine is configured for i, a stock
+//! variable s[i][t] and, for each pair of items, a changeover variable c[i][j][t]. The
+//! configuration can only change in a period where the new item is produced, which
+//! matches the convention of the DP model where the configuration is kept when idle.
+//! When it is lost instead, only the productions of consecutive periods incur changeovers.
+//! Nothing is produced in the periods where the machine is down, and the idle cost is
+//! charged at each available period that produces nothing. The second-order changeovers
+//! are ignored, so the LP relaxation remains a lower bound.
+
+#[cfg(feature = "mip")]
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "mip")]
+use ddo::Solution;
+#[cfg(feature = "mip")]
+use good_lp::ResolutionError;
+use good_lp::{variables, variable, constraint, Expression, Variable, SolverModel, solvers::highs::{highs, HighsProblem}};
+use good_lp::Solution as _;
+#[cfg(feature = "mip")]
+use tracing::warn;
+
+use crate::resolution::model::{Psp, IDLE};
+
+/// Solves the problem with HiGHS within the given time budget. Returns true iff the
+/// solution found is proved optimal or the problem is proved infeasible, with the value
+/// and the decisions of the best solution found, if any
+#[cfg(feature = "mip")]
+pub fn solve_mip(problem: &Psp, budget: Duration) -> (bool, Option<(isize, Solution)>) {
+    let start = Instant::now();
+    let (model, x, _) = formulation(problem, true);
+    let model = model
+        .set_option("time_limit", budget.saturating_sub(start.elapsed()).as_secs_f64())
+        .set_option("mip_rel_gap", 0.0);
+    let solution = match model.solve() {
+        Ok(solution) => solution,
+        Err(ResolutionError::Infeasible) => return (true, None),
+        Err(e) => {
+            warn!("the MIP solver failed: {e}");
+            return (false, None);
+        },
+    };
+    // HiGHS returns the best solution found when the time limit is reached
+    let is_exact = start.elapsed() < budget;
+
+    let mut schedule = vec![IDLE; problem.horizon];
+    for (i, xi) in x.iter().enumerate() {
+        for (t, xit) in xi.iter().enumerate() {
+            if solution.value(*xit) > 0.5 {
+                schedule[t] = i as isize;
+            }
+        }
+    }
+
+    match problem.cost(&schedule) {
+        Some(cost) => (is_exact, Some((-cost, problem.solution(&schedule)))),
+        None => (false, None),
+    }
+}
+
+/// Solves the LP relaxation of the formulation and returns the corresponding lower
+/// bound on the cost, or None if the relaxation is infeasible
+pub fn lp_bound(problem: &Psp) -> Option<isize> {
+    let (model, _, objective) = formulation(problem, false);
+    let solution = model.solve().ok()?;
+    Some((solution.eval(&objective) - 1e-6).ceil() as isize)
+}
+
+/// Builds the formulation with binary or continuous production and setup variables, 
+/// and returns it with the production variables and the objective
+fn formulation(problem: &Psp, integer: bool) -> (HighsProblem, Vec<Vec<Variable>>, Expression) {
+    let (n, h) = (problem.n_items, problem.horizon);
+    let mut vars = variables!();
+    let binary = || if integer { variable().binary() } else { variable().min(0).max(1) };
+
+    let x: Vec<Vec<Variable>> = (0..n).map(|_| (0..h).map(|_| vars.add(binary())).collect()).collect();
+    let y: Vec<Vec<Variable>> = (0..n).map(|_| (0..h).map(|_| vars.add(binary())).collect()).collect();
+    let s: Vec<Vec<Variable>> = (0..n).map(|_| (0..h).map(|_| vars.add(variable().min(0))).collect()).collect();
+    let c: Vec<Vec<Vec<Variable>>> = (0..n).map(|_| (0..n).map(|_| 
+        (0..h).map(|_| vars.add(variable().min(0).max(1))).collect()).collect()).collect();
+
+    let mut objective = Expression::from(0.0);
+    for i in 0..n {
+        for t in 0..h {
+            objective += problem.stocking[i] as f64 * s[i][t];
+            for j in 0..n {
+                if i != j && t > 0 {
+                    objective += problem.changeover(i, j) as f64 * c[i][j][t];
+                }
+            }
+        }
+    }
+
+    if problem.idle > 0 {
+        let idle = problem.idle as f64;
+        for t in (0..h).filter(|t| !problem.is_down(*t)) {
+            objective += idle;
+            x.iter().for_each(|xi| objective -= idle * xi[t]);
+        }
+    }
+
+    let mut model = vars.minimise(objective.clone()).using(highs);
+
+    for t in 0..h {
+        let production = x.iter().map(|xi| xi[t]).sum::<Expression>();
+        let setup = y.iter().map(|yi| yi[t]).sum::<Expression>();
+        model.add_constraint(constraint!(production.clone() <= 1.0));
+        model.add_constraint(constraint!(setup == 1.0));
+        if problem.capacity != usize::MAX {
+            let stock = s.iter().map(|si| si[t]).sum::<Expression>();
+            model.add_constraint(constraint!(stock <= problem.capacity as f64));
+        }
+
+        if problem.is_down(t) {
+            x.iter().for_each(|xi| model.add_constraint(constraint!(xi[t] == 0.0)));
+        }
+
+        match problem.fixed[t] {
+            Some(IDLE) => { model.add_constraint(constraint!(production == 0.0)); },
+            Some(item) => { model.add_constraint(constraint!(x[item as usize][t] == 1.0)); },
+            None => {},
+        }
+
+        for i in 0..n {
+            let demand = problem.demand(i, t) as f64;
+            let prev_stock = if t == 0 { Expression::from(0.0) } else { Expression::from(s[i][t - 1]) };
+            model.add_constraint(constraint!(s[i][t] == prev_stock + x[i][t] - demand));
+            model.add_constraint(constraint!(x[i][t] <= y[i][t]));
+            if problem.max_stock[i] != usize::MAX {
+                model.add_constraint(constraint!(s[i][t] <= problem.max_stock[i] as f64));
+            }
+
+            if t > 0 {
+                // when the setup is lost, only consecutive productions incur a changeover
+                let configured = if problem.keeps_setup() { y[i][t - 1] } else { x[i][t - 1] };
+                for j in 0..n {
+                    if i != j && !problem.can_follow(i, j) {
+                        model.add_constraint(constraint!(configured + x[j][t] <= 1.0));
+                    }
+                    if i != j {
+                        model.add_constraint(constraint!(c[i][j][t] >= configured + y[j][t] - 1.0));
+                        model.add_constraint(constraint!(c[i][j][t] <= x[j][t]));
+                    }
+                }
+            }
+        }
+    }
+
+    for i in 0..n {
+        if h > 0 {
+            model.add_constraint(constraint!(s[i][h - 1] == 0.0));
+        }
+    }
+
+    for schedule in problem.forbidden.iter() {
+        let mut difference = Expression::from(0.0);
+        for (t, item) in schedule.iter().copied().enumerate() {
+            if item == IDLE {
+                difference += x.iter().map(|xi| xi[t]).sum::<Expression>();
+            } else {
+                difference += 1.0;
+                difference -= x[item as usize][t];
+            }
+        }
+        model.add_constraint(constraint!(difference >= 1.0));
+    }
+
+    (model, x, objective)
+}
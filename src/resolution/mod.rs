@@ -1,5 +1,60 @@
 mod solve;
-mod model;
+mod bound;
+mod analyze;
+mod compare;
+mod validate;
+mod tune;
+mod bench;
+mod selftest;
+mod admissibility;
+mod whatif;
+mod simulate;
+mod split;
+#[cfg(feature = "serve")]
+mod serve;
+pub(crate) mod model;
+mod prev_demands;
 mod ub_utils;
+mod preprocess;
+mod heuristic;
+mod local_search;
+mod annealing;
+pub(crate) mod exhaustive;
+pub(crate) mod dp;
+mod lagrangian;
+mod relaxed;
+mod expansion;
+mod cache;
+mod arena;
+mod hashing;
+mod viz;
+mod gantt;
+mod report;
+mod monitor;
+mod dominance;
+mod clustering;
+mod compression;
+#[cfg(feature = "lp")]
+mod mip;
+#[cfg(feature = "sqlite")]
+mod database;
+#[cfg(feature = "xlsx")]
+mod xlsx;
 
-pub use solve::*;
\ No newline at end of file
+pub use solve::*;
+pub use bound::*;
+pub use analyze::*;
+pub use compare::*;
+pub use validate::*;
+pub use tune::*;
+pub use bench::*;
+pub use selftest::*;
+pub use admissibility::*;
+pub use whatif::*;
+pub use simulate::*;
+pub use split::*;
+#[cfg(feature = "serve")]
+pub use serve::*;
+// the compilation of the restricted DDs, which the benchmarks measure
+pub use model::{Psp, PspRanking};
+pub use heuristic::{restricted_dd, Restriction};
\ No newline at end of file
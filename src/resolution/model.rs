@@ -21,12 +21,23 @@
 //! using ddo. It is a fairly simple example but it features most of the aspects you will
 //! want to copy when implementing your own solver.
 
-use std::{vec, collections::BinaryHeap};
+use std::{vec, collections::BinaryHeap, fmt};
 
 use ddo::*;
 use smallbitset::Set32;
 
+use crate::error::{PspError, read_json};
+use crate::instance::{DemandRef, IdleSetup, Precedence, PspInstance, SparseDemand};
+use crate::resolution::compression::CompressedSolutionBound;
+use crate::resolution::lagrangian::LagrangianBound;
 use crate::resolution::ub_utils::all_mst;
+use crate::resolution::cache::BoundCache;
+use crate::resolution::prev_demands::PrevDemands;
+use crate::constraints::SideConstraints;
+use crate::solution::Assignment;
+
+/// The maximum number of forbidden solutions, which the states track in a set of 64 bits
+pub const MAX_FORBIDDEN: usize = u64::BITS as usize;
 
 /// The state of the DP model
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -35,56 +46,960 @@ pub struct PspState {
     /// The item that was produced at time t+1 
     /// (a value of -1 means that we don't know the item that is being produced next)
     pub next: isize,
-    /// The time at which the previous demand for each item had been filled
-    pub prev_demands: Vec<isize>,
+    /// The item that the machine switched to after the productions of the next item, which
+    /// is only tracked when the changeovers depend on the last two items
+    /// (a value of -1 means that we don't know it)
+    pub after_next: isize,
+    /// The index of the last unit of each item that remains to be produced, its units
+    /// being numbered from 0 in chronological order (-1 if none remains)
+    pub prev_demands: PrevDemands,
+    /// The set of forbidden solutions whose decisions have all been taken so far
+    pub matching: u64,
+}
+
+impl PspState {
+    /// Returns the index of the last unit of the given item that remains to be produced
+    /// (-1 if none remains)
+    pub fn prev_demand(&self, item: usize) -> isize {
+        self.prev_demands[item] as isize
+    }
+
+    /// Returns the sum over the items of the distance between the remaining units of this
+    /// state and of the given one
+    pub fn demand_distance(&self, other: &PspState) -> usize {
+        self.prev_demands.iter().zip(other.prev_demands.iter())
+            .map(|(a, b)| a.abs_diff(b) as usize)
+            .sum()
+    }
+}
+
+/// The compact type used to store time periods and units in the states, so that they are
+/// cheap to clone and hash (a value of -1 means there is no such period or unit)
+pub type Period = i16;
+
+/// The decisions allowed by the stock limits at some period
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StockLimit {
+    /// Any decision
+    Free,
+    /// Any item in stock, but the machine cannot stay idle
+    Production,
+    /// Only the given item
+    Item(usize),
+    /// Only the given item, in overtime
+    Overtime(usize),
+    /// Any item with two units in stock, in overtime
+    OvertimeProduction,
+}
+
+/// A demand of an item
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Demand {
+    /// The latest service period
+    pub period: usize,
+    pub quantity: usize,
+    /// The earliest service period (the latest one when it cannot be served late)
+    pub earliest: usize,
+    /// The cumulated quantity of the demands of the item up to this one, included
+    pub cumulated: usize,
 }
 
 /// A constant to tell your machine wont do anything
 pub const IDLE: isize = -1;
 
+/// The first reason why a schedule is infeasible
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Infeasibility {
+    /// The schedule does not have a decision per period, but this number of decisions
+    Length(usize),
+    /// The decision at the given period is neither an item nor idle
+    UnknownItem { period: usize, value: isize },
+    /// An item directly follows an item it may not follow at the given period
+    ForbiddenTransition { period: usize, from: usize, to: usize },
+    /// A demand is not produced before the one that must follow it
+    Precedence(Precedence),
+    /// The item is produced at the given period, when the machine is down
+    Downtime { period: usize, item: usize },
+    /// A side constraint bans the production of the item at the given period
+    BannedProduction { period: usize, item: usize },
+    /// A side constraint bans the transition from the item produced at the given period
+    BannedTransition { period: usize, from: usize, to: usize },
+    /// The total stock exceeds the storage capacity
+    Capacity,
+    /// The demands of the item are not all served on time, or it is produced in excess
+    /// or beyond its maximum stock
+    Item(usize),
+}
+
+impl fmt::Display for Infeasibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Infeasibility::Length(length) => write!(f, "the schedule has {length} periods"),
+            Infeasibility::UnknownItem { period, value } => write!(f, "{value} is produced at period {period}, which is not an item"),
+            Infeasibility::ForbiddenTransition { period, from, to } => write!(f, "item {to} directly follows item {from} at period {period}, which is forbidden"),
+            Infeasibility::Precedence(p) => write!(f, "the demand of item {} at period {} is not produced before the demand of item {} at period {}",
+                p.before.item, p.before.period, p.after.item, p.after.period),
+            Infeasibility::Downtime { period, item } => write!(f, "item {item} is produced at period {period}, when the machine is down"),
+            Infeasibility::BannedProduction { period, item } => write!(f, "item {item} is produced at period {period}, which a side constraint bans"),
+            Infeasibility::BannedTransition { period, from, to } => write!(f, "item {to} directly follows item {from} produced at period {period}, which a side constraint bans"),
+            Infeasibility::Capacity => write!(f, "the storage capacity is exceeded"),
+            Infeasibility::Item(item) => write!(f, "item {item} does not serve its demands on time, or is produced in excess or beyond its maximum stock"),
+        }
+    }
+}
+
 /// This structure describes a PSP instance
 #[derive(Debug, Clone)]
 pub struct Psp {
     pub n_items: usize,
     pub horizon: usize,
+    /// The costs of the instance are multiplied by 10^precision to make them integral
+    pub precision: u32,
     pub stocking: Vec<usize>,
-    pub changeover: Vec<Vec<usize>>,
-    pub demands: Vec<Vec<usize>>,
-    pub prev_demands: Vec<Vec<isize>>,
-    pub rem_demands: Vec<Vec<isize>>,
+    /// The changeover costs between each pair of items, in row-major order
+    pub changeover: Vec<usize>,
+    /// For each triple of items (a, b, c), the additional cost of the changeover from b to
+    /// c when b was switched to from a, in row-major order (empty if there is none)
+    pub second_order: Vec<usize>,
+    /// The demands of each item, in increasing order of period. The service windows of
+    /// an item are in the same order, so the tables of the model are searched in them
+    pub demands: Vec<Vec<Demand>>,
+    /// Whether some demand can be served after its earliest service period
+    pub windows: bool,
+    /// The maximum number of units of each item in stock at the end of a period
+    /// (usize::MAX if unbounded)
+    pub max_stock: Vec<usize>,
+    /// The maximum number of units of all items in stock at the end of a period
+    /// (usize::MAX if unbounded)
+    pub capacity: usize,
+    /// Whether the setup of the machine is kept through idle periods
+    pub idle_setup: IdleSetup,
+    /// The number of periods up to each period, included, where the machine is available:
+    /// nothing is produced at the periods where it is down
+    pub available: Vec<usize>,
+    /// The surcharge of producing a second unit of the item at a period, in overtime, if
+    /// allowed. Such a production is decided by the value of the item plus the number of
+    /// items, in the decisions and the schedules
+    pub overtime: Option<usize>,
+    /// The cost of each period where the machine is available but idle (0 if idling is not
+    /// penalized). Since every schedule produces the same units, it only changes the best
+    /// schedules when overtime is allowed, each unit produced in overtime idling one more period
+    pub idle: usize,
+    /// For each pair of items (a, b), whether b may never directly follow a, in row-major order
+    pub incompatible: Vec<bool>,
+    /// The pairs of demands that must be produced in a given order
+    pub precedences: Vec<Precedence>,
+    /// The schedules that must not be produced again (at most MAX_FORBIDDEN), indexed by time
+    pub forbidden: Vec<Vec<isize>>,
+    /// The decision that must be taken at each time period, if any
+    pub fixed: Vec<Option<isize>>,
+    /// For each period and item, in period-major order, whether the item may not be
+    /// produced at the period by a side constraint (empty if there is none)
+    pub banned_productions: Vec<bool>,
+    /// The (period, from, to) transitions banned by side constraints, in increasing order:
+    /// the production of from at the period may not be directly followed by to
+    pub banned_transitions: Vec<(usize, usize, usize)>,
+    /// For each item, the items with a smaller index that are identical to it (same costs
+    /// and demands). These symmetries must be cleared as soon as decisions are fixed or
+    /// solutions are forbidden, since those may break them
+    pub twins: Vec<Vec<usize>>,
+    /// A stocking cost incurred by every schedule but charged by no transition, as when
+    /// the demands of merged items are moved to earlier periods
+    pub offset: usize,
+    /// The fingerprint of the instance from which the model is built
+    pub fingerprint: String,
+}
+
+impl TryFrom<PspInstance> for Psp {
+    type Error = PspError;
+
+    /// Builds the model of the given instance. This fails if its tables do not have the
+    /// announced sizes, if its horizon does not fit in the states, if the cost of some
+    /// schedule could overflow, or if its demands cannot all be produced on time
+    fn try_from(instance: PspInstance) -> Result<Self, Self::Error> {
+        let (n, h) = (instance.nb_types, instance.nb_periods);
+        if n == 0 || h == 0 {
+            return Err(PspError::InvalidInstance("there must be at least one item and one period".to_string()));
+        }
+        if instance.stocking.len() != n || instance.changeover.len() != n || instance.changeover.iter().any(|r| r.len() != n) {
+            return Err(PspError::InvalidInstance(format!("the cost tables must have {n} items")));
+        }
+        if instance.second_order_changeover.as_ref().map_or(false, |costs| costs.len() != n
+            || costs.iter().any(|rows| rows.len() != n || rows.iter().any(|r| r.len() != n))) {
+            return Err(PspError::InvalidInstance(format!("the second-order changeover costs must be given for {n} items")));
+        }
+        match instance.sparse_demands.as_ref() {
+            Some(_) if !instance.demands.is_empty() || instance.earliest.is_some() => {
+                return Err(PspError::InvalidInstance("sparse demands exclude the demand table and the earliest service periods".to_string()));
+            },
+            Some(sparse) if sparse.len() != n => {
+                return Err(PspError::InvalidInstance(format!("the sparse demands must be given for {n} items")));
+            },
+            Some(sparse) => {
+                if let Some(i) = (0..n).find(|i| sparse[*i].windows(2).any(|w| w[0].period >= w[1].period) || sparse[*i].iter().any(|d| d.period >= h)) {
+                    return Err(PspError::InvalidInstance(format!("the demands of item {i} must be at distinct increasing periods before {h}")));
+                }
+            },
+            None if instance.demands.len() != n || instance.demands.iter().any(|r| r.len() != h) => {
+                return Err(PspError::InvalidInstance(format!("the demand table must have {n} items and {h} periods")));
+            },
+            None => {},
+        }
+        if instance.item_names.as_ref().map_or(false, |names| names.len() != n) || instance.period_labels.as_ref().map_or(false, |labels| labels.len() != h) {
+            return Err(PspError::InvalidInstance(format!("there must be {n} item names and {h} period labels")));
+        }
+        if instance.availability.as_ref().map_or(false, |availability| availability.len() != h) {
+            return Err(PspError::InvalidInstance(format!("the availability of the machine must be given for {h} periods")));
+        }
+        if instance.max_stock.as_ref().map_or(false, |caps| caps.len() != n) {
+            return Err(PspError::InvalidInstance(format!("the maximum stocks must be given for {n} items")));
+        }
+        if instance.earliest.as_ref().map_or(false, |earliest| earliest.len() != n || earliest.iter().any(|r| r.len() != h)) {
+            return Err(PspError::InvalidInstance(format!("the earliest service periods must be given for {n} items and {h} periods")));
+        }
+        let lists = instance.demand_lists();
+        for (i, demands) in lists.iter().enumerate() {
+            let mut last = 0;
+            for d in demands.iter() {
+                let (t, earliest) = (d.period, d.earliest.unwrap_or(d.period));
+                if earliest > t || earliest < last {
+                    return Err(PspError::InvalidInstance(format!("the service window of item {i} at period {t} must start at most at {t} and not before the previous one")));
+                }
+                last = earliest;
+            }
+        }
+        if instance.forbidden_transitions.iter().flatten().any(|(a, b)| *a >= n || *b >= n) {
+            return Err(PspError::InvalidInstance(format!("the forbidden transitions must be between the {n} items")));
+        }
+        let is_demand = |d: &DemandRef| d.item < n && lists[d.item].iter().any(|l| l.period == d.period);
+        if let Some(p) = instance.precedences.iter().flatten().find(|p| !is_demand(&p.before) || !is_demand(&p.after)) {
+            return Err(PspError::InvalidInstance(format!("the precedence {p:?} is not between two demands")));
+        }
+        // the states store the index of a unit, and at most two units are produced per period
+        let max_horizon = Period::MAX as usize / if instance.overtime_cost.is_some() { 2 } else { 1 };
+        if h > max_horizon {
+            return Err(PspError::InvalidInstance(format!("the horizon cannot exceed {max_horizon} periods")));
+        }
+
+        let precision = instance.precision();
+        let scale = 10_usize.pow(precision);
+        if instance.costs().any(|c| !c.is_finite() || c < 0.0 || c * scale as f64 > isize::MAX as f64) {
+            return Err(PspError::InvalidInstance("the costs must be finite non-negative numbers".to_string()));
+        }
+        let scaled = |c: f64| (c * scale as f64).round() as usize;
+
+        let demands = Psp::compute_demands(&lists);
+        let fingerprint = instance.fingerprint();
+        let problem = Psp {
+            n_items: instance.nb_types,
+            horizon: instance.nb_periods,
+            precision,
+            stocking: instance.stocking.iter().copied().map(scaled).collect(),
+            changeover: instance.changeover.iter().flatten().copied().map(scaled).collect(),
+            // the states only track the item after the next one when some of these costs is positive
+            second_order: {
+                let costs = instance.second_order_changeover.iter().flatten().flatten().flatten().copied().map(scaled).collect::<Vec<usize>>();
+                if costs.iter().all(|c| *c == 0) { vec![] } else { costs }
+            },
+            windows: demands.iter().flatten().any(|d| d.earliest != d.period),
+            demands,
+            max_stock: instance.max_stock.unwrap_or_else(|| vec![usize::MAX; n]),
+            capacity: instance.storage_capacity.unwrap_or(usize::MAX),
+            idle_setup: instance.idle_setup.unwrap_or_default(),
+            overtime: instance.overtime_cost.map(scaled),
+            idle: instance.idle_cost.map_or(0, scaled),
+            available: (0..h)
+                .scan(0, |count, t| {
+                    *count += usize::from(instance.availability.as_ref().map_or(true, |a| a[t]));
+                    Some(*count)
+                })
+                .collect(),
+            incompatible: {
+                let mut incompatible = vec![false; n * n];
+                instance.forbidden_transitions.iter().flatten().for_each(|(a, b)| incompatible[a * n + b] = true);
+                incompatible
+            },
+            precedences: instance.precedences.clone().unwrap_or_default(),
+            forbidden: vec![],
+            fixed: vec![None; instance.nb_periods],
+            banned_productions: vec![],
+            banned_transitions: vec![],
+            twins: vec![],
+            offset: 0,
+            fingerprint,
+        };
+
+        if problem.worst_case_cost().is_none() {
+            return Err(PspError::InvalidInstance("the costs of this instance are too large: a schedule cost could overflow".to_string()));
+        }
+
+        // a single item is produced per period (two in overtime), hence at most as many demands
+        // can be due by period t as there are units produced in the periods where the machine
+        // is available
+        let mut periods = problem.demands.iter().flatten().map(|d| (d.period, d.quantity)).collect::<Vec<(usize, usize)>>();
+        periods.sort_unstable();
+        let mut due = 0;
+        for (t, quantity) in periods {
+            due += quantity;
+            if due > problem.available[t] * problem.units_per_period() {
+                return Err(PspError::Infeasible(format!("{due} demands are due by period {t}, when the machine is available {} periods", problem.available[t])));
+            }
+        }
+
+        Ok(problem.with_symmetries())
+    }
 }
 
 impl Psp {
-    pub fn compute_prev_demands(demands: &Vec<Vec<usize>>) -> Vec<Vec<isize>> {
-        let nb_items = demands.len();
-        let nb_periods = demands[0].len();
-        let mut prev_demands = vec![ vec![-1; nb_periods + 1] ; nb_items];
-        for t in 1..=nb_periods {
-            for i in 0..nb_items {
-                if demands[i][t-1] > 0 {
-                    prev_demands[i][t] = (t-1) as isize;
-                } else {
-                    prev_demands[i][t] = prev_demands[i][t-1];
+    /// Reads the instance of the given file and builds its model
+    pub fn from_file(path: &str) -> Result<Self, PspError> {
+        let instance: PspInstance = read_json(path)?;
+        Psp::try_from(instance)
+    }
+
+    /// Returns the demands of each item of the given lists, whose quantities are positive
+    /// and whose periods are increasing
+    pub fn compute_demands(lists: &[Vec<SparseDemand>]) -> Vec<Vec<Demand>> {
+        lists.iter().map(|demands| {
+            let mut cumulated = 0;
+            demands.iter().map(|d| {
+                cumulated += d.quantity;
+                Demand { period: d.period, quantity: d.quantity, earliest: d.earliest.unwrap_or(d.period), cumulated }
+            }).collect()
+        }).collect()
+    }
+
+    /// Formats the given (scaled) cost in the units of the instance
+    pub fn format_cost(&self, cost: isize) -> String {
+        if self.precision == 0 {
+            cost.to_string()
+        } else {
+            let scale = 10_usize.pow(self.precision) as f64;
+            format!("{:.*}", self.precision as usize, cost as f64 / scale)
+        }
+    }
+
+    /// Returns the given (scaled) cost in the units of the instance
+    pub fn unscale(&self, cost: isize) -> f64 {
+        cost as f64 / 10_f64.powi(self.precision as i32)
+    }
+
+    /// Returns an upper bound on the cost of any schedule (and on any sum of costs computed
+    /// by the model and its bounds), or None if it does not fit in an isize. Once it is known to
+    /// fit, costs can be accumulated without overflow checks
+    pub fn worst_case_cost(&self) -> Option<isize> {
+        let horizon = isize::try_from(self.horizon).ok()?;
+        let n_items = isize::try_from(self.n_items).ok()?;
+        let max_stocking = isize::try_from(self.stocking.iter().copied().max().unwrap_or(0)).ok()?;
+        let max_changeover = isize::try_from(self.changeover.iter().copied().max().unwrap_or(0)).ok()?
+            .checked_add(isize::try_from(self.second_order.iter().copied().max().unwrap_or(0)).ok()?)?;
+        let overtime = isize::try_from(self.overtime.unwrap_or(0)).ok()?;
+        let idle = isize::try_from(self.idle).ok()?;
+        let units = self.units_per_period() as isize;
+
+        let stocking = max_stocking.checked_mul(horizon)?.checked_mul(horizon)?.checked_mul(units)?;
+        let changeover = max_changeover.checked_mul(horizon.checked_add(n_items)?)?;
+        let overtime = overtime.checked_mul(horizon)?;
+        let idle = idle.checked_mul(horizon)?;
+        stocking.checked_add(changeover)?.checked_add(overtime)?.checked_add(idle)?.checked_mul(2)
+    }
+
+    /// Returns the cost of producing item b right after item a
+    pub fn changeover(&self, a: usize, b: usize) -> usize {
+        self.changeover[a * self.n_items + b]
+    }
+
+    /// Returns the additional cost of the changeover from item b to item c when b was
+    /// switched to from item a
+    pub fn second_order(&self, a: usize, b: usize, c: usize) -> usize {
+        if self.second_order.is_empty() {
+            0
+        } else {
+            self.second_order[(a * self.n_items + b) * self.n_items + c]
+        }
+    }
+
+    /// Returns the cost of switching from item b to item c, b having been switched to
+    /// from item a if known
+    pub fn changeover_after(&self, a: isize, b: usize, c: usize) -> usize {
+        let second_order = if a == IDLE || b == c { 0 } else { self.second_order(a as usize, b, c) };
+        self.changeover(b, c) + second_order
+    }
+
+    /// Returns true iff item b may directly follow item a
+    pub fn can_follow(&self, a: usize, b: usize) -> bool {
+        !self.incompatible[a * self.n_items + b]
+    }
+
+    /// Returns the demands of the given item, in increasing order of period
+    pub fn demands_of(&self, item: usize) -> &[Demand] {
+        &self.demands[item]
+    }
+
+    /// Returns the demand of the given item at period t
+    pub fn demand(&self, item: usize, t: usize) -> usize {
+        let demands = &self.demands[item];
+        demands.binary_search_by_key(&t, |d| d.period).map_or(0, |k| demands[k].quantity)
+    }
+
+    /// Returns the total number of units of the given item that are demanded
+    pub fn units_demanded(&self, item: usize) -> usize {
+        self.demands[item].last().map_or(0, |d| d.cumulated)
+    }
+
+    /// Returns the demand served by the given unit of the item, its units being numbered
+    /// from 0 in chronological order
+    pub fn demand_of_unit(&self, item: usize, unit: usize) -> &Demand {
+        let demands = &self.demands[item];
+        &demands[demands.partition_point(|d| d.cumulated <= unit)]
+    }
+
+    /// Returns the latest service period of the given unit of the item (-1 if the index
+    /// of the unit is negative)
+    pub fn deadline(&self, item: usize, unit: isize) -> isize {
+        if unit < 0 { -1 } else { self.demand_of_unit(item, unit as usize).period as isize }
+    }
+
+    /// Returns the cumulated demand of the given item up to period t
+    pub fn rem_demand(&self, item: usize, t: usize) -> isize {
+        let demands = &self.demands[item];
+        match demands.partition_point(|d| d.period <= t) {
+            0 => 0,
+            k => demands[k - 1].cumulated as isize,
+        }
+    }
+
+    /// Returns the cumulated demand of the given item whose earliest service period is
+    /// up to period t
+    pub fn served_demand(&self, item: usize, t: usize) -> isize {
+        let demands = &self.demands[item];
+        match demands.partition_point(|d| d.earliest <= t) {
+            0 => 0,
+            k => demands[k - 1].cumulated as isize,
+        }
+    }
+
+    /// Returns the stock of the given item at the end of each period of the schedule.
+    /// A unit leaves the stock at the earliest service period of the demand it serves,
+    /// the demands being served in the order of their windows
+    pub fn stocks<'a>(&'a self, schedule: &'a [isize], item: usize) -> impl Iterator<Item = isize> + 'a {
+        let mut produced = 0;
+        schedule.iter().enumerate().map(move |(t, v)| {
+            if self.item_of(*v) == item as isize {
+                produced += self.units_of(*v) as isize;
+            }
+            (produced - self.served_demand(item, t)).max(0)
+        })
+    }
+
+    /// Returns the maximum number of units produced at a period
+    pub fn units_per_period(&self) -> usize {
+        if self.overtime.is_some() { 2 } else { 1 }
+    }
+
+    /// Returns the largest value of a decision: the last item, in overtime if allowed
+    pub fn max_value(&self) -> isize {
+        (self.n_items * self.units_per_period()) as isize - 1
+    }
+
+    /// Returns the item produced by the given decision value, or IDLE
+    pub fn item_of(&self, value: isize) -> isize {
+        if value >= self.n_items as isize { value - self.n_items as isize } else { value }
+    }
+
+    /// Returns the number of units produced by the given decision value
+    pub fn units_of(&self, value: isize) -> usize {
+        match value {
+            IDLE => 0,
+            v if v >= self.n_items as isize => 2,
+            _ => 1,
+        }
+    }
+
+    /// Returns the periods at which the units of the given item are produced in the
+    /// schedule, in chronological order, a period being repeated for each unit
+    pub fn productions<'a>(&'a self, schedule: &'a [isize], item: usize) -> impl Iterator<Item = usize> + 'a {
+        schedule.iter().enumerate()
+            .filter(move |(_, v)| self.item_of(**v) == item as isize)
+            .flat_map(move |(t, v)| (0..self.units_of(*v)).map(move |_| t))
+    }
+
+    /// Returns the total overtime surcharge of the given schedule
+    pub fn overtime_cost(&self, schedule: &[isize]) -> isize {
+        let overtime = self.overtime.unwrap_or(0) as isize;
+        overtime * schedule.iter().filter(|v| self.units_of(**v) == 2).count() as isize
+    }
+
+    /// Returns the total idle cost of the given schedule, charged at the periods where the
+    /// machine is available
+    pub fn idle_cost(&self, schedule: &[isize]) -> isize {
+        let idle = schedule.iter().enumerate().filter(|(t, v)| **v == IDLE && !self.is_down(*t)).count();
+        self.idle as isize * idle as isize
+    }
+
+    /// Detects the items that are identical: same stocking cost, same changeover costs
+    /// to and from every other item, of both orders, and demands at the same periods
+    pub fn with_symmetries(mut self) -> Self {
+        let n = self.n_items;
+        let identical = |a: usize, b: usize| {
+            self.stocking[a] == self.stocking[b]
+                && self.max_stock[a] == self.max_stock[b]
+                && self.demands_of(a) == self.demands_of(b)
+                && (0..n).all(|i| self.changeover(a, i) == self.changeover(b, i) && self.changeover(i, a) == self.changeover(i, b))
+                && (0..n).all(|i| self.can_follow(a, i) == self.can_follow(b, i) && self.can_follow(i, a) == self.can_follow(i, b))
+                && (self.second_order.is_empty() || (0..n).all(|i| (0..n).all(|j| self.second_order(a, i, j) == self.second_order(b, i, j)
+                    && self.second_order(i, a, j) == self.second_order(i, b, j) && self.second_order(i, j, a) == self.second_order(i, j, b))))
+                && !self.precedences.iter().any(|p| [p.before.item, p.after.item].iter().any(|i| *i == a || *i == b))
+        };
+        let twins = (0..n)
+            .map(|b| (0..b).filter(|a| identical(*a, b)).collect())
+            .collect();
+        self.twins = twins;
+        self
+    }
+
+    /// Fixes the given decisions of the schedule, and forgets the symmetries that no longer
+    /// hold. The decisions fixed at the same period must agree
+    pub fn fix(&mut self, assignments: &[Assignment]) -> Result<(), PspError> {
+        for a in assignments.iter() {
+            if a.period >= self.horizon || a.item < IDLE || a.item > self.max_value() {
+                return Err(PspError::Invalid(format!("cannot fix item {} at period {}: there are {} items and {} periods",
+                    a.item, a.period, self.n_items, self.horizon)));
+            }
+            match self.fixed[a.period] {
+                Some(item) if item != a.item => return Err(PspError::Invalid(format!("period {} is fixed to both items {item} and {}", a.period, a.item))),
+                _ => self.fixed[a.period] = Some(a.item),
+            }
+        }
+        if !assignments.is_empty() {
+            self.clear_symmetries();
+        }
+        Ok(())
+    }
+
+    /// Bans the productions and the transitions of the given side constraints, and forgets
+    /// the symmetries that no longer hold
+    pub fn constrain(&mut self, constraints: &SideConstraints) -> Result<(), PspError> {
+        let check = |item: usize, period: usize| if item >= self.n_items || period >= self.horizon {
+            Err(PspError::Invalid(format!("cannot constrain item {item} at period {period}: there are {} items and {} periods", self.n_items, self.horizon)))
+        } else {
+            Ok(())
+        };
+        for p in constraints.productions.iter() {
+            check(p.item, p.period)?;
+        }
+        for t in constraints.transitions.iter() {
+            check(t.from, t.period)?;
+            check(t.to, t.period)?;
+        }
+
+        if !constraints.productions.is_empty() {
+            self.banned_productions.resize(self.horizon * self.n_items, false);
+            for p in constraints.productions.iter() {
+                self.banned_productions[p.period * self.n_items + p.item] = true;
+            }
+        }
+        self.banned_transitions.extend(constraints.transitions.iter().map(|t| (t.period, t.from, t.to)));
+        self.banned_transitions.sort_unstable();
+        self.banned_transitions.dedup();
+        if self.has_side_constraints() {
+            self.clear_symmetries();
+        }
+        Ok(())
+    }
+
+    /// Returns true iff some production or transition is banned by a side constraint
+    pub fn has_side_constraints(&self) -> bool {
+        !self.banned_productions.is_empty() || !self.banned_transitions.is_empty()
+    }
+
+    /// Returns true iff a side constraint bans the production of the item at the period,
+    /// or its direct transition to the given next item
+    fn is_banned(&self, period: usize, item: usize, next: isize) -> bool {
+        self.banned_productions.get(period * self.n_items + item).copied().unwrap_or(false)
+            || (next != IDLE && self.banned_transitions.binary_search(&(period, item, next as usize)).is_ok())
+    }
+
+    /// Returns the first side constraint that the schedule violates, if any
+    pub fn violated_side_constraint(&self, schedule: &[isize]) -> Option<Infeasibility> {
+        if !self.has_side_constraints() {
+            return None;
+        }
+        let mut prev: Option<(usize, usize)> = None;
+        for (t, item) in schedule.iter().map(|v| self.item_of(*v)).enumerate() {
+            if item == IDLE {
+                if !self.keeps_setup() {
+                    prev = None;
                 }
+                continue;
+            }
+            let item = item as usize;
+            if self.banned_productions.get(t * self.n_items + item).copied().unwrap_or(false) {
+                return Some(Infeasibility::BannedProduction { period: t, item });
             }
+            if let Some((period, from)) = prev.filter(|(period, from)| self.banned_transitions.binary_search(&(*period, *from, item)).is_ok()) {
+                return Some(Infeasibility::BannedTransition { period, from, to: item });
+            }
+            prev = Some((t, item));
         }
-        prev_demands
+        None
     }
 
-    pub fn compute_rem_demands(demands: &Vec<Vec<usize>>) -> Vec<Vec<isize>> {
-        let nb_items = demands.len();
-        let nb_periods = demands[0].len();
-        let mut rem_demands = vec![ vec![0; nb_periods] ; nb_items];
-        for t in 0..nb_periods {
-            for i in 0..nb_items {
-                if t == 0 {
-                    rem_demands[i][t] = demands[i][t] as isize;
-                } else {
-                    rem_demands[i][t] = rem_demands[i][t-1] + demands[i][t] as isize;
+    /// Forgets the symmetries between items
+    pub fn clear_symmetries(&mut self) {
+        self.twins.iter_mut().for_each(|t| t.clear());
+    }
+
+    /// Returns the item produced at each time period in the given solution
+    pub fn schedule(&self, solution: &Solution) -> Vec<isize> {
+        let mut schedule = vec![IDLE; self.horizon];
+        for d in solution.iter() {
+            schedule[d.variable.id()] = d.value;
+        }
+        schedule
+    }
+
+    /// Returns the decisions corresponding to the given schedule, in the order
+    /// in which the variables are branched on
+    pub fn solution(&self, schedule: &[isize]) -> Solution {
+        schedule.iter().copied().enumerate().rev()
+            .map(|(t, value)| Decision { variable: Variable(t), value })
+            .collect()
+    }
+
+    /// Returns the indices of the first and of the last units of the given demand
+    pub fn units_of_demand(&self, demand: &DemandRef) -> (isize, isize) {
+        let last = self.rem_demand(demand.item, demand.period);
+        (last - self.demand(demand.item, demand.period) as isize, last - 1)
+    }
+
+    /// Returns the latest service period of each unit of the given item, in chronological
+    /// order, a period being repeated for each unit demanded at it
+    pub fn deadlines(&self, item: usize) -> impl Iterator<Item = usize> + '_ {
+        self.demands[item].iter().flat_map(|d| (0..d.quantity).map(move |_| d.period))
+    }
+
+    /// Returns true iff the productions of the given item in the schedule satisfy
+    /// each of its demands on time, without any extra production, and never stock
+    /// more units than allowed
+    pub fn is_item_feasible(&self, schedule: &[isize], item: usize) -> bool {
+        if !self.respects_max_stock(schedule, item) {
+            return false;
+        }
+        let mut productions = self.productions(schedule, item);
+        for deadline in self.deadlines(item) {
+            match productions.next() {
+                Some(t) if t <= deadline => {},
+                _ => return false,
+            }
+        }
+        productions.next().is_none()
+    }
+
+    /// Returns true iff the stock of the given item never exceeds its maximum stock
+    /// at the end of a period of the schedule
+    pub fn respects_max_stock(&self, schedule: &[isize], item: usize) -> bool {
+        let cap = self.max_stock[item];
+        if cap == usize::MAX {
+            return true;
+        }
+        self.stocks(schedule, item).all(|stock| stock <= cap as isize)
+    }
+
+    /// Returns true iff the total stock never exceeds the storage capacity at the end
+    /// of a period of the schedule
+    pub fn respects_capacity(&self, schedule: &[isize]) -> bool {
+        if self.capacity == usize::MAX {
+            return true;
+        }
+        let mut total = vec![0_isize; self.horizon];
+        for i in 0..self.n_items {
+            total.iter_mut().zip(self.stocks(schedule, i)).for_each(|(t, s)| *t += s);
+        }
+        total.iter().all(|stock| *stock <= self.capacity as isize)
+    }
+
+    /// Returns true iff some pair of items cannot directly follow each other
+    pub fn has_forbidden_transitions(&self) -> bool {
+        self.incompatible.iter().any(|f| *f)
+    }
+
+    /// Returns true iff no changeover can be made cheaper by going through another item
+    pub fn satisfies_triangle_inequality(&self) -> bool {
+        let n = self.n_items;
+        (0..n).all(|i| (0..n).all(|j| (0..n).all(|k| 
+            self.changeover(i, j) <= self.changeover(i, k).saturating_add(self.changeover(k, j)))))
+    }
+
+    /// Returns true iff the machine is down at period t
+    pub fn is_down(&self, t: usize) -> bool {
+        self.available[t] == if t == 0 { 0 } else { self.available[t - 1] }
+    }
+
+    /// Returns true iff the machine is down at some period
+    pub fn has_downtime(&self) -> bool {
+        self.available.last().map_or(false, |a| *a < self.horizon)
+    }
+
+    /// Returns the first period of the schedule at which an item is produced while the
+    /// machine is down, with this item
+    pub fn downtime_production(&self, schedule: &[isize]) -> Option<(usize, usize)> {
+        schedule.iter().copied().enumerate()
+            .find(|(t, v)| *v != IDLE && self.is_down(*t))
+            .map(|(t, v)| (t, self.item_of(v) as usize))
+    }
+
+    /// Returns true iff the machine keeps its setup through idle periods
+    pub fn keeps_setup(&self) -> bool {
+        self.idle_setup == IdleSetup::Kept
+    }
+
+    /// Returns the total changeover cost of the productions in the given schedule
+    pub fn changeover_cost(&self, schedule: &[isize]) -> isize {
+        let mut cost = 0;
+        // the last item produced, and the one produced before switching to it
+        let (mut prev, mut before) = (IDLE, IDLE);
+        for item in schedule.iter().map(|v| self.item_of(*v)) {
+            if item == IDLE {
+                if !self.keeps_setup() {
+                    (prev, before) = (IDLE, IDLE);
+                }
+                continue;
+            }
+            if prev != IDLE {
+                cost += self.changeover_after(before, prev as usize, item as usize) as isize;
+            }
+            if prev != item {
+                before = prev;
+            }
+            prev = item;
+        }
+        cost
+    }
+
+    /// Returns the first period of the schedule at which an item directly follows an item
+    /// it may not follow, with these two items
+    pub fn forbidden_transition(&self, schedule: &[isize]) -> Option<(usize, usize, usize)> {
+        let mut prev = IDLE;
+        for (t, item) in schedule.iter().map(|v| self.item_of(*v)).enumerate() {
+            if item == IDLE {
+                if !self.keeps_setup() {
+                    prev = IDLE;
                 }
+                continue;
+            }
+            if prev != IDLE && !self.can_follow(prev as usize, item as usize) {
+                return Some((t, prev as usize, item as usize));
             }
+            prev = item;
         }
-        rem_demands
+        None
+    }
+
+    /// Returns the first precedence that the schedule violates. The units of an item are
+    /// delivered in the order they are produced, and a demand is produced once all its
+    /// units are
+    pub fn violated_precedence(&self, schedule: &[isize]) -> Option<&Precedence> {
+        // the period at which the k-th unit of the given item is produced
+        let production = |item: usize, k: isize| self.productions(schedule, item).nth(k as usize);
+        self.precedences.iter().find(|p| {
+            let last = production(p.before.item, self.units_of_demand(&p.before).1);
+            let first = production(p.after.item, self.units_of_demand(&p.after).0);
+            match (last, first) {
+                (Some(last), Some(first)) => last >= first,
+                _ => true,
+            }
+        })
+    }
+
+    /// Returns true iff the schedule produces nothing while the machine is down, makes no
+    /// forbidden transition, respects the precedences and violates no side constraint
+    pub fn respects_sequencing(&self, schedule: &[isize]) -> bool {
+        self.downtime_production(schedule).is_none() && self.forbidden_transition(schedule).is_none() && self.violated_precedence(schedule).is_none()
+            && self.violated_side_constraint(schedule).is_none()
+    }
+
+    /// Returns the number of units that remain to be produced in the given state
+    pub fn remaining_units(&self, state: &PspState) -> isize {
+        state.prev_demands.iter().map(|p| p as isize + 1).sum()
+    }
+
+    /// Returns the number of periods before the given time that are not needed to
+    /// produce the remaining units of the given state
+    pub fn idle_periods(&self, state: &PspState) -> isize {
+        let remaining = self.remaining_units(state);
+        let units = self.units_per_period() as isize;
+        state.time as isize - (remaining + units - 1) / units
+    }
+
+    /// Returns a lower bound on the idle costs of the periods before the given time: all
+    /// the available periods are idle but those producing the remaining units of the given
+    /// state, one at a time
+    pub fn idle_bound(&self, state: &PspState) -> isize {
+        if self.idle == 0 || state.time == 0 {
+            return 0;
+        }
+        let remaining = self.remaining_units(state);
+        let available = self.available[state.time - 1] as isize;
+        self.idle as isize * (available - remaining).max(0)
+    }
+
+    /// Returns the stocking cost of the given item in the schedule
+    pub fn stocking_cost(&self, schedule: &[isize], item: usize) -> isize {
+        self.stocking[item] as isize * self.stocks(schedule, item).sum::<isize>()
+    }
+
+    /// Returns the total cost of the given schedule, or None if it is infeasible
+    pub fn cost(&self, schedule: &[isize]) -> Option<isize> {
+        self.evaluate(schedule).ok()
+    }
+
+    /// Returns the total cost of the given schedule, or the first reason why it is
+    /// infeasible. This is the reference evaluation of the schedules, which does not
+    /// rely on the transitions of the model
+    pub fn evaluate(&self, schedule: &[isize]) -> Result<isize, Infeasibility> {
+        if schedule.len() != self.horizon {
+            return Err(Infeasibility::Length(schedule.len()));
+        }
+        if let Some((period, value)) = schedule.iter().copied().enumerate().find(|(_, v)| *v < IDLE || *v > self.max_value()) {
+            return Err(Infeasibility::UnknownItem { period, value });
+        }
+        if let Some((period, item)) = self.downtime_production(schedule) {
+            return Err(Infeasibility::Downtime { period, item });
+        }
+        if let Some((period, from, to)) = self.forbidden_transition(schedule) {
+            return Err(Infeasibility::ForbiddenTransition { period, from, to });
+        }
+        if let Some(precedence) = self.violated_precedence(schedule) {
+            return Err(Infeasibility::Precedence(*precedence));
+        }
+        if let Some(violation) = self.violated_side_constraint(schedule) {
+            return Err(violation);
+        }
+        if !self.respects_capacity(schedule) {
+            return Err(Infeasibility::Capacity);
+        }
+
+        let mut cost = self.offset as isize + self.changeover_cost(schedule) + self.overtime_cost(schedule)
+            + self.idle_cost(schedule);
+        for i in 0..self.n_items {
+            if !self.is_item_feasible(schedule, i) {
+                return Err(Infeasibility::Item(i));
+            }
+            cost += self.stocking_cost(schedule, i);
+        }
+        Ok(cost)
+    }
+
+    /// Returns the cost of taking the given decision (an item or IDLE) at the period
+    /// before the time of the given state, the schedule being built backwards, with the
+    /// state reached. Returns None if the model does not allow this decision
+    pub fn step(&self, state: &PspState, value: isize) -> Option<(isize, PspState)> {
+        let variable = Variable(state.time.checked_sub(1)?);
+        let mut allowed = false;
+        self.for_each_in_domain(variable, state, &mut |decision: Decision| allowed |= decision.value == value);
+        if !allowed {
+            return None;
+        }
+        let decision = Decision { variable, value };
+        Some((-self.transition_cost(state, decision), self.transition(state, decision)))
+    }
+}
+
+impl Psp {
+    /// Returns true iff an identical item with a smaller index has the same remaining
+    /// units: producing either of them leads to symmetric states
+    fn is_symmetric(&self, state: &PspState, item: usize) -> bool {
+        self.twins.get(item).map_or(false, |twins| 
+            twins.iter().any(|j| state.prev_demands[*j] == state.prev_demands[item]))
+    }
+
+    /// Returns the number of units of the given item that are in stock at the end of
+    /// period t-1 in the given state, when its productions after t-1 are known: its
+    /// remaining units are all produced by then
+    fn stock(&self, state: &PspState, item: usize, t: usize) -> isize {
+        (state.prev_demand(item) + 1 - self.served_demand(item, t - 1)).max(0)
+    }
+
+    /// Returns the decisions that the stock limits leave at time t > 0, or None if there
+    /// is none. Producing an item in stock at t removes one of its units from the stock at
+    /// the end of t-1: a unit of an item whose stock would exceed its cap must be produced
+    /// at t, and some item in stock must be produced if the total stock would exceed the
+    /// capacity. With overtime, two units produced at t absorb an excess of two units.
+    /// As the stocks only decrease with the remaining units, a merged state allows
+    /// all the decisions of the states it merges
+    fn stock_limits(&self, state: &PspState, t: usize) -> Option<StockLimit> {
+        let units = self.units_per_period() as isize;
+        let mut limit = StockLimit::Free;
+        let mut total = 0;
+        for i in 0..self.n_items {
+            let stock = self.stock(state, i, t);
+            total += stock;
+            if self.max_stock[i] == usize::MAX {
+                continue;
+            }
+            let excess = stock - self.max_stock[i] as isize;
+            if excess > units || (excess > 0 && limit != StockLimit::Free) {
+                return None;
+            } else if excess == 2 {
+                limit = StockLimit::Overtime(i);
+            } else if excess == 1 {
+                limit = StockLimit::Item(i);
+            }
+        }
+
+        if self.capacity != usize::MAX {
+            let excess = total - self.capacity as isize;
+            if excess > units {
+                return None;
+            } else if excess == 2 {
+                limit = match limit {
+                    StockLimit::Item(i) | StockLimit::Overtime(i) => StockLimit::Overtime(i),
+                    _ => StockLimit::OvertimeProduction,
+                };
+            } else if excess == 1 && limit == StockLimit::Free {
+                limit = StockLimit::Production;
+            }
+        }
+        Some(limit)
+    }
+
+    /// Returns true iff the given number of units of the item can be produced in the given
+    /// state: it must be allowed to directly precede the next item, and the demands that
+    /// must be produced after those the units serve must already be produced. The units of
+    /// a demand all precede those of the demands that follow it, which merged states (with
+    /// fewer remaining units) never forbid wrongly
+    fn respects_sequencing_in(&self, state: &PspState, item: usize, units: usize) -> bool {
+        if state.next != -1 && !self.can_follow(item, state.next as usize) {
+            return false;
+        }
+        let last = state.prev_demand(item);
+        let first = last - units as isize + 1;
+        self.precedences.iter()
+            .filter(|p| p.before.item == item && {
+                let (begin, end) = self.units_of_demand(&p.before);
+                begin <= last && first <= end
+            })
+            .all(|p| state.prev_demand(p.after.item) < self.units_of_demand(&p.after).0)
+    }
+
+    /// Returns true iff the given value can be taken at time t: it must agree with the
+    /// fixed decision if any, it must not be symmetric to another decision, it must
+    /// respect the sequencing and side constraints and it must not yield one of the
+    /// forbidden solutions
+    fn is_allowed(&self, state: &PspState, t: isize, value: isize) -> bool {
+        if self.fixed[t as usize].map_or(false, |v| v != value) {
+            return false;
+        }
+        let item = self.item_of(value);
+        if item != IDLE && (self.is_symmetric(state, item as usize) || !self.respects_sequencing_in(state, item as usize, self.units_of(value))) {
+            return false;
+        }
+        if item != IDLE && self.is_banned(t as usize, item as usize, state.next) {
+            return false;
+        }
+        t != 0 || !self.forbidden.iter().enumerate()
+            .any(|(i, schedule)| state.matching & (1 << i) != 0 && schedule[0] == value)
     }
 }
 
@@ -96,30 +1011,50 @@ impl Problem for Psp {
     }
 
     fn initial_state(&self) -> Self::State {
-        let mut prev_demands = vec![];
-        for i in 0..self.n_items {
-            prev_demands.push(self.prev_demands[i][self.horizon]);
-        }
+        let prev_demands = (0..self.n_items)
+            .map(|i| self.units_demanded(i) as Period - 1)
+            .collect();
+
+        let matching = match self.forbidden.len() {
+            0 => 0,
+            n => u64::MAX >> (MAX_FORBIDDEN - n),
+        };
 
         PspState {
             time: self.horizon, 
             next: -1,
-            prev_demands
+            after_next: -1,
+            prev_demands,
+            matching,
         }
     }
 
     fn initial_value(&self) -> isize {
-        0
+        -(self.offset as isize)
     }
 
     fn transition(&self, state: &Self::State, decision: ddo::Decision) -> Self::State {
         let mut ret = state.clone();
         ret.time -= 1;
 
+        let t = decision.variable.id();
+        for (i, schedule) in self.forbidden.iter().enumerate() {
+            if schedule[t] != decision.value {
+                ret.matching &= !(1 << i);
+            }
+        }
+
         if decision.value != IDLE {
-            let d        = decision.value as usize;
-            ret.next            = decision.value;
-            ret.prev_demands[d] = self.prev_demands[d][state.prev_demands[d] as usize];
+            let d        = self.item_of(decision.value) as usize;
+            if !self.second_order.is_empty() && state.next != d as isize {
+                ret.after_next  = state.next;
+            }
+            ret.next            = d as isize;
+            let prev = state.prev_demand(d) - self.units_of(decision.value) as isize;
+            ret.prev_demands.set(d, prev as Period);
+        } else if !self.keeps_setup() {
+            ret.next            = -1;
+            ret.after_next      = -1;
         }
 
         ret
@@ -127,20 +1062,35 @@ impl Problem for Psp {
 
     fn transition_cost(&self, state: &Self::State, decision: ddo::Decision) -> isize {
         if decision.value == IDLE {
-            0
+            if self.is_down(decision.variable.id()) { 0 } else { -(self.idle as isize) }
         } else {
-            let d = decision.value as usize;
+            let d = self.item_of(decision.value) as usize;
             let t = decision.variable.id() as isize;
-            let duration = state.prev_demands[d] - t;
-            let stocking = self.stocking[d] as isize * duration;
+            // the units are the last remaining ones of the item, the second one in overtime
+            let (mut stocking, mut overtime) = (0, 0);
+            for unit in 0..self.units_of(decision.value) {
+                if unit > 0 {
+                    overtime = self.overtime.unwrap_or(0) as isize;
+                }
+                let demand = self.demand_of_unit(d, (state.prev_demand(d) - unit as isize) as usize);
+                let duration = (demand.earliest as isize - t).max(0);
+                stocking += self.stocking[d] as isize * duration;
+            }
+            // switching to the next item from this one also sets the history of the
+            // changeover that follows it
             let changeover = 
                 if state.next != -1 {
-                    self.changeover[d][state.next as usize]
+                    let second_order = if state.next != d as isize && state.after_next != -1 {
+                        self.second_order(d, state.next as usize, state.after_next as usize)
+                    } else {
+                        0
+                    };
+                    self.changeover(d, state.next as usize) + second_order
                 } else {
                     0
                 };
             
-            -(changeover as isize + stocking)
+            -(changeover as isize + stocking + overtime)
         }
     }
 
@@ -155,39 +1105,269 @@ impl Problem for Psp {
 
     fn for_each_in_domain(&self, variable: ddo::Variable, state: &Self::State, f: &mut dyn ddo::DecisionCallback) {
         let t = variable.id() as isize;
-        let dom = (0..self.n_items).filter(|i| state.prev_demands[*i] >= t).collect::<Vec<usize>>();
-        let rem_demands = (0..self.n_items).filter(|i| state.prev_demands[*i] >= 0).map(|i| self.rem_demands[i][state.prev_demands[i] as usize]).sum::<isize>();
+        let dom = (0..self.n_items).filter(|i| self.deadline(*i, state.prev_demand(*i)) >= t).collect::<Vec<usize>>();
+        let rem_demands = self.remaining_units(state);
 
-        if rem_demands > t + 1 {
+        let units = self.units_per_period() as isize;
+        let available = self.available[t as usize] as isize;
+        if rem_demands > available * units {
             return;
         }
 
-        for i in dom.iter() {
-            f.apply(Decision {variable, value: *i as isize});
+        let limit = if t > 0 {
+            match self.stock_limits(state, t as usize) {
+                Some(limit) => limit,
+                None => return,
+            }
+        } else {
+            StockLimit::Free
+        };
+
+        // the machine stays idle while it is down
+        if self.is_down(t as usize) {
+            if limit == StockLimit::Free && self.is_allowed(state, t, IDLE) {
+                f.apply(Decision {variable, value: IDLE});
+            }
+            return;
         }
 
-        if rem_demands < t + 1 {
+        let allows = |i: usize, in_overtime: bool| match limit {
+            StockLimit::Free => true,
+            StockLimit::Production => self.stock(state, i, t as usize) > 0,
+            StockLimit::Item(item) => item == i,
+            StockLimit::Overtime(item) => item == i && in_overtime,
+            StockLimit::OvertimeProduction => in_overtime && self.stock(state, i, t as usize) > 1,
+        };
+        // the units that remain after the decision must fit in the periods before t
+        let before = if t == 0 { 0 } else { self.available[t as usize - 1] as isize };
+        let fits = |produced: isize| rem_demands - produced <= before * units;
+        for i in dom.iter().copied() {
+            if allows(i, false) && fits(1) && self.is_allowed(state, t, i as isize) {
+                f.apply(Decision {variable, value: i as isize});
+            }
+            // a second unit is produced in overtime when one more unit remains due at t or later
+            let overtime = (i + self.n_items) as isize;
+            if self.overtime.is_some() && allows(i, true) && fits(2) && self.deadline(i, state.prev_demand(i) - 1) >= t
+                && self.is_allowed(state, t, overtime) {
+                f.apply(Decision {variable, value: overtime});
+            }
+        }
+
+        if limit == StockLimit::Free && fits(0) && self.is_allowed(state, t, IDLE) {
             f.apply(Decision {variable, value: IDLE});
         }
     }
 }
 
+/// The way the states of a layer are merged by the relaxation. In both cases, each item
+/// keeps the fewest remaining units among the merged states
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum MergeStrategy {
+    /// The next item is forgotten
+    #[default]
+    Min,
+    /// The next item is kept when all the merged states share it, which yields a tighter
+    /// bound but fewer identical merged states
+    EqualNext,
+}
+
+/// The lower bound on the changeover costs used by the rough upper bound
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ChangeoverBound {
+    /// Spanning tree over the items that remain to be produced
+    Mst,
+    /// Cheapest incoming changeover of each item that remains to be produced
+    Assignment,
+    /// Best of both
+    #[default]
+    Max,
+    /// No bound on the changeover costs
+    None,
+}
+
+/// The lower bound on the stocking costs used by the rough upper bound
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum StockingBound {
+    /// Optimal stocking costs when the changeovers are ignored
+    #[default]
+    Greedy,
+    /// No bound on the stocking costs
+    None,
+}
+
 /// This structure implements the PSP relaxation
 pub struct PspRelax {
     pb: Psp,
+    merge: MergeStrategy,
+    changeover_bound: ChangeoverBound,
+    stocking_bound: StockingBound,
     mst: Vec<usize>,
+    lagrangian: Option<LagrangianBound>,
+    compressions: Vec<CompressedSolutionBound>,
+    cache: Option<BoundCache>,
 }
 
 impl PspRelax {
     pub fn new(pb: Psp) -> Self {
-        let mst = all_mst(&pb.changeover);
+        let mst = all_mst(pb.n_items, &pb.changeover);
+
+        Self { pb, mst, merge: MergeStrategy::Min, changeover_bound: ChangeoverBound::default(), stocking_bound: StockingBound::default(), lagrangian: None, compressions: vec![], cache: None }
+    }
+
+    /// Caches the rough upper bounds of at most about the given number of states
+    pub fn with_cache(mut self, capacity: usize) -> Self {
+        self.cache = Some(BoundCache::new(capacity));
+        self
+    }
 
-        Self { pb, mst }
+    /// Returns the number of rough upper bounds found in the cache, and of those that
+    /// were computed, if the bounds are cached
+    pub fn cache_stats(&self) -> Option<(usize, usize)> {
+        self.cache.as_ref().map(|c| c.stats())
+    }
+
+    /// Merges the states with the given strategy
+    pub fn with_merge(mut self, merge: MergeStrategy) -> Self {
+        self.merge = merge;
+        self
+    }
+
+    /// Strengthens the rough upper bound with the given Lagrangian stocking bound
+    pub fn with_lagrangian(mut self, lagrangian: LagrangianBound) -> Self {
+        self.lagrangian = Some(lagrangian);
+        self
+    }
+
+    /// Strengthens the rough upper bound with the bound of the given compressed problem.
+    /// With several compressions, the tightest of their bounds is used for each state
+    pub fn with_compression(mut self, compression: CompressedSolutionBound) -> Self {
+        self.compressions.push(compression);
+        self
+    }
+
+    /// Returns true iff the rough upper bound is strengthened with compressed problems
+    pub fn is_compressed(&self) -> bool {
+        !self.compressions.is_empty()
+    }
+
+    /// Returns the tightest upper bound of the compressed problems on the value of the best
+    /// completion of the given state, or None if one of them has no feasible completion
+    /// or there is no compression. The compressed problems ignore the idle cost, which is
+    /// bounded with idle_bound
+    pub fn compression_bound(&self, state: &PspState) -> Option<isize> {
+        self.compressions.iter()
+            .map(|c| c.get_ub(state))
+            .collect::<Option<Vec<isize>>>()
+            .and_then(|values| values.into_iter().min())
+            .map(|value| value - self.pb.idle_bound(state))
+    }
+
+    /// Writes the cached values of the compressed problems to disk
+    pub fn save_compressions(&self) -> Result<(), PspError> {
+        self.compressions.iter().try_for_each(|c| c.save())
+    }
+
+    /// Combines the changeover bounds with the given strategy
+    pub fn with_changeover_bound(mut self, changeover_bound: ChangeoverBound) -> Self {
+        self.changeover_bound = changeover_bound;
+        self
+    }
+
+    /// Returns a lower bound on the changeover costs of the remaining productions
+    fn changeover_bound(&self, state: &PspState) -> isize {
+        // when the setup is lost, a single idle period may spare all the changeovers
+        if !self.pb.keeps_setup() && self.pb.idle_periods(state) > 0 {
+            return 0;
+        }
+        let members = Self::members(state);
+        let mst = || self.mst[u32::from(members) as usize] as isize;
+        match self.changeover_bound {
+            ChangeoverBound::Mst => mst(),
+            ChangeoverBound::Assignment => self.assignment_bound(state, members),
+            ChangeoverBound::Max => mst().max(self.assignment_bound(state, members)),
+            ChangeoverBound::None => 0,
+        }
+    }
+
+    /// Combines the stocking bound with the given strategy
+    pub fn with_stocking_bound(mut self, stocking_bound: StockingBound) -> Self {
+        self.stocking_bound = stocking_bound;
+        self
+    }
+
+    /// Returns a lower bound on the stocking costs of the remaining demands, whatever the
+    /// sequence of items. Without the changeovers, the optimal schedule produces at each
+    /// period, from the latest one, the most expensive unit to stock among those whose
+    /// latest service period is not before it. When demands can be served late, the unit
+    /// is only stocked until its earliest service period, so the time it may spend in its
+    /// window is deducted.
+    /// With overtime, each unit is either produced at a regular period or in overtime:
+    /// the bound lets two units be produced at every period, and adds the surcharge of the
+    /// units that do not fit in the regular periods
+    fn greedy_stocking_bound(&self, state: &PspState) -> isize {
+        let Some(overtime) = self.pb.overtime else {
+            return self.greedy_stocking(state, 1).0;
+        };
+        let (stocking, _) = self.greedy_stocking(state, 2);
+        let (_, extra) = self.greedy_stocking(state, 1);
+        stocking + overtime as isize * extra as isize
+    }
+
+    /// Returns the optimal stocking costs of the remaining demands when the given number
+    /// of units is produced at each period and the changeovers are ignored, and the number
+    /// of units that cannot be produced before the first period
+    fn greedy_stocking(&self, state: &PspState, capacity: usize) -> (isize, usize) {
+        let mut prev_demands = (0..self.pb.n_items).map(|i| state.prev_demand(i)).collect::<Vec<isize>>();
+        let mut stocking = 0;
+        let mut lateness = 0;
+        let mut units = BinaryHeap::new();
+        for time in (0..state.time).rev() {
+            for i in 0..self.pb.n_items {
+                while self.pb.deadline(i, prev_demands[i]) >= time as isize {
+                    let demand = self.pb.demand_of_unit(i, prev_demands[i] as usize);
+                    let cost = self.pb.stocking[i] as isize;
+                    units.push((cost, demand.period));
+                    lateness += cost * (demand.period - demand.earliest) as isize;
+                    prev_demands[i] -= 1;
+                }
+            }
+
+            for _ in 0..capacity {
+                if let Some((cost, deadline)) = units.pop() {
+                    stocking += cost * (deadline - time) as isize;
+                }
+            }
+        }
+        ((stocking - lateness).max(0), units.len())
+    }
+
+    /// Each item that must be produced, or the next item, is switched to from another of
+    /// them, except the first one produced, which cannot be the next item when there are
+    /// others. Hence the sum of their cheapest incoming changeovers minus the largest one
+    /// bounds the changeover costs
+    fn assignment_bound(&self, state: &PspState, members: Set32) -> isize {
+        if members.len() <= 1 {
+            return 0;
+        }
+        let mut total = 0;
+        let mut largest = 0;
+        for j in members.iter() {
+            let incoming = members.iter()
+                .filter(|i| *i != j && self.pb.can_follow(*i as usize, j as usize))
+                .map(|i| self.pb.changeover(i as usize, j as usize))
+                .min()
+                .unwrap_or(0) as isize;
+            total += incoming;
+            if j as isize != state.next {
+                largest = largest.max(incoming);
+            }
+        }
+        total - largest
     }
 
     fn members(state: &PspState) -> Set32 {
         let mut mem = Set32::empty();
-        for (i, d) in state.prev_demands.iter().copied().enumerate() {
+        for (i, d) in state.prev_demands.iter().enumerate() {
             if d >= 0 {
                 mem = mem.insert(i as u8);
             }
@@ -204,16 +1384,36 @@ impl Relaxation for PspRelax {
 
     fn merge(&self, states: &mut dyn Iterator<Item = &Self::State>) -> Self::State {
         let mut time = self.pb.horizon;
-        let mut prev_demands = vec![isize::MAX; self.pb.n_items];
+        let mut prev_demands = vec![Period::MAX; self.pb.n_items];
+        let mut matching = u64::MAX;
+        let mut next = None;
+        let mut after_next = None;
 
         for s in states {
             time = time.min(s.time);
             prev_demands.iter_mut()
-                .zip(s.prev_demands.iter().copied())
+                .zip(s.prev_demands.iter())
                 .for_each(|(x, y)| *x = y.min(*x));
+            matching &= s.matching;
+            next = match next {
+                None => Some(s.next),
+                Some(n) if n == s.next => Some(n),
+                Some(_) => Some(-1),
+            };
+            after_next = match after_next {
+                None => Some(s.after_next),
+                Some(n) if n == s.after_next => Some(n),
+                Some(_) => Some(-1),
+            };
         }
 
-        PspState{time, next: -1, prev_demands}
+        // forgetting the item after the next one spares its second-order changeover
+        let (next, after_next) = match self.merge {
+            MergeStrategy::Min => (-1, -1),
+            MergeStrategy::EqualNext if next.unwrap_or(-1) == -1 => (-1, -1),
+            MergeStrategy::EqualNext => (next.unwrap_or(-1), after_next.unwrap_or(-1)),
+        };
+        PspState{time, next, after_next, prev_demands: prev_demands.into(), matching}
     }
 
     fn relax(
@@ -228,26 +1428,33 @@ impl Relaxation for PspRelax {
     }
 
     fn fast_upper_bound(&self, state: &Self::State) -> isize {
-        let idx: u32 = u32::from(Self::members(state));
-        let co = self.mst[idx as usize] as isize;
+        match self.cache.as_ref() {
+            Some(cache) => cache.get_or_insert_with(state, || self.rough_upper_bound(state)),
+            None => self.rough_upper_bound(state),
+        }
+    }
+}
 
-        let mut prev_demands = state.prev_demands.clone();
-        let mut ww = 0;
-        let mut items = BinaryHeap::new();
-        for time in (0..state.time).rev() {
-            for i in 0..self.pb.n_items {
-                while prev_demands[i] >= time as isize {
-                    items.push((self.pb.stocking[i], prev_demands[i]));
-                    prev_demands[i] = self.pb.prev_demands[i][prev_demands[i] as usize];
-                }
-            }
+impl PspRelax {
+    /// Returns the rough upper bound of the given state
+    fn rough_upper_bound(&self, state: &PspState) -> isize {
+        let co = self.changeover_bound(state);
 
-            if let Some((cost, deadline)) = items.pop() {
-                ww += cost as isize * (time as isize - deadline);
-            }
+        let mut ww = match self.stocking_bound {
+            StockingBound::Greedy => self.greedy_stocking_bound(state),
+            StockingBound::None => 0,
+        };
+
+        if let Some(lagrangian) = self.lagrangian.as_ref() {
+            ww = ww.max(lagrangian.stocking_bound(state.time, &state.prev_demands));
         }
     
-        -(co + ww)
+        let idle = self.pb.idle_bound(state);
+        let ub = -(co + ww + idle);
+        self.compressions.iter()
+            .filter_map(|c| c.get_ub(state))
+            .map(|value| value - idle)
+            .fold(ub, isize::min)
     }
 }
 
@@ -261,9 +1468,228 @@ impl StateRanking for PspRanking {
     type State = PspState;
 
     fn compare(&self, a: &Self::State, b: &Self::State) -> std::cmp::Ordering {
-        let tot_a = a.prev_demands.iter().sum::<isize>();
-        let tot_b = b.prev_demands.iter().sum::<isize>();
+        let tot_a = a.prev_demands.iter().map(|p| p as isize).sum::<isize>();
+        let tot_b = b.prev_demands.iter().map(|p| p as isize).sum::<isize>();
         
         tot_a.cmp(&tot_b)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::{json, Value};
+
+    use crate::instance::fixtures::{instance, parse, with};
+    use crate::resolution::dp::dynamic_programming;
+    use crate::resolution::exhaustive::exhaustive;
+    use super::*;
+
+    fn model(instance: Value) -> Psp {
+        Psp::try_from(parse(instance)).unwrap()
+    }
+
+    /// Returns the cost of the optimal schedules, found both by the dynamic program over
+    /// the states of the model and by the enumeration of the schedules
+    fn optimum(problem: &Psp) -> Option<isize> {
+        let budget = TimeBudget::new(Duration::from_secs(60));
+        let (_, dp) = dynamic_programming(problem, &budget);
+        let (_, enumeration) = exhaustive(problem, &budget);
+        let cost = dp.map(|(value, solution)| {
+            assert_eq!(problem.cost(&problem.schedule(&solution)), Some(-value));
+            -value
+        });
+        assert_eq!(cost, enumeration.map(|(value, _)| -value));
+        cost
+    }
+
+    #[test]
+    fn base_instance() {
+        let problem = model(instance());
+        assert_eq!(problem.cost(&[1, 0, IDLE, 0]), Some(12));
+        assert_eq!(optimum(&problem), Some(12));
+    }
+
+    #[test]
+    fn max_stock_delays_the_productions() {
+        assert_eq!(optimum(&model(with(instance(), json!({"max_stock": [5, 0]})))), Some(13));
+        assert_eq!(optimum(&model(with(instance(), json!({"max_stock": [0, 0]})))), Some(20));
+    }
+
+    #[test]
+    fn max_stock_can_be_infeasible() {
+        let instance = with(instance(), json!({"demands": [[0, 2, 0, 0], [0, 0, 1, 0]]}));
+        assert_eq!(optimum(&model(instance.clone())), Some(11));
+        assert_eq!(optimum(&model(with(instance, json!({"max_stock": [0, 5]})))), None);
+    }
+
+    #[test]
+    fn max_stock_counts_the_units_of_a_demand() {
+        // the three units are stocked during one and two periods
+        assert_eq!(optimum(&model(with(single_item(), json!({"max_stock": [2]})))), Some(3));
+        assert_eq!(model(with(single_item(), json!({"max_stock": [1]}))).cost(&[0, 0, 0]), None);
+    }
+
+    #[test]
+    fn storage_capacity_is_shared_by_the_items() {
+        assert_eq!(optimum(&model(with(instance(), json!({"storage_capacity": 1})))), Some(12));
+        assert_eq!(optimum(&model(with(instance(), json!({"storage_capacity": 0})))), Some(20));
+    }
+
+    #[test]
+    fn lost_setup_saves_the_changeovers_after_idle_periods() {
+        let problem = model(with(instance(), json!({"idle_setup": "lost"})));
+        assert_eq!(problem.cost(&[0, IDLE, 1, 0]), Some(11));
+        assert_eq!(optimum(&problem), Some(11));
+        assert_eq!(model(instance()).cost(&[0, IDLE, 1, 0]), Some(21));
+    }
+
+    #[test]
+    fn forbidden_transitions_are_filtered() {
+        let problem = model(with(instance(), json!({"forbidden_transitions": [[1, 0]]})));
+        assert_eq!(problem.cost(&[1, 0, IDLE, 0]), None);
+        assert_eq!(optimum(&problem), Some(13));
+        // the setup is kept through idle periods, so the items can never follow each other
+        assert_eq!(optimum(&model(with(instance(), json!({"forbidden_transitions": [[0, 1], [1, 0]]})))), None);
+    }
+
+    #[test]
+    fn precedences_order_the_demands() {
+        let before = json!({"item": 0, "period": 3});
+        let after = json!({"item": 1, "period": 2});
+        let problem = model(with(instance(), json!({"precedences": [{"before": before, "after": after}]})));
+        assert_eq!(problem.cost(&[1, 0, IDLE, 0]), None);
+        assert_eq!(optimum(&problem), Some(13));
+    }
+
+    #[test]
+    fn demands_of_several_units_are_produced_unit_by_unit() {
+        // two units of each item, due at periods 2 and 4 of 5 periods
+        let units = with(instance(), json!({"nb_periods": 5, "demands": [[0, 0, 2, 0, 0], [0, 0, 0, 0, 2]]}));
+        let problem = model(units.clone());
+        assert_eq!(problem.cost(&[IDLE, 0, 0, 1, 1]), Some(12));
+        assert_eq!(optimum(&problem), Some(12));
+
+        // both units of an item are produced at once in free overtime
+        let overtime = with(units.clone(), json!({"overtime_cost": 0}));
+        assert_eq!(model(overtime.clone()).cost(&[IDLE, IDLE, 2, IDLE, 3]), Some(10));
+        assert_eq!(optimum(&model(overtime.clone())), Some(10));
+
+        // the units of the second item must all be produced before those of the first one
+        let precedence = json!({"precedences": [{"before": {"item": 1, "period": 4}, "after": {"item": 0, "period": 2}}]});
+        assert_eq!(optimum(&model(with(units, precedence.clone()))), None);
+        let problem = model(with(overtime, precedence));
+        assert_eq!(problem.cost(&[IDLE, 3, 2, IDLE, IDLE]), Some(16));
+        assert_eq!(optimum(&problem), Some(16));
+    }
+
+    fn constrained(instance: Value, constraints: Value) -> Psp {
+        let mut problem = model(instance);
+        problem.constrain(&serde_json::from_value(constraints).unwrap()).unwrap();
+        problem
+    }
+
+    #[test]
+    fn side_constraints_ban_productions_and_transitions() {
+        let problem = constrained(instance(), json!({"productions": [{"item": 1, "period": 0}]}));
+        assert_eq!(problem.cost(&[1, 0, IDLE, 0]), None);
+        assert_eq!(optimum(&problem), Some(13));
+        let problem = constrained(instance(), json!({"transitions": [{"from": 1, "to": 0, "period": 0}]}));
+        assert_eq!(optimum(&problem), Some(13));
+        // the first demand of the first item cannot be produced on time
+        let problem = constrained(instance(), json!({"productions": [{"item": 0, "period": 0}, {"item": 0, "period": 1}]}));
+        assert_eq!(optimum(&problem), None);
+    }
+
+    #[test]
+    fn nothing_is_produced_while_the_machine_is_down() {
+        let problem = model(with(instance(), json!({"availability": [false, true, true, true]})));
+        assert_eq!(problem.cost(&[1, 0, IDLE, 0]), None);
+        assert_eq!(optimum(&problem), Some(20));
+        assert_eq!(optimum(&model(with(instance(), json!({"availability": [true, true, false, true]})))), Some(12));
+
+        let instance = parse(with(instance(), json!({"availability": [false, false, true, true]})));
+        assert!(matches!(Psp::try_from(instance), Err(PspError::Infeasible(_))));
+    }
+
+    /// A single item with 3 units due at the last of 3 periods
+    fn single_item() -> Value {
+        json!({
+            "nb_types": 1,
+            "nb_periods": 3,
+            "stocking": [1],
+            "changeover": [[0]],
+            "demands": [[0, 0, 3]],
+        })
+    }
+
+    #[test]
+    fn overtime_absorbs_the_excess_stock() {
+        assert_eq!(optimum(&model(single_item())), Some(3));
+        // at most one unit can be stocked, so two of them are produced at the last period
+        let overtime = json!({"overtime_cost": 5});
+        let problem = model(with(with(single_item(), overtime.clone()), json!({"max_stock": [1]})));
+        assert_eq!(problem.cost(&[IDLE, 0, 1]), Some(6));
+        assert_eq!(optimum(&problem), Some(6));
+        assert_eq!(optimum(&model(with(with(single_item(), overtime), json!({"storage_capacity": 1})))), Some(6));
+        assert_eq!(optimum(&model(with(single_item(), json!({"max_stock": [1]})))), None);
+    }
+
+    #[test]
+    fn overtime_produces_every_remaining_unit() {
+        // a single unit of each item is demanded at the last of two periods: the first
+        // period must produce the unit that remains for the other item
+        let problem = model(with(instance(), json!({"nb_periods": 2, "demands": [[0, 1], [0, 1]], "overtime_cost": 0})));
+        assert_eq!(problem.cost(&[0, IDLE]), None);
+        assert_eq!(optimum(&problem), Some(11));
+    }
+
+    #[test]
+    fn idle_periods_are_penalized() {
+        // every schedule of the two-item instance leaves one available period idle
+        let problem = model(with(instance(), json!({"idle_cost": 1})));
+        assert_eq!(problem.cost(&[1, 0, IDLE, 0]), Some(13));
+        assert_eq!(optimum(&problem), Some(13));
+
+        // producing both units at once in overtime leaves the first period idle
+        let overtime = json!({"nb_periods": 2, "stocking": [2], "demands": [[0, 2]], "overtime_cost": 1});
+        assert_eq!(optimum(&model(with(single_item(), overtime.clone()))), Some(1));
+        assert_eq!(optimum(&model(with(with(single_item(), overtime), json!({"idle_cost": 3})))), Some(2));
+    }
+
+    #[test]
+    fn idle_periods_count_the_remaining_units() {
+        // two units of each item leave one of the 5 periods idle
+        let units = with(instance(), json!({"nb_periods": 5, "demands": [[0, 0, 2, 0, 0], [0, 0, 0, 0, 2]], "idle_cost": 2}));
+        let problem = model(units.clone());
+        assert_eq!(problem.idle_bound(&problem.initial_state()), 2);
+        assert_eq!(optimum(&problem), Some(14));
+
+        // producing two units at once idles more periods than the stocking it spares
+        let problem = model(with(units, json!({"overtime_cost": 0})));
+        assert_eq!(problem.idle_bound(&problem.initial_state()), 2);
+        assert_eq!(problem.cost(&[IDLE, IDLE, 2, IDLE, 3]), Some(16));
+        assert_eq!(optimum(&problem), Some(14));
+    }
+
+    #[test]
+    fn idle_bound_is_a_lower_bound() {
+        let problem = model(with(instance(), json!({"idle_cost": 1})));
+        assert_eq!(problem.idle_bound(&problem.initial_state()), 1);
+        assert_eq!(model(instance()).idle_bound(&model(instance()).initial_state()), 0);
+    }
+
+    #[test]
+    fn second_order_changeovers_depend_on_the_two_last_items() {
+        // switching back to the first item after the second one costs 7 more
+        let second_order = json!({"second_order_changeover": [[[0, 0], [7, 0]], [[0, 0], [0, 0]]]});
+        let problem = model(with(instance(), second_order.clone()));
+        assert_eq!(problem.cost(&[1, 0, IDLE, 0]), Some(12));
+        assert_eq!(problem.cost(&[IDLE, 0, 1, 0]), Some(27));
+        assert_eq!(optimum(&problem), Some(12));
+
+        let problem = model(with(with(instance(), second_order), json!({"availability": [false, true, true, true]})));
+        assert_eq!(optimum(&problem), Some(27));
+    }
+}
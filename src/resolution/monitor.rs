@@ -0,0 +1,233 @@
+//! This module implements the live terminal dashboard of the solve subcommand. The
+//! solver reports its incumbents and the bounds proved by its runs on the full problem
+//! to a shared monitor, and the subproblems popped by the branch-and-bound are counted by
+//! a fringe wrapper. The bounds of the popped subproblems are not reported: they only hold
+//! for the nodes of one thread, and for the fixed decisions of the neighborhoods of the
+//! local search. A thread redraws the dashboard periodically, and another one waits for
+//! the enter key to stop the search gracefully. The monitor can also record the trace 
+//! of the best bounds over time.
+
+use std::{collections::VecDeque, fmt::Write as _, io::{self, BufRead, Write}, sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}}, thread, time::{Duration, Instant}};
+
+use ddo::{Fringe, SubProblem};
+
+use crate::resolution::model::{Psp, PspState, IDLE};
+
+/// The number of gaps kept to draw the sparkline
+const HISTORY: usize = 60;
+
+/// The progress of the search, as shown on the dashboard
+#[derive(Debug, Default)]
+struct Status {
+    /// The value and the schedule of the best solution found so far
+    incumbent: Option<(isize, Vec<isize>)>,
+    fringe: usize,
+    explored: usize,
+    gaps: VecDeque<f64>,
+    /// The smallest upper bound proved so far
+    best_bound: Option<isize>,
+    /// The time, the incumbent value and the best bound each time one of them changed, 
+    /// if the trace is recorded
+    trace: Option<Vec<(f64, Option<isize>, Option<isize>)>>,
+}
+
+impl Status {
+    /// Records the given upper bound if it improves the best one
+    fn improve_bound(&mut self, bound: isize, time: f64) {
+        if self.best_bound.map_or(true, |b| bound < b) {
+            self.best_bound = Some(bound);
+            self.record(time);
+        }
+    }
+
+    /// Adds the current incumbent value and best bound to the trace, if it is recorded
+    fn record(&mut self, time: f64) {
+        let incumbent = self.incumbent.as_ref().map(|(v, _)| *v);
+        if let Some(trace) = self.trace.as_mut() {
+            trace.push((time, incumbent, self.best_bound));
+        }
+    }
+}
+
+/// The state of the search shared between the solver and the dashboard
+pub struct Monitor {
+    start: Instant,
+    stop: AtomicBool,
+    done: AtomicBool,
+    status: Mutex<Status>,
+}
+
+impl Monitor {
+    pub fn new() -> Self {
+        Monitor { start: Instant::now(), stop: AtomicBool::new(false), done: AtomicBool::new(false), status: Mutex::new(Status::default()) }
+    }
+
+    /// Returns a monitor that records the trace of the best bounds
+    pub fn with_trace(self) -> Self {
+        self.status.lock().unwrap().trace = Some(vec![]);
+        self
+    }
+
+    /// Returns true iff the user asked to stop the search
+    pub fn must_stop(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    /// Records the given solution if it is better than the current incumbent
+    pub fn report_incumbent(&self, problem: &Psp, value: isize, solution: &ddo::Solution) {
+        let mut status = self.status.lock().unwrap();
+        if status.incumbent.as_ref().map_or(true, |(v, _)| value > *v) {
+            status.incumbent = Some((value, problem.schedule(solution)));
+            status.record(self.start.elapsed().as_secs_f64());
+        }
+    }
+
+    /// Records an upper bound proved on the value of the solutions
+    pub fn report_bound(&self, bound: isize) {
+        self.status.lock().unwrap().improve_bound(bound, self.start.elapsed().as_secs_f64());
+    }
+
+    /// Returns the recorded trace in the csv format: the time and the bounds on the cost 
+    /// each time one of them changed
+    pub fn trace_csv(&self, problem: &Psp) -> String {
+        let status = self.status.lock().unwrap();
+        let format = |v: Option<isize>| v.map_or(String::new(), |v| problem.format_cost(-v));
+        let mut out = String::from("time,lower_bound,upper_bound\n");
+        for (time, incumbent, bound) in status.trace.iter().flatten() {
+            writeln!(out, "{time:.3},{},{}", format(*bound), format(*incumbent)).unwrap();
+        }
+        out
+    }
+
+    /// Returns the number of subproblems explored so far
+    pub fn explored(&self) -> usize {
+        self.status.lock().unwrap().explored
+    }
+
+    /// Returns the value of the incumbent and the best upper bound proved, if any
+    #[cfg(feature = "serve")]
+    pub fn progress(&self) -> (Option<isize>, Option<isize>) {
+        let status = self.status.lock().unwrap();
+        (status.incumbent.as_ref().map(|(v, _)| *v), status.best_bound)
+    }
+
+    /// Starts the threads drawing the dashboard and waiting for the enter key, the dashboard
+    /// is redrawn until `finish` is called
+    pub fn start(self: &Arc<Self>, problem: Psp) {
+        let monitor = self.clone();
+        thread::spawn(move || {
+            let mut line = String::new();
+            if matches!(io::stdin().lock().read_line(&mut line), Ok(n) if n > 0) {
+                monitor.stop.store(true, Ordering::Relaxed);
+            }
+        });
+
+        let monitor = self.clone();
+        thread::spawn(move || {
+            while !monitor.done.load(Ordering::Relaxed) {
+                monitor.draw(&problem);
+                thread::sleep(Duration::from_millis(500));
+            }
+        });
+    }
+
+    /// Stops redrawing the dashboard
+    pub fn finish(&self) {
+        self.done.store(true, Ordering::Relaxed);
+    }
+
+    fn draw(&self, problem: &Psp) {
+        let mut status = self.status.lock().unwrap();
+        let elapsed = self.start.elapsed().as_secs_f64();
+
+        let gap = match (status.incumbent.as_ref(), status.best_bound) {
+            (Some((value, _)), Some(bound)) if *value != 0 => Some(((bound - value) as f64 / value.abs() as f64).max(0.0)),
+            _ => None,
+        };
+        if let Some(gap) = gap {
+            status.gaps.push_back(gap);
+            if status.gaps.len() > HISTORY {
+                status.gaps.pop_front();
+            }
+        }
+
+        let mut out = String::from("\x1b[2J\x1b[H");
+        out.push_str(&format!("elapsed    {elapsed:.1}s (press enter to stop)\n"));
+        match status.incumbent.as_ref() {
+            Some((value, _)) => out.push_str(&format!("incumbent  {}\n", problem.format_cost(-value))),
+            None => out.push_str("incumbent  none\n"),
+        }
+        match status.best_bound {
+            Some(bound) => out.push_str(&format!("bound      {}\n", problem.format_cost(-bound))),
+            None => out.push_str("bound      none\n"),
+        }
+        match gap {
+            Some(gap) => out.push_str(&format!("gap        {:.2}% {}\n", 100.0 * gap, Self::sparkline(&status.gaps))),
+            None => out.push_str("gap        none\n"),
+        }
+        out.push_str(&format!("fringe     {}\n", status.fringe));
+        out.push_str(&format!("nodes/s    {:.0}\n", status.explored as f64 / elapsed.max(1e-3)));
+        if let Some((_, schedule)) = status.incumbent.as_ref() {
+            let preview = schedule.iter()
+                .map(|v| if *v == IDLE { ".".to_string() } else { v.to_string() })
+                .collect::<Vec<String>>()
+                .join(" ");
+            out.push_str(&format!("schedule   {preview}\n"));
+        }
+
+        let mut stdout = io::stdout().lock();
+        let _ = stdout.write_all(out.as_bytes());
+        let _ = stdout.flush();
+    }
+
+    fn sparkline(gaps: &VecDeque<f64>) -> String {
+        const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+        let max = gaps.iter().copied().fold(f64::EPSILON, f64::max);
+        gaps.iter().map(|g| BARS[((g / max) * 7.0).round() as usize]).collect()
+    }
+}
+
+/// The fringes used by the solver
+pub type BoxedFringe<'a> = Box<dyn Fringe<State = PspState> + Send + Sync + 'a>;
+
+/// A fringe that counts the subproblems popped by the solver on the monitor
+pub struct MonitoredFringe<'a> {
+    inner: BoxedFringe<'a>,
+    monitor: &'a Monitor,
+}
+
+impl<'a> MonitoredFringe<'a> {
+    pub fn new(inner: BoxedFringe<'a>, monitor: &'a Monitor) -> Self {
+        MonitoredFringe { inner, monitor }
+    }
+}
+
+impl Fringe for MonitoredFringe<'_> {
+    type State = PspState;
+
+    fn push(&mut self, node: SubProblem<Self::State>) {
+        self.inner.push(node);
+    }
+
+    fn pop(&mut self) -> Option<SubProblem<Self::State>> {
+        let node = self.inner.pop();
+        let mut status = self.monitor.status.lock().unwrap();
+        status.fringe = self.inner.len();
+        if node.is_some() {
+            status.explored += 1;
+        }
+        node
+    }
+
+    fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+}
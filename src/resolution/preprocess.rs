@@ -0,0 +1,257 @@
+//! This module simplifies the problems before they are solved. The items that are never
+//! demanded are never produced, so they only enlarge the states and the changeover
+//! matrix: they are removed. The items that are identical in every respect are merged
+//! into a single item that serves all their demands, which removes the symmetric
+//! interleavings of their productions. Finally, the idle periods at both ends of the
+//! horizon are trimmed, since they lengthen every diagram. The solutions of the reduced
+//! problem are mapped back to the items and periods of the original one.
+
+use ddo::Solution;
+use tracing::info;
+
+use crate::instance::{DemandRef, IdleSetup, Precedence, SparseDemand};
+use crate::resolution::model::{Psp, IDLE};
+
+/// A problem restricted to its demanded items and to the periods where they may be
+/// produced, where the identical items are merged
+pub struct Preprocessed {
+    pub problem: Psp,
+    /// The items of the original problem represented by each item of the reduced one,
+    /// which produce its successive units in turn
+    pub items: Vec<Vec<usize>>,
+    /// The number of leading periods of the original problem that are removed
+    pub head: usize,
+    /// The horizon of the original problem
+    pub horizon: usize,
+}
+
+impl Preprocessed {
+    /// Returns the given problem without simplification
+    pub fn identity(problem: &Psp) -> Self {
+        Self {
+            problem: problem.clone(),
+            items: (0..problem.n_items).map(|i| vec![i]).collect(),
+            head: 0,
+            horizon: problem.horizon,
+        }
+    }
+
+    /// Simplifies the given problem: its items and its idle periods are reduced
+    pub fn new(problem: &Psp) -> Self {
+        Self::reduce(problem).trim()
+    }
+
+    /// Removes the items of the given problem that are never demanded, and merges its
+    /// identical items. A problem where an item without demand is fixed is infeasible,
+    /// and it is kept as is
+    fn reduce(problem: &Psp) -> Self {
+        let demanded = |v: isize| v == IDLE || !problem.demands_of(v as usize).is_empty();
+        let items = Self::classes(problem);
+        // the side constraints are given for the items of the original problem, and the
+        // demands of the merged items cannot be stacked over the periods where the machine is
+        // down nor produced in overtime. The units of the merged items are handed out in turn
+        // when restoring the schedule, which changes the second-order changeovers
+        if items.len() == problem.n_items || items.is_empty() || problem.has_side_constraints() || problem.has_downtime()
+            || problem.overtime.is_some()
+            || (!problem.second_order.is_empty() && items.iter().any(|class| class.len() > 1))
+            || problem.fixed.iter().flatten().any(|v| !demanded(*v)) {
+            return Self::identity(problem);
+        }
+
+        // the demands of each merged item, and the stocking cost saved by moving them
+        let mut offset = problem.offset;
+        let demands = items.iter()
+            .map(|class| if class.len() > 1 {
+                let (demands, cost) = Self::stack(problem, class).expect("only the stackable classes are merged");
+                offset += cost;
+                Psp::compute_demands(&[demands]).remove(0)
+            } else {
+                problem.demands[class[0]].clone()
+            })
+            .collect();
+        info!(removed = problem.n_items - items.iter().map(Vec::len).sum::<usize>(),
+            merged = items.iter().filter(|c| c.len() > 1).map(Vec::len).sum::<usize>(),
+            "items preprocessed");
+
+        let mut index = vec![None; problem.n_items];
+        items.iter().enumerate().for_each(|(k, class)| class.iter().for_each(|i| index[*i] = Some(k)));
+        let item = |i: isize| if i == IDLE { IDLE } else { index[i as usize].expect("only demanded items are mapped") as isize };
+        let demand = |d: DemandRef| DemandRef { item: index[d.item].expect("the precedences are between demands"), period: d.period };
+        // the members of a class are identical, so any of them stands for it
+        let representatives = items.iter().map(|class| class[0]).collect::<Vec<usize>>();
+        let pairs = || representatives.iter().flat_map(|a| representatives.iter().map(move |b| (*a, *b)));
+
+        let mut reduced = Psp {
+            n_items: items.len(),
+            stocking: representatives.iter().map(|i| problem.stocking[*i]).collect(),
+            changeover: pairs().map(|(a, b)| problem.changeover(a, b)).collect(),
+            second_order: if problem.second_order.is_empty() {
+                vec![]
+            } else {
+                pairs().flat_map(|(a, b)| representatives.iter().map(move |c| problem.second_order(a, b, *c))).collect()
+            },
+            demands,
+            max_stock: representatives.iter().map(|i| problem.max_stock[*i]).collect(),
+            incompatible: pairs().map(|(a, b)| !problem.can_follow(a, b)).collect(),
+            precedences: problem.precedences.iter()
+                .map(|p| Precedence { before: demand(p.before), after: demand(p.after) })
+                .collect(),
+            // the forbidden schedules that produce a removed item cannot be produced anyway
+            forbidden: problem.forbidden.iter()
+                .filter(|s| s.iter().all(|v| demanded(*v)))
+                .map(|s| s.iter().map(|v| item(*v)).collect())
+                .collect(),
+            fixed: problem.fixed.iter().map(|v| v.map(item)).collect(),
+            twins: vec![vec![]; items.len()],
+            offset,
+            ..problem.clone()
+        };
+        // the symmetries are only valid as long as no decision is fixed nor forbidden
+        if problem.forbidden.is_empty() && problem.fixed.iter().all(|v| v.is_none()) {
+            reduced = reduced.with_symmetries();
+        }
+
+        Self { problem: reduced, items, head: 0, horizon: problem.horizon }
+    }
+
+    /// Removes the periods after the last demand, where nothing may be produced. When the
+    /// setup is kept through idle periods, the changeovers of a schedule only depend on
+    /// its sequence of items, which is produced at least as cheaply as late as possible:
+    /// the n units then start no sooner than n-1 periods before the first demand, and
+    /// the periods before are removed as well. This is only done while no decision is
+    /// fixed nor forbidden, no side constraint is given and the machine is never down, and
+    /// the head is kept when the demands have service windows
+    fn trim(mut self) -> Self {
+        let problem = &self.problem;
+        if !problem.forbidden.is_empty() || problem.fixed.iter().any(|v| v.is_some()) || problem.has_side_constraints()
+            || problem.has_downtime() {
+            return self;
+        }
+        let demands = || problem.demands.iter().flatten();
+        let (Some(first), Some(last)) = (demands().map(|d| d.period).min(), demands().map(|d| d.period).max()) else {
+            return self;
+        };
+        let units = demands().map(|d| d.quantity).sum::<usize>();
+        let head = if !problem.windows && problem.idle_setup == IdleSetup::Kept {
+            (first + 1).saturating_sub(units)
+        } else {
+            0
+        };
+        let horizon = last + 1 - head;
+        if horizon == problem.horizon {
+            return self;
+        }
+        info!(head, tail = problem.horizon - last - 1, "idle periods trimmed");
+
+        let problem = &mut self.problem;
+        // the removed periods are idle, and the machine is available at all of them
+        problem.offset += problem.idle * (problem.horizon - horizon);
+        problem.horizon = horizon;
+        problem.fixed = vec![None; horizon];
+        for demand in problem.demands.iter_mut().flatten() {
+            demand.period -= head;
+            demand.earliest -= head;
+        }
+        for precedence in problem.precedences.iter_mut() {
+            precedence.before.period -= head;
+            precedence.after.period -= head;
+        }
+        self.head = head;
+        self
+    }
+
+    /// Returns the demanded items of the given problem, grouped in classes of identical
+    /// items that can be merged
+    fn classes(problem: &Psp) -> Vec<Vec<usize>> {
+        // moving the demands of the merged items changes their stocks, and their
+        // productions can no longer be told apart by the fixed and forbidden decisions
+        let mergeable = !problem.windows && problem.capacity == usize::MAX
+            && problem.forbidden.is_empty() && problem.fixed.iter().all(|v| v.is_none());
+        let stackable = |i: usize| problem.max_stock[i] == usize::MAX && problem.demands_of(i).iter().all(|d| d.quantity == 1);
+
+        let mut classes: Vec<Vec<usize>> = vec![];
+        let mut class = vec![None; problem.n_items];
+        for i in (0..problem.n_items).filter(|i| !problem.demands_of(*i).is_empty()) {
+            // the twins of an item are identical to each other, so the first one stands for all
+            let twin = problem.twins.get(i)
+                .and_then(|t| t.first())
+                .filter(|_| mergeable && stackable(i))
+                .and_then(|a| class[*a]);
+            match twin {
+                Some(k) => {
+                    classes[k].push(i);
+                    class[i] = Some(k);
+                },
+                None => {
+                    class[i] = Some(classes.len());
+                    classes.push(vec![i]);
+                },
+            }
+        }
+
+        // a class whose demands cannot all be stacked is not merged
+        classes.into_iter()
+            .flat_map(|c| if c.len() > 1 && Self::stack(problem, &c).is_none() {
+                c.into_iter().map(|i| vec![i]).collect()
+            } else {
+                vec![c]
+            })
+            .collect()
+    }
+
+    /// Returns the unit demands of an item serving those of the given identical items,
+    /// each moved to the latest free period before its own, and the stocking cost of
+    /// these moves. A schedule serves the original demands iff it serves the moved ones,
+    /// since a single unit is produced per period. Returns None if some demand would move
+    /// before the first period
+    fn stack(problem: &Psp, class: &[usize]) -> Option<(Vec<SparseDemand>, usize)> {
+        let mut periods: Vec<usize> = vec![];
+        let mut moves = 0;
+        for deadline in problem.deadlines(class[0]).collect::<Vec<usize>>().into_iter().rev() {
+            for _ in class {
+                let period = match periods.last() {
+                    Some(last) => last.checked_sub(1)?.min(deadline),
+                    None => deadline,
+                };
+                moves += deadline - period;
+                periods.push(period);
+            }
+        }
+
+        let demands = periods.into_iter().rev()
+            .map(|period| SparseDemand { period, quantity: 1, earliest: None })
+            .collect();
+        Some((demands, problem.stocking[class[0]] * moves))
+    }
+
+    /// Returns the schedule of the original problem corresponding to the given schedule
+    /// of the reduced problem. The units of a merged item are assigned to its members in
+    /// turn, so that each of them is served on time, and the trimmed periods are idle
+    pub fn restore_schedule(&self, schedule: &[isize]) -> Vec<isize> {
+        let mut produced = vec![0; self.items.len()];
+        let items = schedule.iter()
+            .map(|v| {
+                if *v == IDLE {
+                    return IDLE;
+                }
+                // the items are never merged when overtime is allowed, so the units
+                // produced in overtime keep their value
+                let item = self.problem.item_of(*v);
+                let members = &self.items[item as usize];
+                let count = &mut produced[item as usize];
+                *count += 1;
+                members[(*count - 1) % members.len()] as isize + (*v - item)
+            });
+
+        let mut restored = vec![IDLE; self.head];
+        restored.extend(items);
+        restored.resize(self.horizon, IDLE);
+        restored
+    }
+
+    /// Returns the solution of the given original problem corresponding to the given
+    /// solution of the reduced problem
+    pub fn restore(&self, original: &Psp, solution: &Solution) -> Solution {
+        original.solution(&self.restore_schedule(&self.problem.schedule(solution)))
+    }
+}
@@ -0,0 +1,120 @@
+//! This module implements the persistent representation of the previous demands of the
+//! states. A transition changes the previous demand of a single item, so the successors
+//! of a state share its buffer and only record the few entries that differ from it. The
+//! buffer is copied once the differences no longer fit in the state. The previous
+//! demands of the instances with few items fit in the state itself, and they are stored
+//! there without any allocation: the representation is chosen when the root state is
+//! built, from the number of items.
+
+use std::{fmt, hash::{Hash, Hasher}, ops::Index, sync::Arc};
+
+use crate::resolution::model::Period;
+
+/// The maximum number of entries that may differ from the shared buffer
+const MAX_DIFF: usize = 4;
+
+/// The maximum number of items whose previous demands are stored inline, which take
+/// about as much space as a shared buffer and its differences
+const MAX_INLINE: usize = 24;
+
+/// The time at which the previous demand of each item had been filled
+#[derive(Clone)]
+pub enum PrevDemands {
+    Inline {
+        prev: [Period; MAX_INLINE],
+        nb_items: u8,
+    },
+    Shared {
+        base: Arc<[Period]>,
+        /// The items whose previous demand differs from the buffer, and that demand
+        diff: [(u32, Period); MAX_DIFF],
+        nb_diff: u8,
+    },
+}
+
+impl PrevDemands {
+    /// Returns the number of items
+    pub fn nb_items(&self) -> usize {
+        match self {
+            PrevDemands::Inline { nb_items, .. } => *nb_items as usize,
+            PrevDemands::Shared { base, .. } => base.len(),
+        }
+    }
+
+    /// Returns the previous demands in the order of the items
+    pub fn iter(&self) -> impl Iterator<Item = Period> + '_ {
+        (0..self.nb_items()).map(|i| self[i])
+    }
+
+    /// Sets the previous demand of the given item
+    pub fn set(&mut self, item: usize, value: Period) {
+        match self {
+            PrevDemands::Inline { prev, nb_items } => prev[..*nb_items as usize][item] = value,
+            PrevDemands::Shared { diff, nb_diff, .. } => {
+                let len = *nb_diff as usize;
+                if let Some(entry) = diff[..len].iter_mut().find(|(i, _)| *i as usize == item) {
+                    entry.1 = value;
+                } else if len < MAX_DIFF {
+                    diff[len] = (item as u32, value);
+                    *nb_diff += 1;
+                } else {
+                    let mut base = self.iter().collect::<Vec<Period>>();
+                    base[item] = value;
+                    *self = base.into();
+                }
+            },
+        }
+    }
+}
+
+impl Index<usize> for PrevDemands {
+    type Output = Period;
+
+    fn index(&self, item: usize) -> &Period {
+        match self {
+            PrevDemands::Inline { prev, nb_items } => &prev[..*nb_items as usize][item],
+            PrevDemands::Shared { base, diff, nb_diff } => diff[..*nb_diff as usize].iter()
+                .find(|(i, _)| *i as usize == item)
+                .map_or(&base[item], |(_, prev)| prev),
+        }
+    }
+}
+
+impl From<Vec<Period>> for PrevDemands {
+    fn from(base: Vec<Period>) -> Self {
+        if base.len() <= MAX_INLINE {
+            let mut prev = [0; MAX_INLINE];
+            prev[..base.len()].copy_from_slice(&base);
+            PrevDemands::Inline { prev, nb_items: base.len() as u8 }
+        } else {
+            PrevDemands::Shared { base: base.into(), diff: [(0, 0); MAX_DIFF], nb_diff: 0 }
+        }
+    }
+}
+
+impl FromIterator<Period> for PrevDemands {
+    fn from_iter<I: IntoIterator<Item = Period>>(iter: I) -> Self {
+        iter.into_iter().collect::<Vec<Period>>().into()
+    }
+}
+
+impl PartialEq for PrevDemands {
+    fn eq(&self, other: &Self) -> bool {
+        self.nb_items() == other.nb_items() && self.iter().eq(other.iter())
+    }
+}
+
+impl Eq for PrevDemands {}
+
+impl Hash for PrevDemands {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.nb_items());
+        self.iter().for_each(|prev| prev.hash(state));
+    }
+}
+
+impl fmt::Debug for PrevDemands {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
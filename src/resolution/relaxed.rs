@@ -0,0 +1,153 @@
+//! This module implements the compilation of a relaxed decision diagram from the root
+//! of the problem, which yields the bound obtained by the relaxation before any
+//! branch-and-bound takes place.
+
+use std::collections::hash_map::Entry;
+
+use clap::Args;
+use rand::{SeedableRng, seq::SliceRandom};
+use rand_chacha::ChaChaRng;
+use tracing::trace;
+use ddo::{Problem, Relaxation, StateRanking, Cutoff};
+
+use crate::resolution::expansion::expand;
+use crate::resolution::hashing::StateMap;
+use crate::resolution::model::{Psp, PspState, PspRelax, PspRanking};
+use crate::resolution::viz::{Diagram, VizNode, NodeKind, Edge};
+
+/// How the nodes that are merged are selected when a layer is too wide
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum NodeSelection {
+    /// The nodes with the worst value, ties broken by the ranking
+    #[default]
+    WorstValue,
+    /// The node with the worst value and the nodes whose demands are the closest to its own
+    Similar,
+    /// Nodes drawn at random
+    Random,
+}
+
+/// The options of the selection of the merged nodes
+#[derive(Debug, Clone, Copy, Args)]
+pub struct SelectionArgs {
+    /// How the nodes to merge are selected in the relaxed DDs compiled from the root
+    /// (the branch-and-bound always merges the nodes with the worst value)
+    #[clap(long, value_enum, default_value_t=NodeSelection::WorstValue)]
+    pub merge_selection: NodeSelection,
+    /// The seed of the random number generator used by the random selection
+    #[clap(long, default_value="0")]
+    pub merge_seed: u64,
+}
+
+/// Compiles a relaxed DD of the given width from the root of the problem, and returns
+/// the value of its best terminal node: an upper bound on the optimal value. When a layer
+/// is too wide, the nodes picked by the selection are merged.
+/// Returns None if the problem is infeasible or the cutoff is reached.
+pub fn relaxed_dd(problem: &Psp, relaxation: &PspRelax, ranking: &PspRanking, selection: &SelectionArgs, width: usize, cutoff: &dyn Cutoff) -> Option<isize> {
+    compile_relaxed(problem, relaxation, ranking, selection, width, cutoff, false)?.best_value()
+}
+
+/// Compiles a relaxed DD like `relaxed_dd` and returns it. Only its last layer is kept,
+/// unless all layers are requested. Returns None if the cutoff is reached
+pub fn compile_relaxed(problem: &Psp, relaxation: &PspRelax, ranking: &PspRanking, selection: &SelectionArgs, width: usize, cutoff: &dyn Cutoff, all_layers: bool) -> Option<Diagram> {
+    let width = width.max(1);
+    let mut rng = ChaChaRng::seed_from_u64(selection.merge_seed);
+    let mut diagram = Diagram { layers: vec![vec![VizNode::root(problem)]] };
+
+    for depth in 0..problem.nb_variables() {
+        if cutoff.must_stop() {
+            return None;
+        }
+
+        let layer = diagram.layers.last().expect("the root layer is always present");
+        let mut states = layer.iter().filter(|n| n.kind != NodeKind::Deleted).map(|n| &n.state);
+        let Some(variable) = problem.next_variable(depth, &mut states) else {
+            break;
+        };
+
+        let mut next: StateMap<PspState, VizNode> = StateMap::default();
+        let alive = (0..layer.len()).filter(|i| layer[*i].kind != NodeKind::Deleted).collect::<Vec<usize>>();
+        let states = alive.iter().map(|i| &layer[*i].state).collect::<Vec<&PspState>>();
+        for successor in expand(problem, variable, &states, &|_| true) {
+            let i = alive[successor.parent];
+            let node = &layer[i];
+            let value = node.value + successor.cost;
+            let kind = if node.kind == NodeKind::Exact { NodeKind::Exact } else { NodeKind::Relaxed };
+            let edge = Edge { parent: i, decision: successor.decision.value, cost: successor.cost };
+
+            match next.entry(successor.state) {
+                Entry::Occupied(mut e) => {
+                    let n = e.get_mut();
+                    n.value = n.value.max(value);
+                    n.parents.push(edge);
+                    if kind == NodeKind::Relaxed {
+                        n.kind = kind;
+                    }
+                },
+                Entry::Vacant(e) => {
+                    let state = e.key().clone();
+                    e.insert(VizNode { state, value, kind, parents: vec![edge], merged_into: None });
+                },
+            }
+        }
+
+        let mut nodes = next.into_values().collect::<Vec<VizNode>>();
+        if nodes.len() > width {
+            let mut merged = select_merged(&mut nodes, width - 1, selection.merge_selection, ranking, &mut rng);
+            let state = relaxation.merge(&mut merged.iter().map(|n| &n.state));
+            let value = merged.iter()
+                .map(|n| n.value)
+                .max()
+                .unwrap_or(isize::MIN);
+            let parents = merged.iter().flat_map(|n| n.parents.iter().copied()).collect::<Vec<Edge>>();
+
+            let index = match nodes.iter().position(|n| n.state == state) {
+                Some(index) => {
+                    let node = &mut nodes[index];
+                    node.value = node.value.max(value);
+                    node.parents.extend(parents);
+                    node.kind = NodeKind::Merged;
+                    index
+                },
+                None => {
+                    nodes.push(VizNode { state, value, kind: NodeKind::Merged, parents, merged_into: None });
+                    nodes.len() - 1
+                },
+            };
+
+            for node in merged.iter_mut() {
+                node.kind = NodeKind::Deleted;
+                node.merged_into = Some(index);
+            }
+            nodes.extend(merged);
+        }
+
+        if !all_layers {
+            diagram.layers.clear();
+        }
+        trace!(depth, nodes = nodes.len(), "relaxed layer compiled");
+        diagram.layers.push(nodes);
+    }
+
+    Some(diagram)
+}
+
+/// Removes from the given nodes those that must be merged so that only the given number
+/// of nodes is kept, and returns them
+fn select_merged(nodes: &mut Vec<VizNode>, keep: usize, selection: NodeSelection, ranking: &PspRanking, rng: &mut ChaChaRng) -> Vec<VizNode> {
+    match selection {
+        NodeSelection::WorstValue => {
+            nodes.sort_unstable_by(|a, b| b.value.cmp(&a.value).then_with(|| ranking.compare(&b.state, &a.state)));
+        },
+        NodeSelection::Similar => {
+            let worst = nodes.iter()
+                .min_by(|a, b| a.value.cmp(&b.value).then_with(|| ranking.compare(&a.state, &b.state)))
+                .map(|n| n.state.clone())
+                .expect("the layer is too wide, so it is not empty");
+            // the farthest nodes are kept, and the worst node is the closest to itself
+            nodes.sort_by_cached_key(|n| std::cmp::Reverse((worst.demand_distance(&n.state), n.value)));
+        },
+        NodeSelection::Random => nodes.shuffle(rng),
+    }
+    nodes.split_off(keep)
+}
@@ -0,0 +1,224 @@
+//! This module details the costs of a schedule period by period, so that its
+//! objective value can be checked by hand, the inventories it leads to, and the
+//! indicators that the planners use to judge it.
+
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use crate::resolution::model::{Psp, IDLE};
+use crate::solution::{CostDecomposition, ItemCosts, ItemKpis, Kpis};
+
+/// The costs incurred at a period of a schedule
+pub struct PeriodCosts {
+    /// The item produced, or IDLE
+    pub item: isize,
+    /// The changeover cost incurred to switch to the item
+    pub changeover: isize,
+    /// The surcharge of the unit produced in overtime, if any
+    pub overtime: isize,
+    /// The cost of leaving the machine idle while it is available, if it is
+    pub idle: isize,
+    /// The inventory of each item at the end of the period
+    pub inventory: Vec<isize>,
+    /// The stocking cost of that inventory
+    pub stocking: isize,
+}
+
+/// Returns the costs incurred at each period of the given schedule
+pub fn breakdown(problem: &Psp, schedule: &[isize]) -> Vec<PeriodCosts> {
+    let mut produced = vec![0_isize; problem.n_items];
+    // the last item produced, and the one produced before switching to it
+    let (mut prev, mut before) = (IDLE, IDLE);
+    let mut periods = vec![];
+    for (t, value) in schedule.iter().copied().enumerate() {
+        let (v, units) = (problem.item_of(value), problem.units_of(value));
+        let mut changeover = 0;
+        if v == IDLE && !problem.keeps_setup() {
+            (prev, before) = (IDLE, IDLE);
+        } else if v != IDLE {
+            produced[v as usize] += units as isize;
+            if prev != IDLE {
+                changeover = problem.changeover_after(before, prev as usize, v as usize) as isize;
+            }
+            if prev != v {
+                before = prev;
+            }
+            prev = v;
+        }
+        let overtime = if units > 1 { problem.overtime.unwrap_or(0) as isize } else { 0 };
+        let idle = if v == IDLE && !problem.is_down(t) { problem.idle as isize } else { 0 };
+        // the units leave the stock at the earliest service period of their demands
+        let inventory = produced.iter().enumerate()
+            .map(|(i, p)| (p - problem.served_demand(i, t)).max(0))
+            .collect::<Vec<isize>>();
+        let stocking = inventory.iter().enumerate()
+            .map(|(i, inv)| problem.stocking[i] as isize * inv)
+            .sum::<isize>();
+        periods.push(PeriodCosts { item: v, changeover, overtime, idle, inventory, stocking });
+    }
+    periods
+}
+
+/// The stock level of every item at every period of a schedule, and the production that
+/// serves each demanded unit
+#[derive(Debug, Serialize)]
+pub struct Trajectory {
+    /// The inventory of each item at the end of each period, indexed by item then period
+    pub inventory: Vec<Vec<isize>>,
+    pub deliveries: Vec<Delivery>,
+}
+
+/// A demanded unit and the period at which it is produced
+#[derive(Debug, Serialize)]
+pub struct Delivery {
+    pub item: usize,
+    pub deadline: usize,
+    pub period: usize,
+}
+
+/// Returns the inventory trajectories of the given schedule. The units of an item are
+/// delivered in the order they are produced, which is how an optimal schedule stocks them
+pub fn trajectory(problem: &Psp, schedule: &[isize]) -> Trajectory {
+    let periods = breakdown(problem, schedule);
+    let inventory = (0..problem.n_items)
+        .map(|i| periods.iter().map(|p| p.inventory[i]).collect())
+        .collect();
+
+    let mut deliveries = vec![];
+    for i in 0..problem.n_items {
+        let produced = problem.productions(schedule, i);
+        deliveries.extend(problem.deadlines(i).zip(produced).map(|(deadline, period)| Delivery { item: i, deadline, period }));
+    }
+    deliveries.sort_by_key(|d| (d.deadline, d.item));
+
+    Trajectory { inventory, deliveries }
+}
+
+/// Returns the changeover, stocking, overtime and idle costs of the given schedule, in the
+/// units of the instance. The cost of a changeover is attributed to the item that is switched to
+pub fn decomposition(problem: &Psp, schedule: &[isize]) -> CostDecomposition {
+    let periods = breakdown(problem, schedule);
+    let mut items = vec![(0_isize, 0_isize); problem.n_items];
+    let overtime = periods.iter().map(|p| p.overtime).sum::<isize>();
+    let idle = periods.iter().map(|p| p.idle).sum::<isize>();
+    for period in periods.iter() {
+        if period.item != IDLE {
+            items[period.item as usize].0 += period.changeover;
+        }
+        for (i, inv) in period.inventory.iter().enumerate() {
+            items[i].1 += problem.stocking[i] as isize * inv;
+        }
+    }
+
+    CostDecomposition {
+        changeover: problem.unscale(items.iter().map(|c| c.0).sum()),
+        stocking: problem.unscale(items.iter().map(|c| c.1).sum()),
+        overtime: problem.overtime.map(|_| problem.unscale(overtime)),
+        idle: (problem.idle > 0).then(|| problem.unscale(idle)),
+        items: items.iter().map(|(changeover, stocking)| ItemCosts {
+            changeover: problem.unscale(*changeover),
+            stocking: problem.unscale(*stocking),
+        }).collect(),
+    }
+}
+
+/// Returns, for each period, the item produced, the changeover cost incurred to switch
+/// to it, the overtime surcharge when overtime is allowed, the idle cost when idling is
+/// penalized, the inventory of each item at the end of the period and the stocking cost
+/// of that inventory, in the csv format. The last line gives the totals
+pub fn breakdown_csv(problem: &Psp, schedule: &[isize]) -> String {
+    let mut out = String::from("period,item,changeover");
+    if problem.overtime.is_some() {
+        out.push_str(",overtime");
+    }
+    if problem.idle > 0 {
+        out.push_str(",idle");
+    }
+    for i in 0..problem.n_items {
+        write!(out, ",inventory_{i}").unwrap();
+    }
+    out.push_str(",stocking\n");
+
+    let periods = breakdown(problem, schedule);
+    for (t, period) in periods.iter().enumerate() {
+        let item = if period.item == IDLE { "idle".to_string() } else { period.item.to_string() };
+        write!(out, "{t},{item},{}", problem.format_cost(period.changeover)).unwrap();
+        if problem.overtime.is_some() {
+            write!(out, ",{}", problem.format_cost(period.overtime)).unwrap();
+        }
+        if problem.idle > 0 {
+            write!(out, ",{}", problem.format_cost(period.idle)).unwrap();
+        }
+        for inv in period.inventory.iter() {
+            write!(out, ",{inv}").unwrap();
+        }
+        writeln!(out, ",{}", problem.format_cost(period.stocking)).unwrap();
+    }
+
+    let total_changeover = periods.iter().map(|p| p.changeover).sum::<isize>();
+    let total_stocking = periods.iter().map(|p| p.stocking).sum::<isize>();
+    write!(out, "total,,{}", problem.format_cost(total_changeover)).unwrap();
+    if problem.overtime.is_some() {
+        write!(out, ",{}", problem.format_cost(periods.iter().map(|p| p.overtime).sum())).unwrap();
+    }
+    if problem.idle > 0 {
+        write!(out, ",{}", problem.format_cost(periods.iter().map(|p| p.idle).sum())).unwrap();
+    }
+    out.push_str(&",".repeat(problem.n_items));
+    writeln!(out, ",{}", problem.format_cost(total_stocking)).unwrap();
+    out
+}
+
+/// Returns the indicators of the given schedule. A campaign is interrupted by an idle
+/// period, and a setup is needed after an idle period when the setup is lost
+pub fn kpis(problem: &Psp, schedule: &[isize]) -> Kpis {
+    let periods = breakdown(problem, schedule);
+    let mut items = (0..problem.n_items)
+        .map(|i| ItemKpis {
+            produced: 0,
+            campaigns: 0,
+            longest_campaign: 0,
+            average_inventory: periods.iter().map(|p| p.inventory[i] as f64).sum::<f64>() / periods.len().max(1) as f64,
+            max_inventory: periods.iter().map(|p| p.inventory[i] as usize).max().unwrap_or(0),
+        })
+        .collect::<Vec<ItemKpis>>();
+
+    let (mut setups, mut setup, mut previous, mut length) = (0, IDLE, IDLE, 0);
+    for value in schedule.iter().copied() {
+        let v = problem.item_of(value);
+        if v == IDLE {
+            if !problem.keeps_setup() {
+                setup = IDLE;
+            }
+        } else {
+            let item = &mut items[v as usize];
+            item.produced += problem.units_of(value);
+            if v != setup {
+                setups += 1;
+                setup = v;
+            }
+            if v != previous {
+                item.campaigns += 1;
+                length = 0;
+            }
+            length += 1;
+            item.longest_campaign = item.longest_campaign.max(length);
+        }
+        previous = v;
+    }
+
+    let producing = schedule.iter().filter(|v| **v != IDLE).count();
+    Kpis { setups, utilization: producing as f64 / schedule.len().max(1) as f64, items }
+}
+
+/// Returns the indicators of a schedule as a table with a line per item
+pub fn kpis_table(kpis: &Kpis) -> String {
+    let mut out = format!("setups {}, utilization {:.2}%\n", kpis.setups, kpis.utilization * 100.0);
+    writeln!(out, "{:>6} {:>9} {:>10} {:>16} {:>14} {:>14}", "item", "produced", "campaigns", "longest campaign", "avg inventory", "max inventory").unwrap();
+    for (i, item) in kpis.items.iter().enumerate() {
+        writeln!(out, "{i:>6} {:>9} {:>10} {:>16} {:>14.2} {:>14}",
+            item.produced, item.campaigns, item.longest_campaign, item.average_inventory, item.max_inventory).unwrap();
+    }
+    out
+}
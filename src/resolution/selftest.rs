@@ -0,0 +1,108 @@
+//! This module implements the selftest subcommand: tiny random instances are solved with
+//! the DD solver and with the brute-force solver, which does not rely on the DP model, so
+//! that an error of the model or of its relaxation shows up as a difference between their
+//! optimal costs. The seed of each offending instance is reported so that it can be
+//! generated again.
+
+use clap::Args;
+use ddo::NoCutoff;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaChaRng;
+use tracing::{info, warn};
+
+use crate::error::PspError;
+use crate::generate::PspGenerator;
+use crate::resolution::exhaustive::{exhaustive, EXHAUSTIVE_MAX_PERIODS};
+use crate::resolution::model::Psp;
+use crate::resolution::solve::Solve;
+
+#[derive(Debug, Args)]
+pub struct Selftest {
+    /// The number of random instances that are solved
+    #[clap(long, default_value="100")]
+    pub iterations: u64,
+    /// The seed of the first instance, the next ones are generated with the next seeds
+    #[clap(long, default_value="0")]
+    pub seed: u64,
+    /// The maximum number of items of the instances
+    #[clap(long, default_value="4")]
+    pub max_items: usize,
+    /// The maximum number of periods of the instances
+    #[clap(long, default_value="8")]
+    pub max_periods: usize,
+    /// The options of the solve subcommand used for the DD solver, e.g. "-w 2 --lagrangian"
+    #[clap(long, default_value = "", allow_hyphen_values = true)]
+    pub options: String,
+}
+
+impl Selftest {
+    /// Solves the random instances and reports those on which the solvers disagree.
+    /// Returns the number of such instances
+    pub fn selftest(&self) -> Result<u64, PspError> {
+        if self.max_items == 0 || self.max_periods == 0 || self.max_periods > EXHAUSTIVE_MAX_PERIODS {
+            return Err(PspError::Invalid(format!("the instances must have at least one item and between 1 and {EXHAUSTIVE_MAX_PERIODS} periods")));
+        }
+        let mut args = vec!["selftest".to_string(), "-i".to_string(), "-".to_string()];
+        args.extend(self.options.split_whitespace().map(|a| a.to_string()));
+        let solve = Solve::from_args(args).map_err(|e| PspError::Invalid(format!("invalid solve options: {e}")))?;
+
+        let (mut mismatches, mut skipped) = (0, 0);
+        for seed in self.seed..self.seed + self.iterations {
+            let generate = self.generator_args(seed);
+            let Some(problem) = Self::generate(&generate)? else {
+                skipped += 1;
+                continue;
+            };
+
+            let (_, expected) = exhaustive(&problem, &NoCutoff);
+            let expected = expected.map(|(value, _)| -value);
+            let resolution = solve.solve_problem(&problem, None)?;
+            let found = resolution.incumbent.as_ref().map(|(value, _)| -value);
+            // the cost of the schedule found is recomputed without the model as well
+            let recomputed = resolution.incumbent.as_ref().and_then(|(_, solution)| problem.cost(&problem.schedule(solution)));
+
+            if !resolution.is_exact {
+                warn!(seed, "the DD solver did not prove optimality, the instance is skipped");
+                skipped += 1;
+            } else if found != expected || recomputed != found {
+                mismatches += 1;
+                let format = |cost: Option<isize>| cost.map_or("none".to_string(), |c| problem.format_cost(c));
+                println!("mismatch on seed {seed}: brute force {}, DD {} (schedule cost {})",
+                    format(expected), format(found), format(recomputed));
+                println!("  psp generate {}", generate[1..].join(" "));
+            }
+        }
+
+        info!(iterations = self.iterations, mismatches, skipped, "selftest done");
+        Ok(mismatches)
+    }
+
+    /// Returns the arguments of the generator for the instance of the given seed, whose
+    /// size is drawn from the same seed
+    fn generator_args(&self, seed: u64) -> Vec<String> {
+        let mut rng = ChaChaRng::seed_from_u64(seed);
+        let n_items = rng.gen_range(1..=self.max_items);
+        let n_periods = rng.gen_range(1..=self.max_periods);
+        let n_clusters = rng.gen_range(1..=n_items);
+        let max_lateness = rng.gen_range(0..=2);
+        let density = rng.gen_range(0.3..=1.0_f64);
+        [
+            "generate", "-s", &seed.to_string(), "-n", &n_items.to_string(), "-c", &n_clusters.to_string(),
+            "-p", &n_periods.to_string(), "-d", &format!("{density:.2}"), "--max-lateness", &max_lateness.to_string(),
+            "--max-stocking", "100", "--min-stocking", "10", "--stocking-std-dev", "10",
+            "--max-changeover-position", "100", "--min-changeover-position", "10", "--changeover-position-std-dev", "10",
+        ].iter().map(|a| a.to_string()).collect()
+    }
+
+    /// Generates the instance with the given arguments, or returns None if it has no
+    /// demand or cannot be modeled
+    fn generate(args: &[String]) -> Result<Option<Psp>, PspError> {
+        let mut generator = PspGenerator::from_args(args.iter().cloned())
+            .map_err(|e| PspError::Invalid(format!("invalid generator options: {e}")))?;
+        let instance = generator.instance()?;
+        if instance.demand_lists().iter().all(|d| d.is_empty()) {
+            return Ok(None);
+        }
+        Ok(Psp::try_from(instance).ok())
+    }
+}
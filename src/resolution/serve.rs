@@ -0,0 +1,189 @@
+//! This module implements the HTTP server mode: solve jobs are submitted as instances,
+//! they are solved in the background, and their progress and solution can be polled.
+//!
+//! - `POST /jobs?width=100&timeout=60` submits the instance in the body of the request, 
+//!   with the options of the solve subcommand in the query string, and returns the id of
+//!   the job. Only the options of `SOLVE_OPTIONS` are accepted: the options that read or
+//!   write files are rejected, since the server has no authentication
+//! - `GET /jobs/<id>` returns the status of the job (`running`, `failed`, or the status of
+//!   its resolution once it is done), the cost of its incumbent and its bound
+//! - `GET /jobs/<id>/solution` returns the best solution found, once the job is done
+
+use std::{io::Read, sync::{Arc, Mutex}, thread};
+
+use clap::Args;
+use serde_json::{json, Value};
+use tracing::info;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::error::PspError;
+use crate::instance::PspInstance;
+use crate::resolution::model::Psp;
+use crate::resolution::monitor::Monitor;
+use crate::resolution::report::{decomposition, kpis};
+use crate::resolution::solve::Solve;
+use crate::solution::{PspSolution, SolveStatus};
+
+/// The options of the solve subcommand that a job may set, none of which takes a path
+const SOLVE_OPTIONS: &[&str] = &[
+    "idle-setup", "width", "timeout", "deepening", "deepening-factor", "portfolio", "heuristic-only", "solver",
+    "local-search", "polish", "merge", "changeover-bound", "stocking-bound", "merge-selection", "merge-seed",
+    "no-cache", "cache-size", "no-preprocess", "lagrangian", "lagrangian-iterations", "root-bounds", "dominance",
+    "heuristic-lookahead", "warm-start", "neighborhood", "iterations", "temperature", "cooling", "min-temperature",
+    "seed", "num-solutions", "compression-bound", "solve-meta", "n-meta-items", "compression-method",
+    "compression-seeding", "compression-iterations", "compression-seed", "compression-restarts",
+    "compression-weights", "compression-aggregation", "compression-normalization", "demand-weight", "demand-bins",
+];
+
+#[derive(Debug, Args)]
+pub struct Serve {
+    /// The address on which the server listens. The server has no authentication, so
+    /// it only accepts local connections by default
+    #[clap(long, default_value="127.0.0.1")]
+    pub host: String,
+    /// The port on which the server listens
+    #[clap(short, long, default_value="8080")]
+    pub port: u16,
+}
+
+/// The outcome of a finished job
+struct Outcome {
+    is_exact: bool,
+    solution: Option<PspSolution>,
+    /// The reason why the job failed, if it did
+    error: Option<String>,
+}
+
+/// A solve job
+struct Job {
+    problem: Psp,
+    monitor: Arc<Monitor>,
+    outcome: Arc<Mutex<Option<Outcome>>>,
+}
+
+impl Serve {
+    pub fn serve(&self) -> Result<(), PspError> {
+        let server = Server::http((self.host.as_str(), self.port))
+            .map_err(|e| PspError::Invalid(format!("cannot listen on {}:{}: {e}", self.host, self.port)))?;
+        info!("listening on {}:{}", self.host, self.port);
+
+        let mut jobs: Vec<Job> = vec![];
+        for mut request in server.incoming_requests() {
+            let (status, body) = Self::handle(&mut request, &mut jobs);
+            let header = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap();
+            let response = Response::from_string(body.to_string()).with_status_code(status).with_header(header);
+            let _ = request.respond(response);
+        }
+        Ok(())
+    }
+
+    fn handle(request: &mut Request, jobs: &mut Vec<Job>) -> (u16, Value) {
+        let url = request.url().to_string();
+        let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+        let segments = path.split('/').filter(|s| !s.is_empty()).collect::<Vec<&str>>();
+
+        let method = request.method().clone();
+        match (&method, segments.as_slice()) {
+            (Method::Post, ["jobs"]) => {
+                let mut body = String::new();
+                if request.as_reader().read_to_string(&mut body).is_err() {
+                    return (400, json!({"error": "cannot read the request body"}));
+                }
+                match Self::submit(&body, query) {
+                    Ok(job) => {
+                        jobs.push(job);
+                        (201, json!({"id": jobs.len() - 1}))
+                    },
+                    Err(error) => (400, json!({"error": error})),
+                }
+            },
+            (Method::Get, ["jobs", id]) => match id.parse::<usize>().ok().and_then(|id| jobs.get(id)) {
+                Some(job) => (200, Self::status(job)),
+                None => (404, json!({"error": "no such job"})),
+            },
+            (Method::Get, ["jobs", id, "solution"]) => match id.parse::<usize>().ok().and_then(|id| jobs.get(id)) {
+                Some(job) => match job.outcome.lock().unwrap().as_ref() {
+                    Some(Outcome { solution: Some(solution), .. }) => (200, json!(solution)),
+                    Some(_) => (404, json!({"error": "no solution found"})),
+                    None => (409, json!({"error": "the job is still running"})),
+                },
+                None => (404, json!({"error": "no such job"})),
+            },
+            _ => (404, json!({"error": "unknown endpoint"})),
+        }
+    }
+
+    /// Starts solving the given instance with the options of the query string
+    fn submit(body: &str, query: &str) -> Result<Job, String> {
+        let instance: PspInstance = serde_json::from_str(body).map_err(|e| e.to_string())?;
+        let problem = Psp::try_from(instance).map_err(|e| e.to_string())?;
+
+        let mut args = vec!["serve".to_string(), "-i".to_string(), "-".to_string()];
+        for param in query.split('&').filter(|p| !p.is_empty()) {
+            let (key, value) = param.split_once('=').unwrap_or((param, ""));
+            let key = key.replace('_', "-");
+            if !SOLVE_OPTIONS.contains(&key.as_str()) {
+                return Err(format!("the option {key} is not accepted"));
+            }
+            if value.starts_with('-') {
+                return Err(format!("the value {value} of the option {key} is not accepted"));
+            }
+            args.push(format!("--{key}"));
+            if !value.is_empty() {
+                args.push(value.to_string());
+            }
+        }
+        let solve = Solve::from_args(args).map_err(|e| e.to_string())?;
+
+        let job = Job { problem, monitor: Arc::new(Monitor::new()), outcome: Arc::new(Mutex::new(None)) };
+        let (problem, monitor, outcome) = (job.problem.clone(), job.monitor.clone(), job.outcome.clone());
+        thread::spawn(move || {
+            let done = match solve.solve_problem(&problem, Some(&monitor)) {
+                Ok(resolution) => {
+                    let (status, bound, gap) = (resolution.status(), resolution.lower_bound(), resolution.gap());
+                    let solution = resolution.incumbent.map(|(value, solution)| {
+                        let schedule = problem.schedule(&solution);
+                        PspSolution {
+                            cost: problem.unscale(-value),
+                            decomposition: Some(decomposition(&problem, &schedule)),
+                            kpis: Some(kpis(&problem, &schedule)),
+                            schedule,
+                            status: Some(status),
+                            bound: bound.map(|b| problem.unscale(b)),
+                            gap,
+                            fingerprint: Some(problem.fingerprint.clone()),
+                        }
+                    });
+                    Outcome { is_exact: resolution.is_exact, solution, error: None }
+                },
+                Err(error) => Outcome { is_exact: false, solution: None, error: Some(error.to_string()) },
+            };
+            *outcome.lock().unwrap() = Some(done);
+        });
+
+        Ok(job)
+    }
+
+    fn status(job: &Job) -> Value {
+        let (incumbent, bound) = job.monitor.progress();
+        let cost = |v: Option<isize>| v.map(|v| job.problem.unscale(-v));
+        match job.outcome.lock().unwrap().as_ref() {
+            Some(Outcome { error: Some(error), .. }) => json!({
+                "status": "failed",
+                "error": error,
+            }),
+            Some(outcome) => json!({
+                "status": SolveStatus::new(outcome.is_exact, outcome.solution.is_some()),
+                "is_exact": outcome.is_exact,
+                "cost": outcome.solution.as_ref().map(|s| s.cost),
+                "bound": outcome.solution.as_ref().and_then(|s| s.bound),
+                "gap": outcome.solution.as_ref().and_then(|s| s.gap),
+            }),
+            None => json!({
+                "status": "running",
+                "cost": cost(incumbent),
+                "bound": cost(bound),
+            }),
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! This module implements the simulate subcommand, which evaluates the robustness of a
+//! schedule by Monte Carlo simulation. In each scenario, the demands of the instance are
+//! perturbed: each one may be dropped, or become due a few periods earlier or later. The
+//! schedule is then played as it is. The units of each item serve its demands in the
+//! order they are produced and due, the units produced after the due date of their
+//! demand are late, and the units without demand stay in stock until the end of the horizon.
+
+use clap::Args;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaChaRng;
+
+use crate::error::{PspError, read_json};
+use crate::instance::{PspInstance, SparseDemand};
+use crate::resolution::model::{Psp, IDLE};
+use crate::solution::PspSolution;
+
+#[derive(Debug, Args)]
+pub struct Simulate {
+    /// The path to the instance file (- for the standard input)
+    #[clap(short, long)]
+    pub instance: String,
+    /// The path to the solution file whose schedule is simulated
+    #[clap(short, long)]
+    pub solution: String,
+    /// The number of simulated scenarios
+    #[clap(long, default_value="1000")]
+    pub scenarios: usize,
+    /// The seed of the random perturbations
+    #[clap(long, default_value="0")]
+    pub seed: u64,
+    /// The probability that a demand becomes due at another period
+    #[clap(long, default_value="0.1")]
+    pub shift_probability: f64,
+    /// The maximum number of periods by which a demand is moved
+    #[clap(long, default_value="1")]
+    pub max_shift: usize,
+    /// The probability that a demand is dropped
+    #[clap(long, default_value="0")]
+    pub drop_probability: f64,
+}
+
+/// The outcome of the schedule in a scenario
+struct Outcome {
+    cost: isize,
+    /// The fraction of the demanded units that are served on time
+    service: f64,
+}
+
+impl Simulate {
+    /// Simulates the schedule in the random scenarios and reports the distributions of its
+    /// cost and of its service level
+    pub fn simulate(&self) -> Result<(), PspError> {
+        if !(0.0..=1.0).contains(&self.shift_probability) || !(0.0..=1.0).contains(&self.drop_probability) {
+            return Err(PspError::Invalid("the probabilities must be between 0 and 1".to_string()));
+        }
+        let instance: PspInstance = read_json(&self.instance)?;
+        let solution: PspSolution = read_json(&self.solution)?;
+        let problem = Psp::try_from(instance.clone())?;
+        if solution.schedule.len() != problem.horizon || solution.schedule.iter().any(|v| *v < IDLE || *v > problem.max_value()) {
+            return Err(PspError::Invalid(format!("{}: the schedule is not one of this instance", self.solution)));
+        }
+
+        let demands = instance.demand_lists();
+        let nominal = Self::play(&problem, &demands, &solution.schedule);
+        println!("nominal cost {}, service level {:.2}%", problem.format_cost(nominal.cost), nominal.service * 100.0);
+
+        let mut rng = ChaChaRng::seed_from_u64(self.seed);
+        let mut outcomes = (0..self.scenarios)
+            .map(|_| Self::play(&problem, &self.perturb(&demands, problem.horizon, &mut rng), &solution.schedule))
+            .collect::<Vec<Outcome>>();
+        if outcomes.is_empty() {
+            return Ok(());
+        }
+
+        let n = outcomes.len() as f64;
+        let mean = outcomes.iter().map(|o| o.cost as f64).sum::<f64>() / n;
+        let std_dev = (outcomes.iter().map(|o| (o.cost as f64 - mean).powi(2)).sum::<f64>() / n).sqrt();
+        outcomes.sort_by_key(|o| o.cost);
+        println!("scenarios {}", outcomes.len());
+        println!("cost mean {}, std dev {}, min {}, p5 {}, median {}, p95 {}, max {}",
+            problem.format_cost(mean.round() as isize), problem.format_cost(std_dev.round() as isize),
+            problem.format_cost(quantile(&outcomes, 0.0).cost), problem.format_cost(quantile(&outcomes, 0.05).cost),
+            problem.format_cost(quantile(&outcomes, 0.5).cost), problem.format_cost(quantile(&outcomes, 0.95).cost),
+            problem.format_cost(quantile(&outcomes, 1.0).cost));
+
+        outcomes.sort_by(|a, b| a.service.total_cmp(&b.service));
+        let on_time = outcomes.iter().filter(|o| o.service >= 1.0).count() as f64 / n;
+        println!("service level mean {:.2}%, min {:.2}%, p5 {:.2}%, median {:.2}%",
+            outcomes.iter().map(|o| o.service).sum::<f64>() / n * 100.0,
+            quantile(&outcomes, 0.0).service * 100.0, quantile(&outcomes, 0.05).service * 100.0, quantile(&outcomes, 0.5).service * 100.0);
+        println!("scenarios fully served on time {:.2}%", on_time * 100.0);
+        Ok(())
+    }
+
+    /// Returns the demands of a random scenario
+    fn perturb(&self, demands: &[Vec<SparseDemand>], horizon: usize, rng: &mut ChaChaRng) -> Vec<Vec<SparseDemand>> {
+        let max_shift = self.max_shift as isize;
+        demands.iter()
+            .map(|demands| demands.iter()
+                .filter(|_| !rng.gen_bool(self.drop_probability))
+                .map(|d| {
+                    if max_shift == 0 || !rng.gen_bool(self.shift_probability) {
+                        return *d;
+                    }
+                    let mut shift = rng.gen_range(-max_shift..max_shift);
+                    if shift >= 0 {
+                        shift += 1;
+                    }
+                    let period = d.period.saturating_add_signed(shift).min(horizon - 1);
+                    let earliest = d.earliest.map(|e| e.saturating_add_signed(shift).min(period));
+                    SparseDemand { period, quantity: d.quantity, earliest }
+                })
+                .collect())
+            .collect()
+    }
+
+    /// Plays the schedule against the given demands
+    fn play(problem: &Psp, demands: &[Vec<SparseDemand>], schedule: &[isize]) -> Outcome {
+        let mut cost = problem.changeover_cost(schedule) + problem.overtime_cost(schedule) + problem.idle_cost(schedule);
+        let (mut demanded, mut served) = (0, 0);
+        for (item, demands) in demands.iter().enumerate() {
+            // the (latest, earliest) service periods of the demanded units, by due date
+            let mut units = demands.iter()
+                .flat_map(|d| (0..d.quantity).map(|_| (d.period, d.earliest.unwrap_or(d.period))))
+                .collect::<Vec<(usize, usize)>>();
+            units.sort_unstable();
+            let productions = problem.productions(schedule, item).collect::<Vec<usize>>();
+
+            let stocking = problem.stocking[item] as isize;
+            for (k, produced) in productions.iter().copied().enumerate() {
+                match units.get(k) {
+                    Some((latest, earliest)) if produced <= *latest => {
+                        served += 1;
+                        cost += stocking * earliest.saturating_sub(produced) as isize;
+                    },
+                    Some(_) => {},
+                    None => cost += stocking * (schedule.len() - produced) as isize,
+                }
+            }
+            demanded += units.len();
+        }
+
+        let service = if demanded == 0 { 1.0 } else { served as f64 / demanded as f64 };
+        Outcome { cost, service }
+    }
+}
+
+/// Returns the element at the given quantile of a non-empty sorted slice
+fn quantile<T>(sorted: &[T], q: f64) -> &T {
+    &sorted[((sorted.len() - 1) as f64 * q).round() as usize]
+}
@@ -1,16 +1,83 @@
-use std::{fs::File, io::BufReader, time::Duration};
+use std::{time::{Duration, Instant}, sync::{atomic::{AtomicBool, AtomicIsize, Ordering}, Arc, Mutex}, thread};
 
-use clap::Args;
-use ddo::{FixedWidth, TimeBudget, NoDupFringe, MaxUB, ParBarrierSolverFc, Completion, Solver};
+use clap::{Args, Parser, ValueEnum};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaChaRng;
+use tracing::{debug, info, info_span, warn};
+use ddo::{Problem, Relaxation, FixedWidth, TimeBudget, Completion, Solver, Solution, Cutoff, Fringe, SubProblem};
+#[cfg(not(feature = "sequential"))]
+use ddo::{ParBarrierSolverFc, ParBarrierSolverLel};
+#[cfg(feature = "sequential")]
+use ddo::{SeqCachingSolverFc, SeqCachingSolverLel};
 
-use crate::resolution::model::{Psp, PspRelax, PspRanking};
-use crate::instance::PspInstance;
+use crate::resolution::lagrangian::LagrangianBound;
+use crate::resolution::compression::{CompressionArgs, CompressedSolutionBound, PspCompression};
+use crate::resolution::heuristic::{restricted_dd, compile_restricted, lookahead_greedy};
+use crate::resolution::local_search::local_search;
+use crate::resolution::annealing::{simulated_annealing, Temperature};
+use crate::resolution::exhaustive::{exhaustive, EXHAUSTIVE_MAX_PERIODS};
+use crate::resolution::dp::dynamic_programming;
+#[cfg(feature = "mip")]
+use crate::resolution::mip::solve_mip;
+#[cfg(feature = "lp")]
+use crate::resolution::mip::lp_bound;
+use crate::resolution::relaxed::{relaxed_dd, compile_relaxed, SelectionArgs};
+use crate::resolution::viz::VizConfig;
+use crate::resolution::gantt::gantt_svg;
+use crate::resolution::report::{breakdown_csv, decomposition, kpis, kpis_table, trajectory};
+#[cfg(feature = "xlsx")]
+use crate::resolution::xlsx::write_xlsx;
+use crate::resolution::monitor::{Monitor, MonitoredFringe, BoxedFringe};
+use crate::resolution::dominance::DominanceFringe;
+use crate::resolution::cache::CachedExpansions;
+use crate::resolution::arena::ArenaFringe;
+use crate::resolution::hashing::StateMap;
+use crate::resolution::preprocess::Preprocessed;
+use crate::resolution::model::{Psp, PspState, PspRelax, PspRanking, MergeStrategy, ChangeoverBound, StockingBound, MAX_FORBIDDEN};
+use crate::error::{PspError, read_json, write_file, write_json, STDIO};
+use crate::instance::IdleSetup;
+use crate::solution::{Assignment, PspSolution, SolveStatus};
+use crate::constraints::SideConstraints;
+
+/// The resolution method
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SolverType {
+    /// branch-and-bound with decision diagrams
+    Ddo,
+    /// large neighborhood search re-optimizing windows of periods with decision diagrams
+    Lns,
+    /// backward just-in-time greedy construction
+    Greedy,
+    /// greedy construction followed by local search
+    Localsearch,
+    /// greedy construction followed by simulated annealing
+    Sa,
+    /// enumeration of all the schedules, for instances of at most 12 periods
+    Exhaustive,
+    /// dynamic programming over all the states of the model, without width limit
+    Dp,
+    /// time-indexed MIP formulation solved with HiGHS
+    #[cfg(feature = "mip")]
+    Mip,
+}
 
 #[derive(Debug, Args)]
 pub struct Solve {
-    /// The path to the instance file
+    /// The path to the instance file (- for the standard input)
     #[clap(short, long)]
     pub instance: String,
+    /// Whether the setup is kept through idle periods, overriding the instance
+    #[clap(long, value_enum)]
+    pub idle_setup: Option<IdleSetup>,
+    /// If present, the path to a json file listing the decisions of the schedule that are
+    /// already committed, e.g. [{"period": 0, "item": 2}, {"period": 1, "item": -1}]:
+    /// only the other periods are optimized
+    #[clap(long)]
+    pub fix: Option<String>,
+    /// If present, the path to a json file of side constraints that ban the production
+    /// of some items and some transitions at some periods
+    #[clap(long)]
+    pub constraints: Option<String>,
     /// max number of nodes in a layeer
     #[clap(short, long, default_value="100")]
     pub width: usize,
@@ -20,44 +87,1272 @@ pub struct Solve {
     /// If present, the path where to write the output html
     #[clap(short, long)]
     pub output: Option<String>,
+    /// If present, the width of the solver is increased while optimality is not proved
+    #[clap(long)]
+    pub deepening: bool,
+    /// The factor by which the width is multiplied at each restart, at least 2
+    #[clap(long, default_value="2", value_parser=clap::builder::RangedU64ValueParser::<usize>::new().range(2..))]
+    pub deepening_factor: usize,
+    /// The number of subproblems explored with each width of the deepening. The open
+    /// subproblems are carried to the next width
+    #[clap(long, default_value="1000", value_parser=clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub deepening_nodes: usize,
+    /// If present, several solver configurations are run concurrently until one of them proves optimality
+    #[clap(long, conflicts_with="deepening")]
+    pub portfolio: bool,
+    /// If present, only restricted DDs of increasing width are compiled to find good solutions quickly
+    #[clap(long, conflicts_with_all=["deepening", "portfolio"])]
+    pub heuristic_only: bool,
+    /// The resolution method
+    #[clap(long, value_enum, default_value_t=SolverType::Ddo)]
+    pub solver: SolverType,
+    /// If present, the solutions found are improved by local search before being reported
+    #[clap(long)]
+    pub local_search: bool,
+    /// If present, the solutions found are polished: the windows of the given number of
+    /// consecutive periods are re-optimized exactly in turn, with the rest of the
+    /// schedule fixed, until none of them improves
+    #[clap(long)]
+    pub polish: Option<usize>,
+    /// The way the states are merged in the relaxed DDs
+    #[clap(long, value_enum, default_value_t=MergeStrategy::Min)]
+    pub merge: MergeStrategy,
+    /// The lower bound on the changeover costs used by the rough upper bound
+    #[clap(long, value_enum, default_value_t=ChangeoverBound::Max)]
+    pub changeover_bound: ChangeoverBound,
+    /// The lower bound on the stocking costs used by the rough upper bound
+    #[clap(long, value_enum, default_value_t=StockingBound::Greedy)]
+    pub stocking_bound: StockingBound,
+    #[clap(flatten)]
+    pub selection: SelectionArgs,
+    /// If present, the rough upper bounds are recomputed each time a state is expanded,
+    /// instead of being cached across the compilations of the resolution
+    #[clap(long)]
+    pub no_cache: bool,
+    /// If present, the successors of the states are computed each time a state is expanded,
+    /// instead of being reused by the compilations of a run that share a prefix of their layers
+    #[clap(long)]
+    pub no_layer_cache: bool,
+    /// The maximum number of states whose rough upper bound, and whose successors, are cached
+    #[clap(long, default_value="1000000")]
+    pub cache_size: usize,
+    /// If present, the items that are never demanded are kept in the model, the identical
+    /// items are not merged and the idle periods at both ends of the horizon are kept
+    #[clap(long)]
+    pub no_preprocess: bool,
+    /// If present, the rough upper bound is strengthened with a Lagrangian bound on the stocking costs
+    #[clap(long)]
+    pub lagrangian: bool,
+    /// The number of subgradient iterations of the Lagrangian bound
+    #[clap(long, default_value="100")]
+    pub lagrangian_iterations: usize,
+    /// If present, the bounds obtained at the root by a relaxed DD (and by the LP relaxation 
+    /// when the lp feature is enabled) are reported before solving. The root bounds with and
+    /// without the compression are always reported with the compression bound
+    #[clap(long)]
+    pub root_bounds: bool,
+    /// If present, the subproblems dominated by another one are discarded (this requires
+    /// changeover costs that satisfy the triangle inequality)
+    #[clap(long)]
+    pub dominance: bool,
+    /// The number of decisions evaluated ahead of each decision of the greedy heuristic,
+    /// with their real transition costs and the rough upper bound of the state reached
+    #[clap(long, default_value="0")]
+    pub heuristic_lookahead: usize,
+    /// If present, the greedy solution is given to the DD solver as initial incumbent
+    #[clap(long)]
+    pub warm_start: bool,
+    /// The number of consecutive periods that are re-optimized at each LNS iteration
+    #[clap(long, default_value="10")]
+    pub neighborhood: usize,
+    /// The maximum number of LNS iterations
+    #[clap(long, default_value="100")]
+    pub iterations: usize,
+    /// The initial temperature of the simulated annealing
+    #[clap(long, default_value="1000")]
+    pub temperature: f64,
+    /// The factor by which the temperature is multiplied after each simulated annealing move
+    #[clap(long, default_value="0.99999")]
+    pub cooling: f64,
+    /// The temperature at which the simulated annealing stops
+    #[clap(long, default_value="0.01")]
+    pub min_temperature: f64,
+    /// The seed of the random number generator used by the randomized methods
+    #[clap(long, default_value="0")]
+    pub seed: u64,
+    /// The number of distinct solutions to enumerate, by increasing cost. At most 65, as each
+    /// solution is forbidden once it is found and the states track at most 64 of them
+    #[clap(short='k', long, default_value="1", value_parser=clap::builder::RangedU64ValueParser::<usize>::new().range(1..=MAX_FORBIDDEN as u64 + 1))]
+    pub num_solutions: usize,
+    /// If present, the rough upper bound is strengthened with the bound of a compressed problem
+    #[clap(long)]
+    pub compression_bound: bool,
+    /// If present, the meta-problem of each level of compression is solved before the
+    /// problem, and its optimal cost is compared to the costs of the problem
+    #[clap(long)]
+    pub solve_meta: bool,
+    #[clap(flatten)]
+    pub compression: CompressionArgs,
+    /// If present, a live dashboard of the search is shown, and the search is stopped
+    /// gracefully when the enter key is pressed
+    #[clap(long)]
+    pub tui: bool,
+    /// If present, the path where to write the lower and upper bounds on the cost each time
+    /// one of them changes, with the elapsed time, in the csv format
+    #[clap(long)]
+    pub trace: Option<String>,
+    /// If present, the path where to write the events of the resolution (start, compression
+    /// built, DD search done, incumbent improved, cutoff reached, end) in the json lines format
+    #[clap(long)]
+    pub events: Option<String>,
+    /// If present, the path where to write the best solution, in the json format
+    #[clap(long)]
+    pub solution: Option<String>,
+    /// If present, the cost of each solution is detailed period by period, in the csv format.
+    /// The table is printed, or written to the given path (with the solution number when
+    /// several solutions are enumerated)
+    #[clap(long)]
+    pub breakdown: Option<Option<String>>,
+    /// If present, the path where to write the Gantt chart of the best solution, in the svg format
+    #[clap(long)]
+    pub gantt: Option<String>,
+    /// If present, the path where to write the inventory of each item at each period of the
+    /// best solution and the period at which each demand is produced, in the json format
+    #[clap(long)]
+    pub inventory: Option<String>,
+    /// If present, the path where to write the best solution and its inventories, in the xlsx format
+    #[cfg(feature = "xlsx")]
+    #[clap(long)]
+    pub xlsx: Option<String>,
+    /// If present, the path where to write the DD compiled at the root, in the graphviz dot format
+    #[clap(long)]
+    pub dot: Option<String>,
+    /// If present, the path where to write the statistics of each layer of the DD compiled 
+    /// at the root, in the csv format
+    #[clap(long)]
+    pub layer_stats: Option<String>,
+    /// The DD that is drawn or described, compiled with the given width
+    #[clap(long, value_enum, default_value_t=VizMode::Relaxed)]
+    pub viz: VizMode,
+    #[clap(flatten)]
+    pub viz_config: VizConfig,
+}
+
+/// The decision diagram that is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum VizMode {
+    /// the relaxed DD compiled at the root
+    Relaxed,
+    /// the restricted DD compiled at the root
+    Restricted,
+}
+
+/// The cutset used by the solver when compiling relaxed DDs
+#[derive(Debug, Clone, Copy)]
+enum Cutset {
+    /// frontier cutset
+    Frontier,
+    /// last exact layer
+    LastExactLayer,
+}
+
+/// The configuration of one single solver run
+#[derive(Debug, Clone, Copy)]
+struct Config {
+    cutset: Cutset,
+    width: usize,
+    dominance: bool,
+    /// The maximum number of states whose successors are reused by the compilations of
+    /// the run, if they are cached
+    layer_cache: Option<usize>,
+}
+
+/// A cutoff that stops the solver when the time budget is exhausted, when
+/// another solver of the portfolio has proved optimality, or when the user asks for it
+/// on the dashboard
+struct SharedCutoff<'a> {
+    budget: &'a TimeBudget,
+    stop: &'a AtomicBool,
+    monitor: Option<&'a Monitor>,
+    /// True iff the solver runs on the full problem, whose bounds are reported to the
+    /// monitor, and not on a subproblem with fixed decisions
+    full: bool,
+    /// The value of the best solution found by the configurations of the portfolio, if
+    /// they share it
+    best: Option<&'a AtomicIsize>,
+}
+
+impl Cutoff for SharedCutoff<'_> {
+    fn must_stop(&self) -> bool {
+        self.stop.load(Ordering::Relaxed) || self.budget.must_stop() || self.monitor.map_or(false, |m| m.must_stop())
+    }
+}
+
+impl SharedCutoff<'_> {
+    /// Logs the given improved incumbent and reports it to the dashboard, if any
+    fn report(&self, problem: &Psp, incumbent: &Incumbent) {
+        if let Some((value, solution)) = incumbent.as_ref() {
+            debug!(cost = %problem.format_cost(-value), "incumbent");
+            info!(target: EVENTS, kind = "incumbent", cost = problem.unscale(-value));
+            if let Some(monitor) = self.monitor {
+                monitor.report_incumbent(problem, *value, solution);
+            }
+        }
+    }
+}
+
+type Incumbent = Option<(isize, Solution)>;
+
+/// A fringe that discards the subproblems that cannot improve the best solution shared by
+/// the configurations of the portfolio, which the solver of each configuration only knows 
+/// from its own solutions
+struct SharedBoundFringe<'a> {
+    inner: BoxedFringe<'a>,
+    best: &'a AtomicIsize,
+}
+
+impl Fringe for SharedBoundFringe<'_> {
+    type State = PspState;
+
+    fn push(&mut self, node: SubProblem<Self::State>) {
+        self.inner.push(node);
+    }
+
+    fn pop(&mut self) -> Option<SubProblem<Self::State>> {
+        let best = self.best.load(Ordering::Relaxed);
+        while let Some(node) = self.inner.pop() {
+            if node.ub > best {
+                return Some(node);
+            }
+        }
+        None
+    }
+
+    fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+}
+
+/// The open subproblems of the deepening, carried from the solver of a width to the
+/// solver of the next one
+struct Carried {
+    fringe: ArenaFringe,
+    /// The best value with which each state was explored, like in the barrier of ddo: a
+    /// subproblem of the same state with no better value cannot improve its completions
+    explored: StateMap<Arc<PspState>, isize>,
+    /// The number of subproblems explored with each width
+    nodes_per_round: usize,
+}
+
+impl Carried {
+    fn new(nodes_per_round: usize) -> Self {
+        Carried { fringe: ArenaFringe::default(), explored: StateMap::default(), nodes_per_round }
+    }
+
+    fn is_explored(&self, node: &SubProblem<PspState>) -> bool {
+        self.explored.get(node.state.as_ref()).map_or(false, |value| *value >= node.value)
+    }
+}
+
+/// A fringe that hands the carried subproblems to the solver of one width of the deepening,
+/// until it has explored its share of them. It then looks empty to the solver, which stops,
+/// while the remaining subproblems are kept for the next width. They are not cleared by the
+/// solver either
+struct RoundFringe<'a> {
+    carried: &'a mut Carried,
+    /// The number of subproblems that may still be handed to the solver
+    remaining: usize,
+}
+
+impl<'a> RoundFringe<'a> {
+    fn new(carried: &'a mut Carried) -> Self {
+        let remaining = carried.nodes_per_round;
+        RoundFringe { carried, remaining }
+    }
+}
+
+impl Fringe for RoundFringe<'_> {
+    type State = PspState;
+
+    fn push(&mut self, node: SubProblem<Self::State>) {
+        if !self.carried.is_explored(&node) {
+            self.carried.fringe.push(node);
+        }
+    }
+
+    fn pop(&mut self) -> Option<SubProblem<Self::State>> {
+        while self.remaining > 0 {
+            let node = self.carried.fringe.pop()?;
+            if !self.carried.is_explored(&node) {
+                self.remaining -= 1;
+                self.carried.explored.insert(node.state.clone(), node.value);
+                return Some(node);
+            }
+        }
+        None
+    }
+
+    fn clear(&mut self) {}
+
+    fn len(&self) -> usize {
+        if self.remaining > 0 { self.carried.fringe.len() } else { 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Prints a line of the report of the resolution, to stderr when some machine output is
+/// written to stdout so that it can be piped
+macro_rules! report {
+    ($solve:expr, $($arg:tt)*) => {
+        if $solve.writes_to_stdout() { eprintln!($($arg)*) } else { println!($($arg)*) }
+    };
+}
+
+/// The target of the tracing events that are written to the event log
+pub const EVENTS: &str = "events";
+
+/// The outcome of the resolution of a problem, which need not be complete
+pub struct Resolution {
+    /// True iff the incumbent is proved optimal, or the problem infeasible if there is none
+    pub is_exact: bool,
+    /// The value and the decisions of the best solution found
+    pub incumbent: Incumbent,
+    /// An upper bound on the value of any solution (isize::MAX when no bound is known)
+    pub bound: isize,
+}
+
+impl Resolution {
+    /// The outcome of a method that proves no bound
+    fn heuristic(incumbent: Incumbent) -> Self {
+        Resolution { is_exact: false, incumbent, bound: isize::MAX }
+    }
+
+    pub fn status(&self) -> SolveStatus {
+        SolveStatus::new(self.is_exact, self.incumbent.is_some())
+    }
+
+    /// Returns the (scaled) lower bound on the cost of any solution, if one is known
+    /// and the problem is not proved infeasible
+    pub fn lower_bound(&self) -> Option<isize> {
+        (self.bound != isize::MAX && self.bound != isize::MIN).then(|| -self.bound)
+    }
+
+    /// Returns the relative gap between the cost of the incumbent and the lower bound on 
+    /// the cost, if both are known
+    pub fn gap(&self) -> Option<f64> {
+        let (value, _) = self.incumbent.as_ref()?;
+        let (cost, lower) = (-value, self.lower_bound()?);
+        Some(if cost == 0 { 0.0 } else { (cost - lower).max(0) as f64 / cost as f64 })
+    }
+}
+
+/// Returns the number of threads of each solver
+fn nb_threads() -> usize {
+    if cfg!(feature = "sequential") {
+        1
+    } else {
+        thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+    }
+}
+
+/// Parses the options of the solve subcommand from a list of arguments
+#[derive(Debug, Parser)]
+struct SolveParser {
+    #[clap(flatten)]
+    solve: Solve,
 }
 
 impl Solve {
-    pub fn solve(&self) {
-        let instance: PspInstance = serde_json::from_reader(BufReader::new(File::open(&self.instance).unwrap())).unwrap();
-
-        let prev_demands = Psp::compute_prev_demands(&instance.demands);
-        let rem_demands = Psp::compute_rem_demands(&instance.demands);
-        
-        let problem = Psp {
-            n_items: instance.nb_types,
-            horizon: instance.nb_periods,
-            stocking: instance.stocking,
-            changeover: instance.changeover,
-            demands: instance.demands,
-            prev_demands,
-            rem_demands,
-        };
-        let relaxation = PspRelax::new(problem.clone());
-
-        let width = FixedWidth(self.width);
+    /// Solves the instance and reports the solutions. Returns the status of the 
+    /// resolution of the first solution
+    pub fn solve(&self) -> Result<SolveStatus, PspError> {
+        let original = self.load()?;
+        let preprocessed = self.preprocess(&original);
+        let mut problem = preprocessed.problem.clone();
+        info!(target: EVENTS, kind = "start", instance = %self.instance, fingerprint = %original.fingerprint, config = ?self);
+        self.check_solver(&problem)?;
+        let relaxation = self.relaxation(&problem)?;
+
+        if self.root_bounds || self.compression_bound {
+            self.report_root_bounds(&problem, &relaxation);
+        }
+        if self.dot.is_some() || self.layer_stats.is_some() {
+            self.write_diagram(&problem, &relaxation)?;
+        }
+
+        let monitor = (self.tui || self.trace.is_some()).then(|| match self.trace {
+            Some(_) => Arc::new(Monitor::new().with_trace()),
+            None => Arc::new(Monitor::new()),
+        });
+        if let (true, Some(monitor)) = (self.tui, monitor.as_ref()) {
+            monitor.start(problem.clone());
+        }
+
+        let start = Instant::now();
+        let budget = Duration::from_secs(self.timeout);
+        let meta = if self.solve_meta { self.solve_meta(&problem, budget)? } else { vec![] };
+
+        let mut status = None;
+        for k in 0..self.num_solutions {
+            let remaining = budget.saturating_sub(start.elapsed());
+            let mut resolution = self.solve_with(&problem, &relaxation, remaining, monitor.as_deref());
+
+            if !resolution.is_exact && (self.local_search || self.solver == SolverType::Localsearch) {
+                let cutoff = TimeBudget::new(budget.saturating_sub(start.elapsed()));
+                resolution.incumbent = resolution.incumbent.map(|(value, solution)| local_search(&problem, value, &solution, &cutoff));
+            }
+            if let (false, Some(window)) = (resolution.is_exact, self.polish) {
+                let remaining = budget.saturating_sub(start.elapsed());
+                resolution.incumbent = self.polish(&problem, &relaxation, resolution.incumbent, window, remaining, monitor.as_deref());
+            }
+            if let Some(monitor) = monitor.as_ref() {
+                if let Some((value, solution)) = resolution.incumbent.as_ref() {
+                    monitor.report_incumbent(&problem, *value, solution);
+                }
+                if resolution.lower_bound().is_some() {
+                    monitor.report_bound(resolution.bound);
+                }
+            }
+
+            if self.num_solutions > 1 {
+                report!(self, "solution #{}", k + 1);
+            }
+
+            let current = resolution.status();
+            let first = *status.get_or_insert(current);
+            info!(target: EVENTS, kind = "end", solution = k + 1, status = current.name(),
+                cost = resolution.incumbent.as_ref().map(|(value, _)| problem.unscale(-value)),
+                bound = resolution.lower_bound().map(|bound| problem.unscale(bound)),
+                gap = resolution.gap(), time = start.elapsed().as_secs_f64());
+            report!(self, "is exact {}", resolution.is_exact);
+            report!(self, "status {}", current.name());
+            if let Some(bound) = resolution.lower_bound() {
+                report!(self, "best bound {}", problem.format_cost(bound));
+            }
+            if let Some(gap) = resolution.gap() {
+                report!(self, "gap {:.2}%", gap * 100.0);
+            }
+            let Some((value, solution)) = resolution.incumbent.clone() else {
+                report!(self, "no solution found");
+                break;
+            };
+
+            // the solution is reported with the items of the instance
+            let reduced = problem.schedule(&solution);
+            let solution = preprocessed.restore(&original, &solution);
+            report!(self, "best value {}", problem.format_cost(-value));
+            if k == 0 {
+                self.report_meta_loss(&problem, &meta, -value);
+            }
+
+            let mut sol = String::new();
+            solution
+                .iter().map(|d| d.value)
+                .for_each(|v| sol.push_str(&format!("{v} ")));
+
+            report!(self, "solution: {sol}");
+
+            let schedule = original.schedule(&solution);
+            let costs = decomposition(&original, &schedule);
+            report!(self, "changeover cost {}", costs.changeover);
+            report!(self, "stocking cost {}", costs.stocking);
+            if let Some(overtime) = costs.overtime {
+                report!(self, "overtime cost {overtime}");
+            }
+            if let Some(idle) = costs.idle {
+                report!(self, "idle cost {idle}");
+            }
+            for (i, item) in costs.items.iter().enumerate() {
+                report!(self, "item {i}: changeover {}, stocking {}", item.changeover, item.stocking);
+            }
+            report!(self, "{}", kpis_table(&kpis(&original, &schedule)).trim_end());
+
+            if let Some(path) = self.breakdown.as_ref() {
+                let table = breakdown_csv(&original, &schedule);
+                match path {
+                    Some(path) if self.num_solutions > 1 && path != STDIO => write_file(&format!("{path}.{}", k + 1), table)?,
+                    Some(path) => write_file(path, table)?,
+                    None => print!("{table}"),
+                }
+            }
+            if let (0, Some(path)) = (k, self.solution.as_ref()) {
+                self.write_solution(&original, &solution, first, &resolution, path)?;
+            }
+            if let (0, Some(path)) = (k, self.gantt.as_ref()) {
+                write_file(path, gantt_svg(&original, &schedule))?;
+            }
+            if let (0, Some(path)) = (k, self.inventory.as_ref()) {
+                write_json(path, &trajectory(&original, &schedule))?;
+            }
+            #[cfg(feature = "xlsx")]
+            if let (0, Some(path)) = (k, self.xlsx.as_ref()) {
+                write_xlsx(&original, &schedule, path)?;
+            }
+
+            problem.forbidden.push(reduced);
+            problem.clear_symmetries();
+        }
+
+        relaxation.save_compressions()?;
+        if let Some(monitor) = monitor.as_ref() {
+            monitor.finish();
+            if let Some(path) = self.trace.as_ref() {
+                write_file(path, monitor.trace_csv(&problem))?;
+            }
+        }
+        Ok(status.unwrap_or(SolveStatus::Unknown))
+    }
+
+    /// Returns true iff one of the output files is the standard output
+    fn writes_to_stdout(&self) -> bool {
+        [&self.solution, &self.gantt, &self.inventory, &self.dot, &self.layer_stats, &self.trace, &self.events].iter()
+            .any(|path| path.as_deref() == Some(STDIO))
+            || self.breakdown.as_ref().map_or(false, |path| path.as_deref().map_or(true, |p| p == STDIO))
+    }
+
+    /// Reads the instance and builds its model
+    fn load(&self) -> Result<Psp, PspError> {
+        let _span = info_span!("load", instance = %self.instance).entered();
+        let mut problem = Psp::from_file(&self.instance)?;
+        if let Some(idle_setup) = self.idle_setup {
+            problem.idle_setup = idle_setup;
+        }
+        if let Some(path) = self.constraints.as_ref() {
+            let constraints: SideConstraints = read_json(path)?;
+            problem.constrain(&constraints)?;
+            info!(productions = constraints.productions.len(), transitions = constraints.transitions.len(), "side constraints read");
+        }
+        if let Some(path) = self.fix.as_ref() {
+            let assignments: Vec<Assignment> = read_json(path)?;
+            problem.fix(&assignments)?;
+            info!(fixed = assignments.len(), "decisions fixed");
+        }
+        info!(items = problem.n_items, horizon = problem.horizon, "instance loaded");
+        Ok(problem)
+    }
+
+    /// Removes the items without demand and the idle periods from the problem and merges
+    /// its identical items, unless preprocessing is disabled
+    /// or a compression of the original items is loaded
+    fn preprocess(&self, problem: &Psp) -> Preprocessed {
+        if self.no_preprocess || self.compression.load_compression.is_some() {
+            Preprocessed::identity(problem)
+        } else {
+            Preprocessed::new(problem)
+        }
+    }
+
+    /// Checks that the selected method supports the problem
+    fn check_solver(&self, problem: &Psp) -> Result<(), PspError> {
+        if cfg!(feature = "sequential") && self.tui {
+            return Err(PspError::Invalid("the dashboard needs threads and is not available in the sequential builds".to_string()));
+        }
+        // the dashboard waits for the enter key on the standard input
+        if self.tui && [Some(&self.instance), self.fix.as_ref(), self.constraints.as_ref()].into_iter().flatten().any(|path| path == STDIO) {
+            return Err(PspError::Invalid("the dashboard reads the standard input, which cannot provide the inputs".to_string()));
+        }
+        if self.solver == SolverType::Exhaustive && problem.horizon > EXHAUSTIVE_MAX_PERIODS {
+            return Err(PspError::Invalid(format!("the exhaustive solver is limited to {EXHAUSTIVE_MAX_PERIODS} periods")));
+        }
+        #[cfg(feature = "mip")]
+        if self.solver == SolverType::Mip && (problem.windows || !problem.precedences.is_empty() || problem.has_side_constraints() || problem.has_downtime()
+            || problem.overtime.is_some() || !problem.second_order.is_empty()) {
+            return Err(PspError::Invalid("the MIP formulation does not support service windows, precedences, side constraints, downtime, overtime nor second-order changeovers".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Builds the relaxation of the problem, with the requested bounds
+    pub fn relaxation(&self, problem: &Psp) -> Result<PspRelax, PspError> {
+        if self.lagrangian && (problem.windows || problem.overtime.is_some()) {
+            warn!("the Lagrangian bound does not support service windows nor overtime and is disabled");
+        }
+        let mut relaxation = self.plain_relaxation(problem);
+        if self.compression_bound {
+            let _span = info_span!("compression").entered();
+            for bound in self.compression.bounds(problem)? {
+                relaxation = relaxation.with_compression(bound);
+            }
+            let ensemble = self.compression.compression_ensemble.len();
+            info!(levels = ?self.compression.n_meta_items, ensemble, "compression bound built");
+            info!(target: EVENTS, kind = "compression", levels = ?self.compression.n_meta_items, ensemble);
+        }
+        Ok(relaxation)
+    }
+
+    /// Parses the options from the given arguments, the first of which is the program name
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Self, clap::Error> {
+        SolveParser::try_parse_from(args).map(|p| p.solve)
+    }
+
+    /// Solves the instance once with the selected method, without reporting the solution. 
+    /// Returns whether optimality was proved and the (scaled) cost of the best solution found
+    pub fn evaluate(&self) -> Result<(bool, Option<isize>), PspError> {
+        let problem = self.load()?;
+        let resolution = self.solve_problem(&problem, None)?;
+        Ok((resolution.is_exact, resolution.incumbent.map(|(value, _)| -value)))
+    }
+
+    /// Solves the given problem once with the selected method, without reporting the solution.
+    pub fn solve_problem(&self, original: &Psp, monitor: Option<&Monitor>) -> Result<Resolution, PspError> {
+        let preprocessed = self.preprocess(original);
+        let problem = &preprocessed.problem;
+        self.check_solver(problem)?;
+        let relaxation = self.relaxation(problem)?;
+        let start = Instant::now();
+        let budget = Duration::from_secs(self.timeout);
+
+        let mut resolution = self.solve_with(problem, &relaxation, budget, monitor);
+        if let Some((hits, misses)) = relaxation.cache_stats() {
+            debug!(hits, misses, "rough upper bound cache");
+        }
+        relaxation.save_compressions()?;
+        if !resolution.is_exact && (self.local_search || self.solver == SolverType::Localsearch) {
+            let cutoff = TimeBudget::new(budget.saturating_sub(start.elapsed()));
+            resolution.incumbent = resolution.incumbent.map(|(value, solution)| local_search(problem, value, &solution, &cutoff));
+        }
+        if let (false, Some(window)) = (resolution.is_exact, self.polish) {
+            let remaining = budget.saturating_sub(start.elapsed());
+            resolution.incumbent = self.polish(problem, &relaxation, resolution.incumbent, window, remaining, monitor);
+        }
+        resolution.incumbent = resolution.incumbent.map(|(value, solution)| (value, preprocessed.restore(original, &solution)));
+        Ok(resolution)
+    }
+
+    /// Solves the meta-problem of each level of compression of the problem, within the
+    /// given time budget for all of them, and reports its best cost and the root bound of the compression.
+    /// Returns the compressions with their best cost, if any, and whether it is optimal
+    fn solve_meta(&self, problem: &Psp, budget: Duration) -> Result<Vec<(PspCompression, Option<isize>, bool)>, PspError> {
+        let _span = info_span!("meta").entered();
+        let begin = Instant::now();
+        let mut levels = vec![];
+        let mut previous = problem.clone();
+        for (level, compression) in self.compression.compressions(problem)?.into_iter().enumerate() {
+            let meta = &compression.meta_problem;
+            let start = Instant::now();
+            let resolution = self.solve_deepening(meta, &self.plain_relaxation(meta), budget.saturating_sub(begin.elapsed()), None);
+            let cost = resolution.incumbent.as_ref().map(|(value, _)| -value);
+            let kind = if resolution.is_exact { "optimum" } else { "best" };
+            match cost {
+                Some(cost) => report!(self, "meta level {level}: {} meta-items, {kind} {} ({:.3}s)",
+                    meta.n_items, meta.format_cost(cost), start.elapsed().as_secs_f64()),
+                None => report!(self, "meta level {level}: {} meta-items, no solution ({:.3}s)", meta.n_items, start.elapsed().as_secs_f64()),
+            }
+
+            // the bound of this level alone, from the compression of the root of the compressed problem
+            let root = previous.initial_state();
+            match CompressedSolutionBound::new(compression.clone()).get_ub(&root) {
+                Some(value) => report!(self, "meta level {level}: root compression bound {}", previous.format_cost(-value)),
+                None => report!(self, "meta level {level}: root compression bound none (infeasible)"),
+            }
+            previous = meta.clone();
+            levels.push((compression, cost, resolution.is_exact));
+        }
+        Ok(levels)
+    }
+
+    /// Reports how much the meta-problems lose with respect to the given cost of a
+    /// solution of the problem. The optimal cost of a meta-problem built with the cheapest
+    /// costs can never be above it
+    fn report_meta_loss(&self, problem: &Psp, meta: &[(PspCompression, Option<isize>, bool)], cost: isize) {
+        for (level, (compression, meta_cost, is_exact)) in meta.iter().enumerate() {
+            let Some(meta_cost) = meta_cost else {
+                continue;
+            };
+            // the meta-problems do not include the cost saved by the preprocessing
+            let meta_cost = meta_cost + problem.offset as isize;
+            let loss = if cost == 0 { 0.0 } else { (cost - meta_cost) as f64 / cost as f64 };
+            report!(self, "meta level {level}: loss {:.2}%", loss * 100.0);
+            if *is_exact && compression.representatives.is_none() && meta_cost > cost {
+                warn!(level, meta = %problem.format_cost(meta_cost), cost = %problem.format_cost(cost), "the meta-problem is not a relaxation of the problem");
+            }
+        }
+    }
+
+    /// Builds the relaxation of the problem with the requested bounds, except the
+    /// compression bound
+    fn plain_relaxation(&self, problem: &Psp) -> PspRelax {
+        let mut relaxation = PspRelax::new(problem.clone())
+            .with_merge(self.merge)
+            .with_changeover_bound(self.changeover_bound)
+            .with_stocking_bound(self.stocking_bound);
+        if !self.no_cache {
+            relaxation = relaxation.with_cache(self.cache_size);
+        }
+        if self.lagrangian && !problem.windows && problem.overtime.is_none() {
+            relaxation = relaxation.with_lagrangian(LagrangianBound::new(problem, self.lagrangian_iterations));
+        }
+        relaxation
+    }
+
+    /// Prints the lower bounds obtained at the root by the relaxed DD and, if requested,
+    /// by the LP relaxation. With the compression bound, its value at the root and the
+    /// bound of a relaxed DD without it are printed as well, to measure its contribution
+    fn report_root_bounds(&self, problem: &Psp, relaxation: &PspRelax) {
         let cutoff = TimeBudget::new(Duration::from_secs(self.timeout));
+        let start = Instant::now();
+        match relaxed_dd(problem, relaxation, &PspRanking, &self.selection, self.width, &cutoff) {
+            Some(value) => report!(self, "root relaxed dd bound {} ({:.3}s)", problem.format_cost(-value), start.elapsed().as_secs_f64()),
+            None => report!(self, "root relaxed dd bound none"),
+        }
+
+        if self.compression_bound {
+            let root = problem.initial_state();
+            match relaxation.compression_bound(&root) {
+                Some(value) => report!(self, "root compression bound {}", problem.format_cost(-(problem.initial_value() + value))),
+                None => report!(self, "root compression bound none (infeasible)"),
+            }
+            let start = Instant::now();
+            match relaxed_dd(problem, &self.plain_relaxation(problem), &PspRanking, &self.selection, self.width, &cutoff) {
+                Some(value) => report!(self, "root plain relaxed dd bound {} ({:.3}s)", problem.format_cost(-value), start.elapsed().as_secs_f64()),
+                None => report!(self, "root plain relaxed dd bound none"),
+            }
+        }
+
+        #[cfg(feature = "lp")]
+        if self.root_bounds {
+            let start = Instant::now();
+            if problem.windows || !problem.precedences.is_empty() || problem.overtime.is_some() {
+                report!(self, "root lp bound unsupported with service windows, precedences or overtime");
+            } else {
+                match lp_bound(problem) {
+                    Some(bound) => report!(self, "root lp bound {} ({:.3}s)", problem.format_cost(bound), start.elapsed().as_secs_f64()),
+                    None => report!(self, "root lp bound none"),
+                }
+            }
+        }
+    }
+
+    /// Writes the given solution to a json file
+    fn write_solution(&self, problem: &Psp, solution: &Solution, status: SolveStatus, resolution: &Resolution, path: &str) -> Result<(), PspError> {
+        let schedule = problem.schedule(solution);
+        let cost = problem.cost(&schedule).expect("the solutions found are feasible");
+        let solution = PspSolution {
+            cost: problem.unscale(cost),
+            decomposition: Some(decomposition(problem, &schedule)),
+            kpis: Some(kpis(problem, &schedule)),
+            schedule,
+            status: Some(status),
+            bound: resolution.lower_bound().map(|b| problem.unscale(b)),
+            gap: resolution.gap(),
+            fingerprint: Some(problem.fingerprint.clone()),
+        };
+        write_json(path, &solution)
+    }
+
+    /// Writes the drawing and the layer statistics of the DD compiled at the root to the requested files
+    fn write_diagram(&self, problem: &Psp, relaxation: &PspRelax) -> Result<(), PspError> {
+        let cutoff = TimeBudget::new(Duration::from_secs(self.timeout));
+        let diagram = match self.viz {
+            VizMode::Relaxed => compile_relaxed(problem, relaxation, &PspRanking, &self.selection, self.width, &cutoff, true),
+            VizMode::Restricted => compile_restricted(problem, &PspRanking, self.width, &cutoff),
+        };
+        let Some(diagram) = diagram else {
+            warn!("the {:?} dd could not be compiled within the time limit", self.viz);
+            return Ok(());
+        };
+
+        if let Some(path) = self.dot.as_ref() {
+            write_file(path, diagram.to_dot(problem, &self.viz_config))?;
+        }
+        if let Some(path) = self.layer_stats.as_ref() {
+            write_file(path, diagram.layer_stats(problem))?;
+        }
+        Ok(())
+    }
+
+    /// Solves the problem with the selected method. The bound of the resolution is at least
+    /// as tight as the one of the relaxation at the root, and it is the value of the incumbent
+    /// when optimality is proved
+    fn solve_with(&self, problem: &Psp, relaxation: &PspRelax, budget: Duration, monitor: Option<&Monitor>) -> Resolution {
+        let mut resolution = match self.solver {
+            SolverType::Ddo if self.heuristic_only => self.solve_heuristic(problem, budget, monitor),
+            SolverType::Ddo if self.portfolio => self.solve_portfolio(problem, relaxation, budget, monitor),
+            SolverType::Ddo => self.solve_deepening(problem, relaxation, budget, monitor),
+            SolverType::Lns => self.solve_lns(problem, relaxation, budget, monitor),
+            SolverType::Greedy | SolverType::Localsearch => Resolution::heuristic(lookahead_greedy(problem, self.heuristic_lookahead)),
+            SolverType::Sa => self.solve_annealing(problem, budget),
+            SolverType::Exhaustive => {
+                let (is_exact, incumbent) = exhaustive(problem, &TimeBudget::new(budget));
+                Resolution { is_exact, incumbent, bound: isize::MAX }
+            },
+            SolverType::Dp => {
+                let (is_exact, incumbent) = dynamic_programming(problem, &TimeBudget::new(budget));
+                Resolution { is_exact, incumbent, bound: isize::MAX }
+            },
+            #[cfg(feature = "mip")]
+            SolverType::Mip => {
+                let (is_exact, incumbent) = solve_mip(problem, budget);
+                Resolution { is_exact, incumbent, bound: isize::MAX }
+            },
+        };
+
+        let root = problem.initial_state();
+        let root_bound = problem.initial_value().saturating_add(relaxation.fast_upper_bound(&root));
+        resolution.bound = resolution.bound.min(root_bound);
+        if resolution.is_exact {
+            resolution.bound = resolution.incumbent.as_ref().map_or(isize::MIN, |(value, _)| *value);
+        }
+        resolution
+    }
+
+    /// Solves the problem with the given width. When the deepening mode is enabled, the
+    /// width is increased each time the given number of subproblems have been explored,
+    /// and the subproblems left open are explored with the larger width, until optimality
+    /// is proved
+    fn solve_deepening(&self, problem: &Psp, relaxation: &PspRelax, budget: Duration, monitor: Option<&Monitor>) -> Resolution {
+        let start = Instant::now();
+        let stop = AtomicBool::new(false);
+        let nb_threads = nb_threads();
+
+        let mut config = Config { cutset: Cutset::Frontier, width: self.width, dominance: self.use_dominance(problem), layer_cache: self.layer_cache() };
+        let mut incumbent = if self.warm_start { lookahead_greedy(problem, self.heuristic_lookahead) } else { None };
+        let mut bound = isize::MAX;
+        // the subproblems left open with a width are explored with the next one
+        let mut carried = self.deepening.then(|| Carried::new(self.deepening_nodes));
+
+        let mut iteration = 0;
+        loop {
+            let _span = info_span!("deepening", iteration).entered();
+            let time_budget = TimeBudget::new(budget.saturating_sub(start.elapsed()));
+            let cutoff = SharedCutoff { budget: &time_budget, stop: &stop, monitor, full: true, best: None };
+
+            let previous = incumbent.as_ref().map(|(value, _)| *value);
+            let (is_exact, run_bound) = Self::run(problem, relaxation, config, &cutoff, nb_threads, &mut incumbent, carried.as_mut());
+            if incumbent.as_ref().map(|(value, _)| *value) != previous {
+                cutoff.report(problem, &incumbent);
+            }
+            bound = bound.min(run_bound);
+
+            if is_exact || !self.deepening || start.elapsed() >= budget {
+                return Resolution { is_exact, incumbent, bound };
+            }
+
+            config.width = config.width.saturating_mul(self.deepening_factor);
+            info!("continuing with width {}", config.width);
+            iteration += 1;
+        }
+    }
+
+    /// Runs several configurations concurrently, the first one that proves optimality
+    /// stops all the others. They run in turn in the sequential builds. The best solution
+    /// of the configurations that are done is shared: it starts the later ones, and all
+    /// of them discard the subproblems that cannot improve it. With the compression bound,
+    /// each configuration also runs with the plain relaxation, whose bound is weaker but
+    /// cheaper to compute
+    fn solve_portfolio(&self, problem: &Psp, relaxation: &PspRelax, budget: Duration, monitor: Option<&Monitor>) -> Resolution {
+        let dominance = self.use_dominance(problem);
+        let layer_cache = self.layer_cache();
+        let plain = relaxation.is_compressed().then(|| self.plain_relaxation(problem));
+        let configs = std::iter::once(relaxation).chain(plain.as_ref())
+            .flat_map(|relaxation| [
+                (Config { cutset: Cutset::Frontier,       width: self.width,                    dominance, layer_cache }, relaxation),
+                (Config { cutset: Cutset::LastExactLayer, width: self.width,                    dominance, layer_cache }, relaxation),
+                (Config { cutset: Cutset::Frontier,       width: self.width.saturating_mul(10), dominance, layer_cache }, relaxation),
+                (Config { cutset: Cutset::LastExactLayer, width: self.width.saturating_mul(10), dominance, layer_cache }, relaxation),
+            ])
+            .collect::<Vec<(Config, &PspRelax)>>();
+        let nb_threads = (nb_threads() / configs.len()).max(1);
+
+        let budget = TimeBudget::new(budget);
+        let stop = AtomicBool::new(false);
+        let best = Mutex::new(Resolution::heuristic(None));
+        // the value of the best incumbent, read by the fringes without locking
+        let best_value = AtomicIsize::new(isize::MIN);
+
+        let run = |(config, relaxation): (Config, &PspRelax)| {
+            let cutoff = SharedCutoff { budget: &budget, stop: &stop, monitor, full: true, best: Some(&best_value) };
+            let mut incumbent = best.lock().unwrap().incumbent.clone();
+            let (is_exact, bound) = Self::run(problem, relaxation, config, &cutoff, nb_threads, &mut incumbent, None);
+
+            let mut best = best.lock().unwrap();
+            if Self::improves(&incumbent, &best.incumbent) {
+                cutoff.report(problem, &incumbent);
+                best.incumbent = incumbent;
+                if let Some((value, _)) = best.incumbent.as_ref() {
+                    best_value.fetch_max(*value, Ordering::Relaxed);
+                }
+            }
+            // the subproblems discarded with the shared incumbent cannot improve it, so it
+            // is optimal when the search is complete
+            if is_exact && !stop.swap(true, Ordering::Relaxed) {
+                info!(compressed = relaxation.is_compressed(), "optimality proved by {config:?}");
+                best.is_exact = true;
+                best.bound = bound;
+            } else if !best.is_exact {
+                best.bound = best.bound.min(bound);
+            }
+        };
+
+        #[cfg(not(feature = "sequential"))]
+        thread::scope(|s| {
+            let run = &run;
+            for config in configs {
+                s.spawn(move || run(config));
+            }
+        });
+        #[cfg(feature = "sequential")]
+        for config in configs {
+            if stop.load(Ordering::Relaxed) || budget.must_stop() {
+                break;
+            }
+            run(config);
+        }
+
+        best.into_inner().unwrap()
+    }
+
+    /// Compiles restricted DDs from the root with a width that is doubled each time, 
+    /// until the time budget is exhausted or a DD is compiled without restriction
+    fn solve_heuristic(&self, problem: &Psp, budget: Duration, monitor: Option<&Monitor>) -> Resolution {
+        let ranking = PspRanking;
+        let time_budget = TimeBudget::new(budget);
+        let stop = AtomicBool::new(false);
+        let cutoff = SharedCutoff { budget: &time_budget, stop: &stop, monitor, full: false, best: None };
+        let mut width = self.width;
+        let mut incumbent = None;
+
+        while !cutoff.must_stop() {
+            let restriction = restricted_dd(problem, &ranking, width, &cutoff);
+            if Self::improves(&restriction.best, &incumbent) {
+                incumbent = restriction.best;
+                cutoff.report(problem, &incumbent);
+                if let Some((value, _)) = incumbent.as_ref() {
+                    info!("width {width}: found solution of value {}", problem.format_cost(-value));
+                }
+            }
+            if restriction.is_exact {
+                return Resolution { is_exact: true, incumbent, bound: isize::MAX };
+            }
+            width = width.saturating_mul(2);
+        }
+
+        Resolution::heuristic(incumbent)
+    }
+
+    /// Starts from the greedy solution and improves it by simulated annealing
+    fn solve_annealing(&self, problem: &Psp, budget: Duration) -> Resolution {
+        let cutoff = TimeBudget::new(budget);
+        let mut rng = ChaChaRng::seed_from_u64(self.seed);
+        let temperature = Temperature {
+            initial: self.temperature,
+            cooling: self.cooling,
+            min: self.min_temperature,
+        };
+
+        let incumbent = lookahead_greedy(problem, self.heuristic_lookahead)
+            .map(|(value, solution)| simulated_annealing(problem, value, &solution, temperature, &mut rng, &cutoff));
+        Resolution::heuristic(incumbent)
+    }
+
+    /// Starts from a restricted DD solution, and repeatedly re-optimizes a random window 
+    /// of consecutive periods exactly while all other decisions are fixed
+    fn solve_lns(&self, problem: &Psp, relaxation: &PspRelax, budget: Duration, monitor: Option<&Monitor>) -> Resolution {
         let ranking = PspRanking;
-        let mut fringe = NoDupFringe::new(MaxUB::new(&ranking));
+        let time_budget = TimeBudget::new(budget);
+        let stop = AtomicBool::new(false);
+        let cutoff = SharedCutoff { budget: &time_budget, stop: &stop, monitor, full: false, best: None };
+        let nb_threads = nb_threads();
+        // the decisions of the subproblems are fixed outside of their window
+        let config = Config { cutset: Cutset::Frontier, width: self.width, dominance: false, layer_cache: self.layer_cache() };
+
+        let mut rng = ChaChaRng::seed_from_u64(self.seed);
+        let size = self.neighborhood.clamp(1, problem.horizon.max(1));
+        let mut subproblem = problem.clone();
+        subproblem.clear_symmetries();
+        let mut incumbent = restricted_dd(problem, &ranking, self.width, &cutoff).best;
+
+        for iteration in 0..self.iterations {
+            if cutoff.must_stop() {
+                break;
+            }
+            let Some((_, solution)) = incumbent.as_ref() else {
+                break;
+            };
+
+            let schedule = problem.schedule(solution);
+            // the horizon of an empty instance is shorter than the window
+            let from = rng.gen_range(0..=problem.horizon.saturating_sub(size));
+            let window = from..(from + size);
+            subproblem.fixed = schedule.iter().copied().enumerate()
+                .map(|(t, v)| if window.contains(&t) { None } else { Some(v) })
+                .collect();
+
+            let _span = info_span!("lns", iteration, from).entered();
+            let mut candidate = incumbent.clone();
+            let (is_exact, bound) = Self::run(&subproblem, relaxation, config, &cutoff, nb_threads, &mut candidate, None);
+
+            if size == problem.horizon && is_exact {
+                return Resolution { is_exact, incumbent: candidate, bound };
+            }
+            if Self::improves(&candidate, &incumbent) {
+                if let Some((value, _)) = candidate.as_ref() {
+                    info!("iteration {iteration}: window {window:?} improved to {}", problem.format_cost(-value));
+                }
+                incumbent = candidate;
+                cutoff.report(problem, &incumbent);
+            }
+        }
+
+        Resolution::heuristic(incumbent)
+    }
+
+    /// Re-optimizes the windows of the given number of periods of the incumbent in turn,
+    /// each window overlapping half of the previous one, with the DD solver while the
+    /// other periods are fixed. The passes over the horizon are repeated until none of
+    /// them improves the incumbent or the time budget is exhausted
+    fn polish(&self, problem: &Psp, relaxation: &PspRelax, mut incumbent: Incumbent, periods: usize, budget: Duration, monitor: Option<&Monitor>) -> Incumbent {
+        let time_budget = TimeBudget::new(budget);
+        let stop = AtomicBool::new(false);
+        let cutoff = SharedCutoff { budget: &time_budget, stop: &stop, monitor, full: false, best: None };
+        let nb_threads = nb_threads();
+        // the decisions of the subproblems are fixed outside of their window
+        let config = Config { cutset: Cutset::Frontier, width: self.width, dominance: false, layer_cache: self.layer_cache() };
+
+        let size = periods.clamp(1, problem.horizon.max(1));
+        let step = (size / 2).max(1);
+        // the horizon of an empty instance is shorter than the window
+        let last = problem.horizon.saturating_sub(size);
+        let mut starts = (0..=last).step_by(step).collect::<Vec<usize>>();
+        if starts.last() != Some(&last) {
+            starts.push(last);
+        }
+        let mut subproblem = problem.clone();
+        subproblem.clear_symmetries();
 
-        let mut solver = ParBarrierSolverFc::new(&problem, &relaxation, &ranking, &width, &cutoff, &mut fringe);
+        let mut improved = true;
+        while improved && !cutoff.must_stop() {
+            improved = false;
+            for from in starts.iter().copied() {
+                let Some((_, solution)) = incumbent.as_ref() else {
+                    return None;
+                };
+                if cutoff.must_stop() {
+                    break;
+                }
+                let schedule = problem.schedule(solution);
+                let window = from..(from + size);
+                subproblem.fixed = schedule.iter().copied().enumerate()
+                    .map(|(t, v)| if window.contains(&t) { None } else { Some(v) })
+                    .collect();
+
+                let _span = info_span!("polish", from).entered();
+                let mut candidate = incumbent.clone();
+                Self::run(&subproblem, relaxation, config, &cutoff, nb_threads, &mut candidate, None);
+                if Self::improves(&candidate, &incumbent) {
+                    if let Some((value, _)) = candidate.as_ref() {
+                        info!("window {window:?} polished to {}", problem.format_cost(-value));
+                    }
+                    incumbent = candidate;
+                    cutoff.report(problem, &incumbent);
+                    improved = true;
+                }
+            }
+        }
+        incumbent
+    }
+
+    /// Runs the solver with the given configuration, starting from the given incumbent
+    /// which is updated with the best solution found, and from the given carried subproblems
+    /// if any. Returns true iff optimality is proved, and the best upper bound proved on the
+    /// value of the solutions
+    fn run(problem: &Psp, relaxation: &PspRelax, config: Config, cutoff: &SharedCutoff, nb_threads: usize, incumbent: &mut Incumbent, mut carried: Option<&mut Carried>) -> (bool, isize) {
+        let _span = info_span!("run", width = config.width, cutset = ?config.cutset, dominance = config.dominance).entered();
+        let start = Instant::now();
+        let ranking = PspRanking;
+        let width = FixedWidth(config.width);
+        let mut fringe: BoxedFringe = match (carried.as_deref_mut(), config.dominance) {
+            (Some(carried), true) => Box::new(DominanceFringe::new(RoundFringe::new(carried))),
+            (Some(carried), false) => Box::new(RoundFringe::new(carried)),
+            (None, true) => Box::new(DominanceFringe::new(ArenaFringe::default())),
+            (None, false) => Box::new(ArenaFringe::default()),
+        };
+        if let Some(best) = cutoff.best {
+            fringe = Box::new(SharedBoundFringe { inner: fringe, best });
+        }
+        if let Some(monitor) = cutoff.monitor {
+            fringe = Box::new(MonitoredFringe::new(fringe, monitor));
+        }
+
+        // the compilations of the run expand the states of the shared prefixes of their layers once
+        let expansions = config.layer_cache.map(|capacity| CachedExpansions::new(problem, capacity));
+        let compiled: &(dyn Problem<State = PspState> + Send + Sync) = match expansions.as_ref() {
+            Some(expansions) => expansions,
+            None => problem,
+        };
+
+        #[cfg(not(feature = "sequential"))]
+        let mut solver: Box<dyn Solver + '_> = match config.cutset {
+            Cutset::Frontier => 
+                Box::new(ParBarrierSolverFc::custom(compiled, relaxation, &ranking, &width, cutoff, fringe.as_mut(), nb_threads)),
+            Cutset::LastExactLayer => 
+                Box::new(ParBarrierSolverLel::custom(compiled, relaxation, &ranking, &width, cutoff, fringe.as_mut(), nb_threads)),
+        };
+        #[cfg(feature = "sequential")]
+        let mut solver: Box<dyn Solver + '_> = {
+            debug_assert_eq!(nb_threads, 1, "the sequential solvers use a single thread");
+            match config.cutset {
+                Cutset::Frontier => 
+                    Box::new(SeqCachingSolverFc::custom(compiled, relaxation, &ranking, &width, cutoff, fringe.as_mut())),
+                Cutset::LastExactLayer => 
+                    Box::new(SeqCachingSolverLel::custom(compiled, relaxation, &ranking, &width, cutoff, fringe.as_mut())),
+            }
+        };
+
+        if let Some((value, solution)) = incumbent.clone() {
+            solver.set_primal(value, solution);
+        }
 
         let Completion{best_value, is_exact} = solver.maximize();
+        if let (Some(value), Some(solution)) = (best_value, solver.best_solution()) {
+            *incumbent = Some((value, solution));
+        }
+        // the subproblems discarded with the shared incumbent are bounded by its value
+        let bound = solver.best_upper_bound().max(cutoff.best.map_or(isize::MIN, |best| best.load(Ordering::Relaxed)));
+        drop(solver);
+        drop(fringe);
+        // the solver stops when the carried subproblems look exhausted, which they are only
+        // when none of those that remain can improve the incumbent
+        let open = carried.and_then(|carried| carried.fringe.best_ub()).unwrap_or(isize::MIN);
+        let is_exact = is_exact && open <= incumbent.as_ref().map_or(isize::MIN, |(value, _)| *value);
+        let bound = bound.max(open);
+        if let (Some(monitor), true) = (cutoff.monitor, cutoff.full && bound != isize::MAX && bound != isize::MIN) {
+            monitor.report_bound(bound);
+        }
+        if let Some((hits, misses)) = expansions.as_ref().map(|expansions| expansions.stats()) {
+            debug!(hits, misses, "expansion cache");
+        }
+        let time = start.elapsed().as_secs_f64();
+        debug!(is_exact, best = ?best_value, bound, time, "dd search done");
+        info!(target: EVENTS, kind = "dd", width = config.width, is_exact, bound, time);
+        if !is_exact && cutoff.must_stop() {
+            info!(target: EVENTS, kind = "cutoff", width = config.width, time);
+        }
+
+        (is_exact, bound)
+    }
 
-        let best_value = best_value.map(|v| -v).unwrap_or(isize::MAX);
-        println!("is exact {is_exact}");
-        println!("best value {best_value}");
+    /// Returns the maximum number of states whose successors are cached by each run, if
+    /// they are reused
+    fn layer_cache(&self) -> Option<usize> {
+        (!self.no_layer_cache).then_some(self.cache_size)
+    }
 
-        let mut sol = String::new();
-        solver.best_solution().unwrap()
-            .iter().map(|d| d.value)
-            .for_each(|v| sol.push_str(&format!("{v} ")));
+    /// Returns true iff the dominance check is requested and valid for the given problem
+    fn use_dominance(&self, problem: &Psp) -> bool {
+        if self.dominance && !problem.satisfies_triangle_inequality() {
+            warn!("dominance disabled: the changeover costs do not satisfy the triangle inequality");
+            return false;
+        }
+        if self.dominance && problem.has_forbidden_transitions() {
+            warn!("dominance disabled: some transitions between items are forbidden");
+            return false;
+        }
+        if self.dominance && !problem.second_order.is_empty() {
+            warn!("dominance disabled: dropping productions changes the second-order changeovers");
+            return false;
+        }
+        if self.dominance && problem.idle > 0 {
+            warn!("dominance disabled: dropping productions adds idle periods");
+            return false;
+        }
+        if self.dominance && problem.has_side_constraints() {
+            warn!("dominance disabled: dropping productions can create banned transitions");
+            return false;
+        }
+        if self.dominance && problem.fixed.iter().any(Option::is_some) {
+            warn!("dominance disabled: some decisions are fixed");
+            return false;
+        }
+        self.dominance
+    }
 
-        println!("solution: {sol}");
+    fn improves(a: &Incumbent, b: &Incumbent) -> bool {
+        match (a, b) {
+            (Some((x, _)), Some((y, _))) => x > y,
+            (Some(_), None) => true,
+            _ => false,
+        }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::instance::fixtures::{instance, parse, with};
+    use super::*;
+
+    /// The instance of the fixtures, whose changeovers satisfy the triangle inequality,
+    /// with the given fields replaced
+    fn model(fields: Value) -> Psp {
+        Psp::try_from(parse(with(instance(), fields))).unwrap()
+    }
+
+    fn dominance() -> Solve {
+        Solve::from_args(["psp", "-i", "instance.json", "--dominance"].map(String::from)).unwrap()
+    }
+
+    #[test]
+    fn side_constraints_disable_the_dominance() {
+        let mut problem = model(json!({}));
+        assert!(dominance().use_dominance(&problem));
+        problem.constrain(&serde_json::from_value(json!({"transitions": [{"from": 1, "to": 0, "period": 0}]})).unwrap()).unwrap();
+        assert!(!dominance().use_dominance(&problem));
+    }
+
+    #[test]
+    fn idle_costs_disable_the_dominance() {
+        assert!(!dominance().use_dominance(&model(json!({"idle_cost": 1}))));
+    }
+
+    #[test]
+    fn the_solutions_enumerated_fit_in_the_states() {
+        let parse = |k: &str| Solve::from_args(["psp", "-i", "instance.json", "-k", k].map(String::from));
+        assert_eq!(parse("65").unwrap().num_solutions, MAX_FORBIDDEN + 1);
+        assert!(parse("66").is_err());
+        assert!(parse("0").is_err());
+    }
+
+    /// Returns the optimal value of the problem found with the given options
+    fn optimum(problem: &Psp, args: &[&str]) -> Option<isize> {
+        let args = ["psp", "-i", "instance.json"].iter().chain(args).map(|arg| arg.to_string());
+        let resolution = Solve::from_args(args).unwrap().solve_problem(problem, None).unwrap();
+        assert!(resolution.is_exact);
+        resolution.incumbent.map(|(value, _)| value)
+    }
+
+    #[test]
+    fn the_portfolio_finds_the_optimum_of_the_sequential_solver() {
+        let problem = model(json!({}));
+        // the compressed and the plain relaxations run concurrently with the compression bound
+        for args in [&["--portfolio"][..], &["--portfolio", "--compression-bound", "--n-meta-items", "1"]] {
+            assert_eq!(optimum(&problem, args), optimum(&problem, &[]));
+        }
+    }
+
+    #[test]
+    fn the_deepening_explores_the_open_subproblems_with_larger_widths() {
+        let problem = model(json!({"nb_periods": 6, "demands": [[0, 1, 0, 1, 0, 1], [0, 0, 1, 0, 0, 1]]}));
+        // each width explores a single subproblem
+        assert_eq!(optimum(&problem, &["-w", "1", "--deepening", "--deepening-nodes", "1"]), optimum(&problem, &[]));
+    }
+
+    #[test]
+    fn the_layer_cache_keeps_the_optimum() {
+        let problem = model(json!({"nb_periods": 6, "demands": [[0, 1, 0, 1, 0, 1], [0, 0, 1, 0, 0, 1]]}));
+        // the small width restricts the DDs, so that the subproblems are compiled again
+        assert_eq!(optimum(&problem, &["-w", "1"]), optimum(&problem, &["-w", "1", "--no-layer-cache"]));
+    }
+}
@@ -0,0 +1,349 @@
+//! This module implements the split and stitch subcommands, the building blocks of the
+//! decomposition of the instances over their horizon. The horizon is cut into windows
+//! that overlap: each window keeps the demands due in its core, and sees those due in the
+//! overlap with the next window, which it plans without keeping them. When a window has
+//! more units to produce than periods, the extra units are carried to the previous one,
+//! which must have them in stock at the end of its core. The sub-solutions are then
+//! stitched on the cores of their windows, and the schedule obtained is evaluated on
+//! the original instance.
+
+use std::path::Path;
+
+use clap::Args;
+use serde::{Serialize, Deserialize};
+use tracing::{info, warn};
+
+use crate::error::{PspError, read_json, write_json};
+use crate::instance::{PspInstance, SparseDemand};
+use crate::resolution::model::{Psp, IDLE};
+use crate::resolution::report::{decomposition, kpis};
+use crate::solution::PspSolution;
+
+/// The name of the file describing the windows in the output directory of split
+const MANIFEST: &str = "split.json";
+
+#[derive(Debug, Args)]
+pub struct Split {
+    /// The path to the instance file (- for the standard input)
+    #[clap(short, long)]
+    pub instance: String,
+    /// The number of periods of each window
+    #[clap(short, long)]
+    pub window: usize,
+    /// The number of periods shared by consecutive windows
+    #[clap(long, default_value = "0")]
+    pub overlap: usize,
+    /// The directory where the sub-instances and their description are written
+    #[clap(short, long)]
+    pub output: String,
+}
+
+#[derive(Debug, Args)]
+pub struct Stitch {
+    /// The path to the original instance file (- for the standard input)
+    #[clap(short, long)]
+    pub instance: String,
+    /// The path to the description of the windows written by split
+    #[clap(short, long)]
+    pub manifest: String,
+    /// The paths to the solution files of the sub-instances, in the order of the windows
+    #[clap(short, long, required = true, num_args = 1..)]
+    pub solutions: Vec<String>,
+    /// The path to the file where the stitched solution is written
+    #[clap(short, long)]
+    pub output: Option<String>,
+}
+
+/// The windows of an instance split by the split subcommand
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SplitManifest {
+    /// The fingerprint of the original instance
+    pub fingerprint: String,
+    pub horizon: usize,
+    pub parts: Vec<SplitPart>,
+}
+
+/// A window of the horizon and its sub-instance
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SplitPart {
+    /// The path to the sub-instance
+    pub instance: String,
+    /// The first period of the window
+    pub start: usize,
+    /// The first period after the core of the window, whose schedule is kept
+    pub core_end: usize,
+    /// The first period after the window
+    pub end: usize,
+    /// The number of units of each item that are in stock at the end of the core, for the
+    /// demands of the next windows. They are due at the last period of the core in the
+    /// sub-instance
+    pub inventory: Vec<usize>,
+}
+
+/// A unit that is demanded
+#[derive(Debug, Clone, Copy)]
+struct Unit {
+    item: usize,
+    /// The period at which the unit is due in the instance
+    deadline: usize,
+    /// The period at which the unit is due in the window that produces it
+    due: usize,
+    earliest: Option<usize>,
+}
+
+impl Split {
+    /// Writes the sub-instances of the windows and their description
+    pub fn split(&self) -> Result<(), PspError> {
+        if self.window == 0 || self.overlap >= self.window {
+            return Err(PspError::Invalid("the windows must be longer than their overlap".to_string()));
+        }
+        let instance: PspInstance = read_json(&self.instance)?;
+        if instance.precedences.as_ref().is_some_and(|p| !p.is_empty()) {
+            warn!("the precedences are not kept in the sub-instances");
+        }
+
+        let horizon = instance.nb_periods;
+        let core = self.window - self.overlap;
+        let starts = (0..horizon).step_by(core).collect::<Vec<usize>>();
+        let units = Self::units(&instance);
+
+        // the units kept by each window, from the last one
+        let mut kept = vec![vec![]; starts.len()];
+        let mut carried: Vec<Unit> = vec![];
+        for (k, start) in starts.iter().copied().enumerate().rev() {
+            let core_end = (start + core).min(horizon);
+            let mut pool = units.iter().copied().filter(|u| (start..core_end).contains(&u.deadline)).collect::<Vec<Unit>>();
+            pool.extend(carried.drain(..).map(|u| Unit { due: core_end - 1, earliest: u.earliest.map(|e| e.min(core_end - 1)), ..u }));
+            carried = Self::carry(&instance, pool, start, core_end, &mut kept[k]);
+        }
+        if !carried.is_empty() {
+            return Err(PspError::Invalid(format!("{} units cannot be produced before their deadline", carried.len())));
+        }
+
+        let dir = Path::new(&self.output);
+        std::fs::create_dir_all(dir).map_err(|source| PspError::Io { path: self.output.clone(), source })?;
+        let mut parts = vec![];
+        for (k, start) in starts.iter().copied().enumerate() {
+            let core_end = (start + core).min(horizon);
+            let end = (start + self.window).min(horizon);
+            // the window sees the units of the next windows that are due in its overlap
+            let units = kept[k..].iter().flatten().filter(|u| u.due < end).copied().collect::<Vec<Unit>>();
+            let mut inventory = vec![0; instance.nb_types];
+            units.iter().filter(|u| u.deadline >= core_end && u.due < core_end).for_each(|u| inventory[u.item] += 1);
+
+            let path = dir.join(format!("part-{k}.json")).to_string_lossy().to_string();
+            write_json(&path, &Self::window(&instance, &units, start, end))?;
+            parts.push(SplitPart { instance: path, start, core_end, end, inventory });
+        }
+
+        info!(windows = parts.len(), carried = parts.iter().flat_map(|p| p.inventory.iter()).sum::<usize>(), "instance split");
+        let manifest = SplitManifest { fingerprint: instance.fingerprint(), horizon, parts };
+        write_json(&dir.join(MANIFEST).to_string_lossy(), &manifest)
+    }
+
+    /// Returns the units demanded in the given instance
+    fn units(instance: &PspInstance) -> Vec<Unit> {
+        instance.demand_lists().into_iter().enumerate()
+            .flat_map(|(item, demands)| demands.into_iter()
+                .flat_map(move |d| (0..d.quantity).map(move |_| Unit { item, deadline: d.period, due: d.period, earliest: d.earliest })))
+            .collect()
+    }
+
+    /// Assigns the units of the pool to the periods of the core where the machine is
+    /// available, two per period with overtime, from the last one, so that the cheapest
+    /// units to stock are produced first. The units assigned are kept by the window, and
+    /// the others are returned since they must be carried to the previous one
+    fn carry(instance: &PspInstance, mut pool: Vec<Unit>, start: usize, core_end: usize, kept: &mut Vec<Unit>) -> Vec<Unit> {
+        let available = |t: &usize| instance.availability.as_ref().map_or(true, |a| a[*t]);
+        let capacity = if instance.overtime_cost.is_some() { 2 } else { 1 };
+        for t in (start..core_end).rev().filter(available) {
+            for _ in 0..capacity {
+                let unit = pool.iter().enumerate()
+                    .filter(|(_, u)| u.due >= t)
+                    .max_by(|(_, a), (_, b)| instance.stocking[a.item].total_cmp(&instance.stocking[b.item]))
+                    .map(|(k, _)| k);
+                if let Some(k) = unit {
+                    kept.push(pool.swap_remove(k));
+                }
+            }
+        }
+        pool
+    }
+
+    /// Returns the sub-instance of the window between the given periods, with the given units
+    fn window(instance: &PspInstance, units: &[Unit], start: usize, end: usize) -> PspInstance {
+        let mut lists: Vec<Vec<SparseDemand>> = vec![vec![]; instance.nb_types];
+        for unit in units {
+            let period = unit.due - start;
+            let earliest = unit.earliest.filter(|e| *e > start).map(|e| e - start);
+            let demands = &mut lists[unit.item];
+            match demands.iter_mut().find(|d| d.period == period) {
+                Some(d) => {
+                    d.quantity += 1;
+                    d.earliest = match (d.earliest, earliest) {
+                        (None, None) => None,
+                        (a, b) => Some(a.unwrap_or(period).min(b.unwrap_or(period))),
+                    };
+                },
+                None => demands.push(SparseDemand { period, quantity: 1, earliest }),
+            }
+        }
+        lists.iter_mut().for_each(|demands| demands.sort_by_key(|d| d.period));
+
+        PspInstance {
+            nb_periods: end - start,
+            demands: vec![],
+            sparse_demands: Some(lists),
+            earliest: None,
+            period_labels: instance.period_labels.as_ref().map(|labels| labels[start..end].to_vec()),
+            availability: instance.availability.as_ref().map(|a| a[start..end].to_vec()),
+            precedences: None,
+            ..instance.clone()
+        }
+    }
+}
+
+impl Stitch {
+    /// Concatenates the cores of the sub-solutions, and evaluates the schedule obtained.
+    /// The units produced twice, by the cores of two windows, are dropped
+    pub fn stitch(&self) -> Result<(), PspError> {
+        let instance: PspInstance = read_json(&self.instance)?;
+        let manifest: SplitManifest = read_json(&self.manifest)?;
+        if manifest.fingerprint != instance.fingerprint() {
+            warn!(manifest = %self.manifest, "the windows were not split from this instance");
+        }
+        if manifest.parts.len() != self.solutions.len() {
+            return Err(PspError::Invalid(format!("expected {} solutions, one per window, got {}", manifest.parts.len(), self.solutions.len())));
+        }
+        let problem = Psp::try_from(instance)?;
+        if manifest.horizon != problem.horizon {
+            return Err(PspError::Invalid(format!("the windows cover {} periods, the instance has {}", manifest.horizon, problem.horizon)));
+        }
+
+        let mut schedule = vec![IDLE; problem.horizon];
+        for (part, path) in manifest.parts.iter().zip(self.solutions.iter()) {
+            let solution: PspSolution = read_json(path)?;
+            if solution.schedule.len() != part.end - part.start {
+                return Err(PspError::Invalid(format!("{path}: expected a schedule of {} periods, got {}", part.end - part.start, solution.schedule.len())));
+            }
+            schedule[part.start..part.core_end].copy_from_slice(&solution.schedule[..part.core_end - part.start]);
+        }
+        let dropped = Self::drop_surplus(&problem, &mut schedule);
+        if dropped > 0 {
+            println!("{dropped} units produced twice dropped");
+        }
+
+        // the sub-instances do not know the setup of the machine at their start
+        for part in manifest.parts.iter().skip(1) {
+            let before = schedule[..part.start].iter().rev().find(|v| **v != IDLE).map(|v| problem.item_of(*v));
+            let after = schedule[part.start..].iter().find(|v| **v != IDLE).map(|v| problem.item_of(*v));
+            // the item produced before switching to the last one, for the second-order changeovers
+            let history = schedule[..part.start].iter().rev()
+                .filter(|v| **v != IDLE)
+                .map(|v| problem.item_of(*v))
+                .find(|i| Some(*i) != before)
+                .unwrap_or(IDLE);
+            if let (Some(a), Some(b)) = (before, after) {
+                let cost = problem.changeover_after(history, a as usize, b as usize);
+                println!("boundary at period {}: {a} -> {b}, changeover {}", part.start, problem.format_cost(cost as isize));
+            }
+        }
+
+        let cost = match problem.evaluate(&schedule) {
+            Ok(cost) => cost,
+            Err(reason) => {
+                println!("stitched cost none, the schedule is infeasible: {reason}");
+                return Ok(());
+            },
+        };
+        println!("stitched cost {}", problem.format_cost(cost));
+        if let Some(path) = self.output.as_ref() {
+            let solution = PspSolution {
+                cost: problem.unscale(cost),
+                decomposition: Some(decomposition(&problem, &schedule)),
+                kpis: Some(kpis(&problem, &schedule)),
+                schedule,
+                status: None,
+                bound: None,
+                gap: None,
+                fingerprint: Some(problem.fingerprint.clone()),
+            };
+            write_json(path, &solution)?;
+        }
+        Ok(())
+    }
+
+    /// Removes the units produced that serve no demand: the units demanded are matched to
+    /// the latest units of their item produced before their deadline. A period loses its
+    /// overtime unit first, and becomes idle once it produces nothing. Returns the number
+    /// of units dropped
+    fn drop_surplus(problem: &Psp, schedule: &mut [isize]) -> usize {
+        let mut surplus = vec![];
+        for item in 0..problem.n_items {
+            let mut productions = problem.productions(schedule, item).collect::<Vec<usize>>();
+            productions.reverse();
+            let mut k = 0;
+            for deadline in problem.deadlines(item).rev() {
+                // the productions after the deadline serve none of the remaining units
+                while productions.get(k).is_some_and(|t| *t > deadline) {
+                    surplus.push(productions[k]);
+                    k += 1;
+                }
+                k += 1;
+            }
+            surplus.extend(productions.iter().skip(k));
+        }
+        for t in surplus.iter().copied() {
+            let item = problem.item_of(schedule[t]);
+            schedule[t] = if problem.units_of(schedule[t]) > 1 { item } else { IDLE };
+        }
+        surplus.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use ddo::TimeBudget;
+    use serde_json::json;
+
+    use crate::instance::fixtures::{instance, parse, with};
+    use crate::resolution::exhaustive::exhaustive;
+    use super::*;
+
+    #[test]
+    fn the_stitched_schedule_serves_the_original_demands() {
+        let dir = std::env::temp_dir().join(format!("psp-split-{}", std::process::id()));
+        let path = |name: &str| dir.join(name).to_string_lossy().to_string();
+        std::fs::create_dir_all(&dir).unwrap();
+        // the last window has one unit more than periods, which the first one produces
+        let instance = parse(with(instance(), json!({"nb_periods": 6, "demands": [[0, 0, 2, 0, 1, 0], [0, 0, 0, 0, 0, 3]]})));
+        write_json(&path("instance.json"), &instance).unwrap();
+        Split { instance: path("instance.json"), window: 3, overlap: 0, output: path("parts") }.split().unwrap();
+
+        let manifest: SplitManifest = read_json(&path("parts/split.json")).unwrap();
+        assert_eq!(manifest.parts.len(), 2);
+        assert_eq!(manifest.parts[0].inventory.iter().sum::<usize>(), 1);
+        let budget = TimeBudget::new(Duration::from_secs(60));
+        let solutions = manifest.parts.iter().enumerate()
+            .map(|(k, part)| {
+                let problem = Psp::from_file(&part.instance).unwrap();
+                let (value, solution) = exhaustive(&problem, &budget).1.expect("the windows are feasible");
+                let solution = PspSolution { cost: problem.unscale(-value), schedule: problem.schedule(&solution), status: None, bound: None, gap: None,
+                    decomposition: None, fingerprint: None, kpis: None };
+                let file = path(&format!("solution-{k}.json"));
+                write_json(&file, &solution).unwrap();
+                file
+            })
+            .collect();
+        Stitch { instance: path("instance.json"), manifest: path("parts/split.json"), solutions, output: Some(path("stitched.json")) }.stitch().unwrap();
+
+        let problem = Psp::try_from(instance).unwrap();
+        let stitched: PspSolution = read_json(&path("stitched.json")).unwrap();
+        assert!((0..problem.n_items).all(|i| problem.is_item_feasible(&stitched.schedule, i)));
+        let cost = problem.evaluate(&stitched.schedule).unwrap();
+        assert_eq!(stitched.cost, problem.unscale(cost));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,116 @@
+//! This module implements a random search over the parameters of the solver. The 
+//! instances are grouped in classes of the same size, and each sampled configuration is 
+//! run on all the instances of a class. A configuration is scored on a class by the 
+//! average ratio between the cost it finds and the best cost found by any configuration.
+
+use std::{collections::BTreeMap, fs, time::{Duration, Instant}};
+
+use clap::Args;
+use rand::{Rng, SeedableRng, seq::SliceRandom};
+use rand_chacha::ChaChaRng;
+
+use crate::error::{PspError, read_json};
+use crate::instance::PspInstance;
+use crate::resolution::solve::Solve;
+
+#[derive(Debug, Args)]
+pub struct Tune {
+    /// The directory containing the instance files
+    #[clap(short, long)]
+    pub dir: String,
+    /// The total time budget of the search, in seconds
+    #[clap(short, long, default_value="600")]
+    pub budget: u64,
+    /// The timeout of each run, in seconds
+    #[clap(short, long, default_value="10")]
+    pub timeout: u64,
+    /// The seed of the random number generator used to sample the configurations
+    #[clap(long, default_value="0")]
+    pub seed: u64,
+}
+
+impl Tune {
+    pub fn tune(&self) -> Result<(), PspError> {
+        let io_error = |source| PspError::Io { path: self.dir.clone(), source };
+        let mut classes: BTreeMap<String, Vec<(String, usize)>> = BTreeMap::new();
+        for entry in fs::read_dir(&self.dir).map_err(io_error)? {
+            let path = entry.map_err(io_error)?.path();
+            if path.extension().map_or(true, |e| e != "json") {
+                continue;
+            }
+            let path = path.display().to_string();
+            let instance: PspInstance = read_json(&path)?;
+            let class = format!("{} items, {} periods", instance.nb_types, instance.nb_periods);
+            classes.entry(class).or_default().push((path, instance.nb_types));
+        }
+
+        let start = Instant::now();
+        let budget = Duration::from_secs(self.budget);
+        let mut rng = ChaChaRng::seed_from_u64(self.seed);
+
+        for (class, instances) in classes.iter() {
+            let class_budget = budget.saturating_sub(start.elapsed()) / classes.len() as u32;
+            let class_start = Instant::now();
+            let n_items = instances.iter().map(|i| i.1).min().unwrap_or(1);
+
+            // the costs found by each configuration on each instance of the class
+            let mut results: Vec<(Vec<String>, Vec<Option<isize>>)> = vec![];
+            while class_start.elapsed() + Duration::from_secs(self.timeout) * instances.len() as u32 <= class_budget || results.is_empty() {
+                let args = self.sample(n_items, &mut rng);
+                let costs = instances.iter()
+                    .map(|(path, _)| {
+                        let mut config = vec!["tune".to_string(), "-i".to_string(), path.clone(), "-t".to_string(), self.timeout.to_string()];
+                        config.extend(args.iter().cloned());
+                        let solve = Solve::from_args(config).expect("the sampled configurations are valid");
+                        Ok(solve.evaluate()?.1)
+                    })
+                    .collect::<Result<_, PspError>>()?;
+                results.push((args, costs));
+            }
+
+            let best = (0..instances.len())
+                .map(|i| results.iter().filter_map(|r| r.1[i]).min())
+                .collect::<Vec<Option<isize>>>();
+            let score = |costs: &[Option<isize>]| costs.iter().zip(best.iter())
+                .map(|(c, b)| match (c, b) {
+                    (Some(c), Some(b)) => (*c as f64 + 1.0) / (*b as f64 + 1.0),
+                    (None, Some(_)) => f64::INFINITY,
+                    _ => 1.0,
+                })
+                .sum::<f64>() / costs.len().max(1) as f64;
+
+            if let Some((args, costs)) = results.iter().min_by(|a, b| score(&a.1).total_cmp(&score(&b.1))) {
+                println!("class {class}: {} configurations tried", results.len());
+                println!("  best configuration {}", args.join(" "));
+                println!("  average ratio to the best costs {:.4}", score(costs));
+            }
+        }
+        Ok(())
+    }
+
+    /// Samples the arguments of a random configuration
+    fn sample(&self, n_items: usize, rng: &mut impl Rng) -> Vec<String> {
+        let mut args = vec![];
+        let solver = ["ddo", "lns", "sa", "localsearch"].choose(rng).copied().unwrap_or("ddo");
+        args.extend(["--solver".to_string(), solver.to_string()]);
+
+        let width = [10, 50, 100, 500, 1000].choose(rng).copied().unwrap_or(100);
+        args.extend(["-w".to_string(), width.to_string()]);
+
+        if solver == "ddo" || solver == "lns" {
+            if rng.gen_bool(0.5) {
+                args.push("--compression-bound".to_string());
+                let n_meta_items = rng.gen_range(1..=n_items.clamp(1, 10));
+                let method = ["kmeans", "kmedoids", "agglomerative"].choose(rng).copied().unwrap_or("kmeans");
+                args.extend(["--n-meta-items".to_string(), n_meta_items.to_string(), "--compression-method".to_string(), method.to_string()]);
+            }
+            if rng.gen_bool(0.5) {
+                args.push("--lagrangian".to_string());
+            }
+        }
+        if solver == "ddo" && rng.gen_bool(0.5) {
+            args.push("--warm-start".to_string());
+        }
+        args
+    }
+}
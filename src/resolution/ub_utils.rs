@@ -20,20 +20,19 @@
 use smallbitset::Set32;
 
 /// returns the cost the minimum spanning trees for all subset of items
-pub fn all_mst(changeover: &Vec<Vec<usize>>) -> Vec<usize> {
-    let n_items = changeover.len() as u8;
-
+pub fn all_mst(n_items: usize, changeover: &[usize]) -> Vec<usize> {
     let n_poss = 2_u32.pow(n_items as u32);
     let mut ret = vec![];
     for i in 0..n_poss {
         let bs = Set32::from(i);
-        ret.push(mst(bs, changeover));
+        ret.push(mst(bs, n_items, changeover));
     }
     ret
 }
 
 /// minimum spanning tree
-pub fn mst(members: Set32, changeover: &[Vec<usize>]) -> usize {
+/// (the changeover costs are given in row-major order)
+pub fn mst(members: Set32, n_items: usize, changeover: &[usize]) -> usize {
     if members.len() <= 1 {
         0
     } else {
@@ -49,8 +48,8 @@ pub fn mst(members: Set32, changeover: &[Vec<usize>]) -> usize {
                 if a == b {
                     continue;
                 }
-                let edge = changeover[a as usize][b as usize];
-                let edge = edge.min(changeover[b as usize][a as usize]);
+                let edge = changeover[a as usize * n_items + b as usize];
+                let edge = edge.min(changeover[b as usize * n_items + a as usize]);
                 if edge < emin {
                     emin = edge;
                     bmin = b;
@@ -0,0 +1,54 @@
+use clap::Args;
+
+use crate::constraints::SideConstraints;
+use crate::error::{PspError, read_json};
+use crate::instance::IdleSetup;
+use crate::resolution::model::Psp;
+use crate::solution::PspSolution;
+
+#[derive(Debug, Args)]
+pub struct Validate {
+    /// The path to the instance file (- for the standard input)
+    #[clap(short, long)]
+    pub instance: String,
+    /// If present, the path to a solution file whose feasibility and cost are checked
+    /// (- for the standard input)
+    pub solution: Option<String>,
+    /// Whether the setup is kept through idle periods, overriding the instance
+    #[clap(long, value_enum)]
+    pub idle_setup: Option<IdleSetup>,
+    /// If present, the path to a json file of side constraints that the solution must
+    /// respect as well
+    #[clap(long)]
+    pub constraints: Option<String>,
+}
+
+impl Validate {
+    /// Checks that the instance can be modeled and that the solution, if any, is a feasible
+    /// schedule of the given cost
+    pub fn validate(&self) -> Result<(), PspError> {
+        let mut problem = Psp::from_file(&self.instance)?;
+        if let Some(idle_setup) = self.idle_setup {
+            problem.idle_setup = idle_setup;
+        }
+        if let Some(path) = self.constraints.as_ref() {
+            let constraints: SideConstraints = read_json(path)?;
+            problem.constrain(&constraints)?;
+        }
+        println!("valid instance: {} items, {} periods", problem.n_items, problem.horizon);
+
+        let Some(path) = self.solution.as_ref() else {
+            return Ok(());
+        };
+        let solution: PspSolution = read_json(path)?;
+        let cost = problem.evaluate(&solution.schedule)
+            .map_err(|reason| PspError::Invalid(format!("{path}: the schedule is infeasible: {reason}")))?;
+
+        let cost = problem.unscale(cost);
+        if (cost - solution.cost).abs() > 1e-6 * cost.abs().max(1.0) {
+            return Err(PspError::Invalid(format!("{path}: the schedule costs {cost}, not {}", solution.cost)));
+        }
+        println!("valid solution: cost {cost}");
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+//! This module records the nodes of the decision diagrams compiled from the root, 
+//! and draws them in the graphviz dot format.
+
+use std::fmt::Write;
+
+use clap::Args;
+use ddo::Problem;
+
+use crate::resolution::model::{Psp, PspState};
+
+/// The status of a node of a recorded decision diagram
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    /// a node whose state and value are exact
+    Exact,
+    /// a node that descends from a merged node
+    Relaxed,
+    /// a node obtained by merging other nodes
+    Merged,
+    /// a node that was removed from the diagram: dropped from a restricted DD, or merged 
+    /// into another node in a relaxed DD
+    Deleted,
+}
+
+/// An arc from a node of the previous layer
+#[derive(Debug, Clone, Copy)]
+pub struct Edge {
+    /// The index of the parent in the previous layer
+    pub parent: usize,
+    pub decision: isize,
+    pub cost: isize,
+}
+
+/// A node of a recorded decision diagram
+#[derive(Debug, Clone)]
+pub struct VizNode {
+    pub state: PspState,
+    pub value: isize,
+    pub kind: NodeKind,
+    pub parents: Vec<Edge>,
+    /// The index of the node this one was merged into, if any
+    pub merged_into: Option<usize>,
+}
+
+impl VizNode {
+    /// Returns the node of the root of the problem
+    pub fn root(problem: &Psp) -> Self {
+        VizNode {
+            state: problem.initial_state(),
+            value: problem.initial_value(),
+            kind: NodeKind::Exact,
+            parents: vec![],
+            merged_into: None,
+        }
+    }
+}
+
+/// The layers of a decision diagram, from the root to the terminal nodes
+#[derive(Debug, Clone)]
+pub struct Diagram {
+    pub layers: Vec<Vec<VizNode>>,
+}
+
+/// The options of the drawing of a decision diagram
+#[derive(Debug, Clone, Args)]
+pub struct VizConfig {
+    /// If present, the nodes removed from the drawn DD are shown
+    #[clap(long)]
+    pub show_deleted: bool,
+    /// If present, the nodes merged together are grouped with the resulting node in the drawing
+    #[clap(long)]
+    pub group_merged: bool,
+    /// The maximum number of nodes drawn
+    #[clap(long, default_value="1000")]
+    pub max_nodes: usize,
+}
+
+impl Diagram {
+    /// Returns the value of the best terminal node
+    pub fn best_value(&self) -> Option<isize> {
+        self.layers.last()?.iter()
+            .filter(|n| n.kind != NodeKind::Deleted)
+            .map(|n| n.value)
+            .max()
+    }
+
+    /// Returns the drawing of the diagram in the graphviz dot format
+    pub fn to_dot(&self, problem: &Psp, config: &VizConfig) -> String {
+        let mut out = String::new();
+        let mut drawn: Vec<Vec<bool>> = vec![vec![]; self.layers.len()];
+        let mut count = 0;
+
+        writeln!(out, "digraph {{").unwrap();
+        writeln!(out, "  ranksep = 1;").unwrap();
+        for (l, layer) in self.layers.iter().enumerate() {
+            drawn[l] = vec![false; layer.len()];
+            for (i, node) in layer.iter().enumerate() {
+                if count >= config.max_nodes || (node.kind == NodeKind::Deleted && !config.show_deleted) {
+                    continue;
+                }
+                drawn[l][i] = true;
+                count += 1;
+            }
+
+            for (i, node) in layer.iter().enumerate().filter(|(i, _)| drawn[l][*i]) {
+                if config.group_merged && node.kind == NodeKind::Merged {
+                    writeln!(out, "  subgraph cluster_{l}_{i} {{").unwrap();
+                    writeln!(out, "    style = dashed;").unwrap();
+                    writeln!(out, "    n{l}_{i};").unwrap();
+                    for (j, _) in layer.iter().enumerate().filter(|(j, n)| drawn[l][*j] && n.merged_into == Some(i)) {
+                        writeln!(out, "    n{l}_{j};").unwrap();
+                    }
+                    writeln!(out, "  }}").unwrap();
+                }
+
+                let style = match node.kind {
+                    NodeKind::Exact => "",
+                    NodeKind::Relaxed => ", color=orange",
+                    NodeKind::Merged => ", color=red, style=bold",
+                    NodeKind::Deleted => ", color=gray, style=dashed",
+                };
+                writeln!(out, "  n{l}_{i} [label=\"{}\\ncost {}\"{style}];", Self::label(&node.state), problem.format_cost(-node.value)).unwrap();
+
+                for edge in node.parents.iter().filter(|e| l > 0 && drawn[l - 1][e.parent]) {
+                    writeln!(out, "  n{}_{} -> n{l}_{i} [label=\"{} ({})\"];", l - 1, edge.parent, edge.decision, problem.format_cost(-edge.cost)).unwrap();
+                }
+            }
+        }
+        writeln!(out, "}}").unwrap();
+
+        out
+    }
+
+    /// Returns, for each layer, the number of nodes kept, merged and deleted, and the costs
+    /// of its best and worst kept nodes, in the csv format
+    pub fn layer_stats(&self, problem: &Psp) -> String {
+        let mut out = String::from("layer,nodes,merged,deleted,best,worst\n");
+        for (l, layer) in self.layers.iter().enumerate() {
+            let count = |kind: NodeKind| layer.iter().filter(|n| n.kind == kind).count();
+            let values = layer.iter().filter(|n| n.kind != NodeKind::Deleted).map(|n| n.value);
+            let best = values.clone().max().map_or(String::new(), |v| problem.format_cost(-v));
+            let worst = values.min().map_or(String::new(), |v| problem.format_cost(-v));
+            let kept = layer.len() - count(NodeKind::Deleted);
+            writeln!(out, "{l},{kept},{},{},{best},{worst}", count(NodeKind::Merged), count(NodeKind::Deleted)).unwrap();
+        }
+        out
+    }
+
+    fn label(state: &PspState) -> String {
+        format!("t {} next {}\\nprev {:?}", state.time, state.next, state.prev_demands)
+    }
+}
@@ -0,0 +1,181 @@
+//! This module implements the whatif subcommand, which measures the sensitivity of a
+//! schedule to changes of the demands: some demands are moved to other periods, and the
+//! schedule is evaluated again on the modified instance. The modified instance can also
+//! be re-optimized quickly, to tell how much of the cost increase a new schedule recovers.
+
+use std::str::FromStr;
+
+use clap::Args;
+
+use crate::error::{PspError, read_json};
+use crate::instance::PspInstance;
+use crate::resolution::model::Psp;
+use crate::resolution::solve::{Resolution, Solve};
+use crate::solution::PspSolution;
+
+#[derive(Debug, Args)]
+pub struct Whatif {
+    /// The path to the instance file (- for the standard input)
+    #[clap(short, long)]
+    pub instance: String,
+    /// The path to the solution file whose schedule is evaluated
+    #[clap(short, long)]
+    pub solution: String,
+    /// A demand that is moved, as item,period,delta: the demand of the item due at the
+    /// period becomes due delta periods later, or earlier if delta is negative. It can
+    /// be repeated to move several demands at once
+    #[clap(long, required = true, allow_hyphen_values = true)]
+    pub shift: Vec<DemandShift>,
+    /// If present, the modified instance is re-optimized
+    #[clap(long)]
+    pub reoptimize: bool,
+    /// The options of the solve subcommand used to re-optimize, e.g. "-t 10 -w 100"
+    #[clap(long, default_value = "-t 10", allow_hyphen_values = true)]
+    pub options: String,
+}
+
+/// The move of the demand of an item from a period to another
+#[derive(Debug, Clone, Copy)]
+pub struct DemandShift {
+    pub item: usize,
+    pub period: usize,
+    pub delta: isize,
+}
+
+impl FromStr for DemandShift {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields = s.split(',').map(|f| f.trim()).collect::<Vec<&str>>();
+        let [item, period, delta] = fields[..] else {
+            return Err(format!("expected item,period,delta, got {s}"));
+        };
+        Ok(Self {
+            item: item.parse().map_err(|e| format!("invalid item {item}: {e}"))?,
+            period: period.parse().map_err(|e| format!("invalid period {period}: {e}"))?,
+            delta: delta.parse().map_err(|e| format!("invalid delta {delta}: {e}"))?,
+        })
+    }
+}
+
+impl DemandShift {
+    /// Moves the demand in the given instance. The service window of the demand is moved
+    /// with it, and the precedences of the demand follow it. The demand is merged with
+    /// the one of the item that is due at its new period, if any
+    fn apply(&self, instance: &mut PspInstance) -> Result<(), PspError> {
+        let mut lists = instance.demand_lists();
+        let Some(demands) = lists.get_mut(self.item) else {
+            return Err(PspError::Invalid(format!("there is no item {}", self.item)));
+        };
+        let Some(k) = demands.iter().position(|d| d.period == self.period) else {
+            return Err(PspError::Invalid(format!("item {} has no demand due at period {}", self.item, self.period)));
+        };
+        let Some(target) = self.period.checked_add_signed(self.delta).filter(|p| *p < instance.nb_periods) else {
+            return Err(PspError::Invalid(format!("the demand of item {} at period {} cannot be moved out of the horizon", self.item, self.period)));
+        };
+
+        let mut demand = demands.remove(k);
+        demand.period = target;
+        demand.earliest = demand.earliest.map(|e| e.checked_add_signed(self.delta).unwrap_or(0).min(target));
+        match demands.iter_mut().find(|d| d.period == target) {
+            Some(d) => {
+                d.quantity += demand.quantity;
+                d.earliest = match (d.earliest, demand.earliest) {
+                    (None, None) => None,
+                    (a, b) => Some(a.unwrap_or(target).min(b.unwrap_or(target))),
+                };
+            },
+            None => {
+                demands.push(demand);
+                demands.sort_by_key(|d| d.period);
+            },
+        }
+
+        for precedence in instance.precedences.iter_mut().flatten() {
+            for demand in [&mut precedence.before, &mut precedence.after] {
+                if demand.item == self.item && demand.period == self.period {
+                    demand.period = target;
+                }
+            }
+        }
+        instance.sparse_demands = Some(lists);
+        instance.demands = vec![];
+        instance.earliest = None;
+        Ok(())
+    }
+}
+
+impl Whatif {
+    /// Reports the cost of the schedule before and after the demands are moved, and the
+    /// cost of the re-optimized schedule if requested
+    pub fn whatif(&self) -> Result<(), PspError> {
+        let instance: PspInstance = read_json(&self.instance)?;
+        let solution: PspSolution = read_json(&self.solution)?;
+        let problem = Psp::try_from(instance.clone())?;
+        let before = problem.evaluate(&solution.schedule)
+            .map_err(|reason| PspError::Invalid(format!("{}: the schedule is infeasible: {reason}", self.solution)))?;
+        println!("original cost {}", problem.format_cost(before));
+
+        let modified = self.shifted(instance)?;
+        match modified.evaluate(&solution.schedule) {
+            Ok(after) => println!("shifted cost {}, delta {}", modified.format_cost(after), modified.format_cost(after - before)),
+            Err(reason) => println!("shifted cost none, the schedule is infeasible: {reason}"),
+        }
+
+        if self.reoptimize {
+            let resolution = self.reoptimized(&modified)?;
+            let status = resolution.status().name();
+            match resolution.incumbent.as_ref() {
+                Some((value, _)) => println!("reoptimized cost {} ({status}), delta {}",
+                    modified.format_cost(-value), modified.format_cost(-value - before)),
+                None => println!("reoptimized cost none ({status})"),
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the model of the given instance whose demands are moved
+    fn shifted(&self, mut instance: PspInstance) -> Result<Psp, PspError> {
+        for shift in self.shift.iter() {
+            shift.apply(&mut instance)?;
+        }
+        Psp::try_from(instance)
+    }
+
+    /// Re-optimizes the modified problem with the options of the solve subcommand
+    fn reoptimized(&self, modified: &Psp) -> Result<Resolution, PspError> {
+        let mut args = vec!["whatif".to_string(), "-i".to_string(), self.instance.clone()];
+        args.extend(self.options.split_whitespace().map(|a| a.to_string()));
+        let solve = Solve::from_args(args).map_err(|e| PspError::Invalid(format!("invalid solve options: {e}")))?;
+        solve.solve_problem(modified, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::instance::fixtures::{instance, parse};
+    use crate::resolution::model::IDLE;
+    use super::*;
+
+    #[test]
+    fn the_reoptimized_schedule_serves_the_shifted_demands() {
+        // the demand of the first item at period 3 joins the one at period 1
+        let whatif = Whatif {
+            instance: "instance.json".to_string(),
+            solution: "solution.json".to_string(),
+            shift: vec![DemandShift { item: 0, period: 3, delta: -2 }],
+            reoptimize: true,
+            options: "-t 10".to_string(),
+        };
+        let modified = whatif.shifted(parse(instance())).unwrap();
+        assert_eq!(modified.demand(0, 1), 2);
+        assert_eq!(modified.cost(&[1, 0, IDLE, 0]), None);
+
+        let resolution = whatif.reoptimized(&modified).unwrap();
+        let (value, solution) = resolution.incumbent.expect("the shifted instance is feasible");
+        let schedule = modified.schedule(&solution);
+        assert!((0..modified.n_items).all(|i| modified.is_item_feasible(&schedule, i)));
+        assert_eq!(modified.evaluate(&schedule), Ok(-value));
+        assert_eq!(-value, 11);
+    }
+}
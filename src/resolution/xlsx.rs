@@ -0,0 +1,71 @@
+//! This module writes a schedule to an Excel workbook, with a summary of its costs, the
+//! item produced at each period and the inventory of each item at the end of each period.
+
+use rust_xlsxwriter::{Format, Workbook, Worksheet, XlsxError};
+
+use crate::error::{PspError, write_file};
+use crate::resolution::model::{Psp, IDLE};
+use crate::resolution::report::breakdown;
+
+/// Writes the workbook of the given schedule to the given file
+pub fn write_xlsx(problem: &Psp, schedule: &[isize], path: &str) -> Result<(), PspError> {
+    let workbook = workbook(problem, schedule).map_err(|source| PspError::Xlsx { path: path.to_string(), source })?;
+    write_file(path, workbook)
+}
+
+fn workbook(problem: &Psp, schedule: &[isize]) -> Result<Vec<u8>, XlsxError> {
+    let periods = breakdown(problem, schedule);
+    let bold = Format::new().set_bold();
+    let mut workbook = Workbook::new();
+
+    let changeover = periods.iter().map(|p| p.changeover).sum::<isize>();
+    let stocking = periods.iter().map(|p| p.stocking).sum::<isize>();
+    let overtime = periods.iter().map(|p| p.overtime).sum::<isize>();
+    let idle = periods.iter().map(|p| p.idle).sum::<isize>();
+    let mut summary = vec![("Cost", changeover + stocking + overtime + idle), ("Changeover", changeover), ("Stocking", stocking)];
+    if problem.overtime.is_some() {
+        summary.push(("Overtime", overtime));
+    }
+    if problem.idle > 0 {
+        summary.push(("Idle", idle));
+    }
+    let sheet = workbook.add_worksheet().set_name("Summary")?;
+    for (row, (name, value)) in summary.iter().enumerate() {
+        sheet.write_string_with_format(row as u32, 0, *name, &bold)?;
+        sheet.write_number(row as u32, 1, problem.unscale(*value))?;
+    }
+
+    let sheet = workbook.add_worksheet().set_name("Schedule")?;
+    header(sheet, &["Period", "Item", "Changeover", "Stocking"], &bold)?;
+    for (t, period) in periods.iter().enumerate() {
+        let row = t as u32 + 1;
+        sheet.write_number(row, 0, t as f64)?;
+        if period.item == IDLE {
+            sheet.write_string(row, 1, "idle")?;
+        } else {
+            sheet.write_number(row, 1, period.item as f64)?;
+        }
+        sheet.write_number(row, 2, problem.unscale(period.changeover))?;
+        sheet.write_number(row, 3, problem.unscale(period.stocking))?;
+    }
+
+    let sheet = workbook.add_worksheet().set_name("Inventory")?;
+    let items = (0..problem.n_items).map(|i| format!("Item {i}")).collect::<Vec<String>>();
+    header(sheet, &std::iter::once("Period").chain(items.iter().map(|i| i.as_str())).collect::<Vec<&str>>(), &bold)?;
+    for (t, period) in periods.iter().enumerate() {
+        let row = t as u32 + 1;
+        sheet.write_number(row, 0, t as f64)?;
+        for (i, inv) in period.inventory.iter().enumerate() {
+            sheet.write_number(row, i as u16 + 1, *inv as f64)?;
+        }
+    }
+
+    workbook.save_to_buffer()
+}
+
+fn header(sheet: &mut Worksheet, names: &[&str], format: &Format) -> Result<(), XlsxError> {
+    for (col, name) in names.iter().enumerate() {
+        sheet.write_string_with_format(0, col as u16, *name, format)?;
+    }
+    Ok(())
+}
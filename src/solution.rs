@@ -0,0 +1,132 @@
+//! This module defines the representation of a solution of a PSP instance, as it is
+//! written to and read from solution files.
+
+use serde::{Serialize, Deserialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PspSolution {
+    /// The cost of the schedule, in the units of the instance
+    pub cost: f64,
+    /// The item produced at each period (-1 when the machine is idle), plus the number of
+    /// items when a second unit is produced in overtime
+    pub schedule: Vec<isize>,
+    /// The outcome of the resolution that found the schedule, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<SolveStatus>,
+    /// The best lower bound proved on the cost of any schedule, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bound: Option<f64>,
+    /// The relative gap between the cost and the bound, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gap: Option<f64>,
+    /// The changeover and stocking parts of the cost, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decomposition: Option<CostDecomposition>,
+    /// The fingerprint of the instance that the schedule solves, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
+    /// The indicators of the schedule for the planners, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kpis: Option<Kpis>,
+}
+
+/// The indicators of a schedule that the planners look at besides its cost
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Kpis {
+    /// The number of times the machine is set up for an item, including the first one
+    pub setups: usize,
+    /// The fraction of the periods where an item is produced
+    pub utilization: f64,
+    pub items: Vec<ItemKpis>,
+}
+
+/// The indicators of a schedule for an item
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemKpis {
+    /// The number of units produced
+    pub produced: usize,
+    /// The number of runs of consecutive productions of the item
+    pub campaigns: usize,
+    /// The number of periods of the longest campaign
+    pub longest_campaign: usize,
+    /// The average number of units in stock at the end of the periods
+    pub average_inventory: f64,
+    pub max_inventory: usize,
+}
+
+/// A decision of a schedule that is already committed, as read from the files given to
+/// the --fix option of solve
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Assignment {
+    pub period: usize,
+    /// The item produced at the period (-1 when the machine is idle)
+    pub item: isize,
+}
+
+/// The changeover and stocking costs of a schedule, in total and for each item
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CostDecomposition {
+    pub changeover: f64,
+    pub stocking: f64,
+    /// The surcharges of the units produced in overtime, when overtime is allowed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overtime: Option<f64>,
+    /// The costs of the available periods where the machine is idle, when idling is penalized
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle: Option<f64>,
+    pub items: Vec<ItemCosts>,
+}
+
+/// The costs of a schedule due to an item: the changeovers to the item, and its inventory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemCosts {
+    pub changeover: f64,
+    pub stocking: f64,
+}
+
+/// The outcome of the resolution of an instance, which is also given by the exit code of
+/// the solve subcommand
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SolveStatus {
+    /// A solution was found and proved optimal (exit code 0)
+    Optimal,
+    /// A solution was found but its optimality was not proved in time (exit code 3)
+    Feasible,
+    /// The instance was proved to have no solution (exit code 4)
+    Infeasible,
+    /// No solution was found in time, and none was proved to exist (exit code 5)
+    Unknown,
+}
+
+impl SolveStatus {
+    /// The exit code of the errors other than infeasibility
+    pub const ERROR_EXIT_CODE: u8 = 1;
+
+    pub fn new(is_exact: bool, has_solution: bool) -> Self {
+        match (is_exact, has_solution) {
+            (true, true) => SolveStatus::Optimal,
+            (false, true) => SolveStatus::Feasible,
+            (true, false) => SolveStatus::Infeasible,
+            (false, false) => SolveStatus::Unknown,
+        }
+    }
+
+    pub fn exit_code(self) -> u8 {
+        match self {
+            SolveStatus::Optimal => 0,
+            SolveStatus::Feasible => 3,
+            SolveStatus::Infeasible => 4,
+            SolveStatus::Unknown => 5,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SolveStatus::Optimal => "optimal",
+            SolveStatus::Feasible => "feasible",
+            SolveStatus::Infeasible => "infeasible",
+            SolveStatus::Unknown => "unknown",
+        }
+    }
+}